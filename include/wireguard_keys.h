@@ -0,0 +1,133 @@
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#ifndef WIREGUARD_KEYS_H
+#define WIREGUARD_KEYS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Length of keys in bytes.
+ */
+#define WG_KEY_LEN 32
+
+/**
+ * Size of a buffer which holds a key in any encoding, including the terminating NUL.
+ */
+#define WG_ENCODED_MAX_LEN 96
+
+/**
+ * Result of the functions of the C interface. The values are stable, new ones are only ever
+ * appended.
+ */
+typedef enum WgStatus {
+  /**
+   * Success
+   */
+  WG_STATUS_OK = 0,
+  /**
+   * A pointer argument is null
+   */
+  WG_STATUS_NULL_POINTER = 1,
+  /**
+   * String is not a key in any enabled encoding, or not valid UTF-8
+   */
+  WG_STATUS_PARSE = 2,
+  /**
+   * String is a valid key in several encodings, with different results
+   */
+  WG_STATUS_AMBIGUOUS = 3,
+  /**
+   * Encoding name is unknown, or the encoding is not enabled
+   */
+  WG_STATUS_ENCODING = 4,
+  /**
+   * Output buffer is too small for the encoded key
+   */
+  WG_STATUS_BUFFER_TOO_SMALL = 5,
+  /**
+   * Public key is of low order, so the shared secret is all zeroes
+   */
+  WG_STATUS_LOW_ORDER = 6,
+} WgStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Describe the status code. The string is static and must not be freed. Takes an integer
+ * rather than a [WgStatus], as C allows passing values which are not valid variants.
+ */
+const char *wg_status_message(int status);
+
+/**
+ * Generate a new private key into `privkey`.
+ *
+ * # Safety
+ *
+ * `privkey` has to be null or valid for writing [WG_KEY_LEN] bytes.
+ */
+enum WgStatus wg_generate_privkey(uint8_t *privkey);
+
+/**
+ * Generate a new preshared key into `secret`.
+ *
+ * # Safety
+ *
+ * `secret` has to be null or valid for writing [WG_KEY_LEN] bytes.
+ */
+enum WgStatus wg_generate_psk(uint8_t *secret);
+
+/**
+ * Derive the public key of `privkey` into `pubkey`.
+ *
+ * # Safety
+ *
+ * `privkey` has to be null or valid for reading [WG_KEY_LEN] bytes, and `pubkey` null or valid
+ * for writing [WG_KEY_LEN] bytes.
+ */
+enum WgStatus wg_derive_pubkey(const uint8_t *privkey, uint8_t *pubkey);
+
+/**
+ * Compute the x25519 shared secret of `privkey` and `pubkey` into `shared`, see
+ * [Privkey::diffie_hellman].
+ *
+ * # Safety
+ *
+ * `privkey` and `pubkey` have to be null or valid for reading [WG_KEY_LEN] bytes, and `shared`
+ * null or valid for writing [WG_KEY_LEN] bytes.
+ */
+enum WgStatus wg_dh(const uint8_t *privkey, const uint8_t *pubkey, uint8_t *shared);
+
+/**
+ * Parse a key of any type from the NUL-terminated `string` into `key`, detecting the encoding
+ * like [Pubkey::parse].
+ *
+ * # Safety
+ *
+ * `string` has to be null or a valid NUL-terminated string, and `key` null or valid for
+ * writing [WG_KEY_LEN] bytes.
+ */
+enum WgStatus wg_parse_key(const char *string, uint8_t *key);
+
+/**
+ * Encode `key` as NUL-terminated string into `buffer` of `length` bytes. The encoding is given
+ * by name, such as `base64` or `hex`, see [Encoding::from_name]. A buffer of
+ * [WG_ENCODED_MAX_LEN] bytes is large enough for every encoding.
+ *
+ * # Safety
+ *
+ * `key` has to be null or valid for reading [WG_KEY_LEN] bytes, `encoding` null or a valid
+ * NUL-terminated string, and `buffer` null or valid for writing `length` bytes.
+ */
+enum WgStatus wg_encode_key(const uint8_t *key, const char *encoding, char *buffer, size_t length);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WIREGUARD_KEYS_H */
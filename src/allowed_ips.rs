@@ -0,0 +1,380 @@
+//! Typed representation of IP networks, as used for the `Address` and `AllowedIPs` settings of
+//! WireGuard. A [Cidr] is an IPv4 or IPv6 address with a prefix length, and [AllowedIps] is a
+//! list of them, parsed from and written as comma-separated WireGuard syntax.
+//!
+//! A [Cidr] keeps the address it was created with, because interface addresses carry the host
+//! part. Use [Cidr::network] or [AllowedIps::normalize] to clear the host bits.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Possible errors that can be generated when parsing networks.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CidrError {
+    /// Address is not a valid IPv4 or IPv6 address
+    #[error("invalid address")]
+    Address,
+    /// Prefix length is not a number or too long for the address family
+    #[error("invalid prefix length")]
+    Prefix,
+}
+
+/// IPv4 or IPv6 network in CIDR notation, such as `10.0.0.0/24`.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Create network from address and prefix length.
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, CidrError> {
+        if prefix > Self::max_prefix(&addr) {
+            return Err(CidrError::Prefix);
+        }
+        Ok(Cidr { addr, prefix })
+    }
+
+    /// Create network containing only the given address.
+    pub fn host(addr: IpAddr) -> Self {
+        Cidr {
+            addr,
+            prefix: Self::max_prefix(&addr),
+        }
+    }
+
+    fn max_prefix(addr: &IpAddr) -> u8 {
+        match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    /// Address, including host bits.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Prefix length.
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Determine if this is an IPv4 network.
+    pub fn is_ipv4(&self) -> bool {
+        self.addr.is_ipv4()
+    }
+
+    /// Determine if this is an IPv6 network.
+    pub fn is_ipv6(&self) -> bool {
+        self.addr.is_ipv6()
+    }
+
+    /// Address with the host bits cleared, masked to the given prefix length.
+    fn masked(addr: IpAddr, prefix: u8) -> IpAddr {
+        match addr {
+            IpAddr::V4(addr) => {
+                let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+                IpAddr::V4(Ipv4Addr::from(u32::from(addr) & mask))
+            }
+            IpAddr::V6(addr) => {
+                let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(u128::from(addr) & mask))
+            }
+        }
+    }
+
+    /// Network with the host bits cleared.
+    pub fn network(&self) -> Self {
+        Cidr {
+            addr: Self::masked(self.addr, self.prefix),
+            prefix: self.prefix,
+        }
+    }
+
+    /// Determine if the address is part of this network.
+    pub fn contains_addr(&self, addr: &IpAddr) -> bool {
+        addr.is_ipv4() == self.addr.is_ipv4()
+            && Self::masked(*addr, self.prefix) == Self::masked(self.addr, self.prefix)
+    }
+
+    /// Determine if the other network is entirely part of this network.
+    pub fn contains(&self, other: &Cidr) -> bool {
+        self.prefix <= other.prefix && self.contains_addr(&other.addr)
+    }
+
+    /// Determine if the networks have any address in common.
+    pub fn overlaps(&self, other: &Cidr) -> bool {
+        self.contains(other) || other.contains(self)
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    fn from(addr: IpAddr) -> Self {
+        Cidr::host(addr)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        match value.split_once('/') {
+            Some((addr, prefix)) => {
+                let addr = addr.trim().parse().map_err(|_| CidrError::Address)?;
+                let prefix = prefix.trim().parse().map_err(|_| CidrError::Prefix)?;
+                Cidr::new(addr, prefix)
+            }
+            None => Ok(Cidr::host(value.parse().map_err(|_| CidrError::Address)?)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Cidr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// List of networks a peer is allowed to send from, and which are routed to it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct AllowedIps(Vec<Cidr>);
+
+impl AllowedIps {
+    /// Create empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a network.
+    pub fn push(&mut self, cidr: Cidr) {
+        self.0.push(cidr);
+    }
+
+    /// Iterate over networks.
+    pub fn iter(&self) -> std::slice::Iter<'_, Cidr> {
+        self.0.iter()
+    }
+
+    /// Number of networks.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Determine if the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Normalized list: host bits are cleared, networks are sorted, and networks which are
+    /// contained in other networks of the list are removed.
+    pub fn normalize(&self) -> Self {
+        let mut networks: Vec<Cidr> = self.0.iter().map(Cidr::network).collect();
+        networks.sort();
+        let mut normalized: Vec<Cidr> = Vec::with_capacity(networks.len());
+        for network in networks {
+            match normalized.last() {
+                Some(last) if last.contains(&network) => {}
+                _ => normalized.push(network),
+            }
+        }
+        AllowedIps(normalized)
+    }
+
+    /// Determine if the address is part of any network in this list.
+    pub fn contains_addr(&self, addr: &IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains_addr(addr))
+    }
+
+    /// Determine if the network is entirely part of a network in this list.
+    pub fn contains(&self, other: &Cidr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(other))
+    }
+
+    /// Pairs of overlapping networks between this list and another.
+    pub fn overlapping<'a>(
+        &'a self,
+        other: &'a AllowedIps,
+    ) -> impl Iterator<Item = (Cidr, Cidr)> + 'a {
+        self.0.iter().flat_map(move |a| {
+            other
+                .0
+                .iter()
+                .filter(move |b| a.overlaps(b))
+                .map(move |b| (*a, *b))
+        })
+    }
+
+    /// Determine if any network of this list overlaps with any network of another.
+    pub fn overlaps(&self, other: &AllowedIps) -> bool {
+        self.overlapping(other).next().is_some()
+    }
+}
+
+impl From<Vec<Cidr>> for AllowedIps {
+    fn from(networks: Vec<Cidr>) -> Self {
+        AllowedIps(networks)
+    }
+}
+
+impl FromIterator<Cidr> for AllowedIps {
+    fn from_iter<I: IntoIterator<Item = Cidr>>(iter: I) -> Self {
+        AllowedIps(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Cidr> for AllowedIps {
+    fn extend<I: IntoIterator<Item = Cidr>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for AllowedIps {
+    type Item = Cidr;
+    type IntoIter = std::vec::IntoIter<Cidr>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AllowedIps {
+    type Item = &'a Cidr;
+    type IntoIter = std::slice::Iter<'a, Cidr>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl std::ops::Deref for AllowedIps {
+    type Target = [Cidr];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for AllowedIps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, cidr) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", cidr)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AllowedIps {
+    type Err = CidrError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(Cidr::from_str)
+            .collect()
+    }
+}
+
+#[test]
+fn test_cidr_parse() {
+    let cidr: Cidr = "10.0.0.1/24".parse().unwrap();
+    assert_eq!(cidr.addr(), "10.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(cidr.prefix(), 24);
+    assert_eq!(cidr.to_string(), "10.0.0.1/24");
+    assert_eq!(cidr.network().to_string(), "10.0.0.0/24");
+    assert_eq!(
+        "fd00::1".parse::<Cidr>().unwrap().to_string(),
+        "fd00::1/128"
+    );
+    assert_eq!("0.0.0.0/0".parse::<Cidr>().unwrap().network().prefix(), 0);
+    assert_eq!("10.0.0.1/33".parse::<Cidr>(), Err(CidrError::Prefix));
+    assert_eq!("10.0.0.1/x".parse::<Cidr>(), Err(CidrError::Prefix));
+    assert_eq!("10.0.0/8".parse::<Cidr>(), Err(CidrError::Address));
+}
+
+#[test]
+fn test_cidr_contains() {
+    let network: Cidr = "10.0.0.0/8".parse().unwrap();
+    assert!(network.contains_addr(&"10.1.2.3".parse().unwrap()));
+    assert!(!network.contains_addr(&"11.0.0.1".parse().unwrap()));
+    assert!(!network.contains_addr(&"::a00:1".parse().unwrap()));
+    assert!(network.contains(&"10.1.0.0/16".parse().unwrap()));
+    assert!(!network.contains(&"0.0.0.0/0".parse().unwrap()));
+    assert!(network.overlaps(&"0.0.0.0/0".parse().unwrap()));
+    assert!(!network.overlaps(&"192.168.0.0/16".parse().unwrap()));
+    let all: Cidr = "::/0".parse().unwrap();
+    assert!(all.contains(&"fd00::1/128".parse().unwrap()));
+    assert!(!all.contains(&network));
+}
+
+#[test]
+fn test_allowed_ips() {
+    let allowed: AllowedIps = "10.1.0.5/16, 10.0.0.0/8,fd00::/64, 192.168.1.1"
+        .parse()
+        .unwrap();
+    assert_eq!(allowed.len(), 4);
+    assert_eq!(
+        allowed.to_string(),
+        "10.1.0.5/16, 10.0.0.0/8, fd00::/64, 192.168.1.1/32"
+    );
+    assert_eq!(
+        allowed.normalize().to_string(),
+        "10.0.0.0/8, 192.168.1.1/32, fd00::/64"
+    );
+    assert!(allowed.contains_addr(&"fd00::1".parse().unwrap()));
+    assert!(!allowed.contains_addr(&"fd01::1".parse().unwrap()));
+    let other: AllowedIps = "192.168.0.0/16, 172.16.0.0/12".parse().unwrap();
+    assert!(allowed.overlaps(&other));
+    assert_eq!(
+        allowed.overlapping(&other).collect::<Vec<_>>(),
+        vec![(
+            "192.168.1.1/32".parse().unwrap(),
+            "192.168.0.0/16".parse().unwrap()
+        )]
+    );
+    assert_eq!("".parse::<AllowedIps>().unwrap(), AllowedIps::new());
+    assert!("10.0.0.0/8, foo".parse::<AllowedIps>().is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_allowed_ips_serde() {
+    use serde_test::{assert_tokens, Token};
+    let allowed: AllowedIps = "10.0.0.0/8, fd00::1/128".parse().unwrap();
+    assert_tokens(
+        &allowed,
+        &[
+            Token::Seq { len: Some(2) },
+            Token::Str("10.0.0.0/8"),
+            Token::Str("fd00::1/128"),
+            Token::SeqEnd,
+        ],
+    );
+}
@@ -0,0 +1,251 @@
+//! Extractors for [axum](https://docs.rs/axum) handlers, which reject requests with invalid keys
+//! with status 400 and a JSON body describing the problem, instead of axum's plain text
+//! rejections.
+//!
+//! Keys in the path can be extracted with [KeyPath], keys in headers with
+//! [FromHeader]. Both use [KeyRejection]:
+//!
+//! ```
+//! use axum::{routing::get, Router};
+//! use wireguard_keys::axum::KeyPath;
+//! use wireguard_keys::header::HeaderPubkey;
+//! use wireguard_keys::Pubkey;
+//!
+//! async fn peer(KeyPath(pubkey): KeyPath<Pubkey>, client: HeaderPubkey) -> String {
+//!     format!("{pubkey} requested by {}", *client)
+//! }
+//!
+//! let app: Router = Router::new().route("/peers/{pubkey}", get(peer));
+//! ```
+//!
+//! With the `serde` feature, `axum::extract::Path<Pubkey>` works as well, including in tuples
+//! and structs of multiple path parameters, but rejects invalid keys with axum's own
+//! [PathRejection].
+
+use crate::header::{FromHeader, HeaderError, HeaderName};
+use crate::ParseError;
+use axum::extract::rejection::PathRejection;
+use axum::extract::{FromRequestParts, Path};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Rejection of extractors reading keys, which responds with status 400 and a JSON body with
+/// an `error` code and a human-readable `message`.
+#[derive(Error, Debug)]
+pub enum KeyRejection {
+    /// Key in header is missing or invalid
+    #[error(transparent)]
+    Header(#[from] HeaderError),
+    /// Key in path is invalid
+    #[error("invalid key in path: {0}")]
+    Path(#[source] ParseError),
+    /// Path does not have a single parameter
+    #[error("{0}")]
+    PathParam(String),
+}
+
+impl KeyRejection {
+    /// Machine-readable error code, which is written as `error` in the response body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            KeyRejection::Header(error) => error.code(),
+            KeyRejection::Path(_) => "invalid_key",
+            KeyRejection::PathParam(_) => "invalid_path",
+        }
+    }
+}
+
+impl From<PathRejection> for KeyRejection {
+    fn from(rejection: PathRejection) -> Self {
+        KeyRejection::PathParam(rejection.body_text())
+    }
+}
+
+impl IntoResponse for KeyRejection {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "error": self.code(),
+            "message": self.to_string(),
+        });
+        (StatusCode::BAD_REQUEST, Json(body)).into_response()
+    }
+}
+
+/// Extractor parsing a key from the only parameter of the path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyPath<K>(pub K);
+
+impl<K, S> FromRequestParts<S> for KeyPath<K>
+where
+    K: FromStr<Err = ParseError> + Send,
+    S: Send + Sync,
+{
+    type Rejection = KeyRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(mut data) = Path::<String>::from_request_parts(parts, state).await?;
+        let result = data.parse().map(KeyPath).map_err(KeyRejection::Path);
+        zeroize::Zeroize::zeroize(&mut data);
+        result
+    }
+}
+
+impl<K, H, S> FromRequestParts<S> for FromHeader<K, H>
+where
+    K: FromStr<Err = ParseError> + Send,
+    H: HeaderName + Send,
+    S: Send + Sync,
+{
+    type Rejection = KeyRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let values = parts
+            .headers
+            .get_all(H::NAME)
+            .iter()
+            .map(|value| value.to_str())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| HeaderError::Encoding(H::NAME))?;
+        Ok(Self::from_values(values)?)
+    }
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+fn extract<T: FromRequestParts<()>>(request: axum::http::Request<()>) -> Result<T, T::Rejection> {
+    let (mut parts, ()) = request.into_parts();
+    crate::block_on(T::from_request_parts(&mut parts, &()))
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+fn rejection_body(rejection: KeyRejection) -> serde_json::Value {
+    let response = rejection.into_response();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = crate::block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[cfg(any(
+    feature = "hex",
+    feature = "base64",
+    feature = "base32",
+    feature = "base58",
+    feature = "proquint"
+))]
+#[test]
+fn test_axum_header() {
+    use crate::header::HeaderPubkey;
+    use crate::Pubkey;
+    use axum::http::Request;
+
+    let pubkey = Pubkey::new([1; 32]);
+    let request = Request::builder()
+        .header("x-wireguard-pubkey", pubkey.to_string())
+        .body(())
+        .unwrap();
+    assert_eq!(*extract::<HeaderPubkey>(request).unwrap(), pubkey);
+
+    let rejection = extract::<HeaderPubkey>(Request::new(())).unwrap_err();
+    assert_eq!(
+        rejection_body(rejection),
+        serde_json::json!({
+            "error": "missing_header",
+            "message": "missing header X-WireGuard-Pubkey",
+        })
+    );
+    let request = Request::builder()
+        .header("x-wireguard-pubkey", "abc")
+        .body(())
+        .unwrap();
+    let rejection = extract::<HeaderPubkey>(request).unwrap_err();
+    assert_eq!(rejection_body(rejection)["error"], "invalid_key");
+    let request = Request::builder()
+        .header("x-wireguard-pubkey", &[0xff][..])
+        .body(())
+        .unwrap();
+    let rejection = extract::<HeaderPubkey>(request).unwrap_err();
+    assert_eq!(rejection_body(rejection)["error"], "invalid_header");
+}
+
+#[cfg(feature = "base64")]
+#[test]
+fn test_axum_path() {
+    use crate::Pubkey;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::get;
+    use axum::Router;
+    use std::convert::Infallible;
+    use tower_service::Service;
+
+    async fn peer(KeyPath(pubkey): KeyPath<Pubkey>) -> String {
+        pubkey.to_string()
+    }
+
+    async fn pair(_: KeyPath<Pubkey>) {}
+
+    #[cfg(feature = "serde")]
+    async fn serde_path(Path(pubkey): Path<Pubkey>) -> String {
+        pubkey.to_string()
+    }
+    #[cfg(not(feature = "serde"))]
+    async fn serde_path() -> String {
+        String::new()
+    }
+
+    let mut app = Router::new()
+        .route("/peers/{pubkey}", get(peer))
+        .route("/pairs/{a}/{b}", get(pair))
+        .route("/serde/{pubkey}", get(serde_path));
+    let mut request = |uri: String| -> Response {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let result: Result<Response, Infallible> = crate::block_on(app.call(request));
+        result.unwrap()
+    };
+    // contains `-` and `_`, so that it is not ambiguous with base58
+    let pubkey = Pubkey::new([0xfb; 32]);
+    let response = request(format!("/peers/{}", pubkey.to_url_component()));
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = crate::block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+    assert_eq!(body, pubkey.to_string());
+
+    let response = request("/peers/abc".into());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = crate::block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"], "invalid_key");
+    let response = request("/pairs/a/b".into());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    #[cfg(feature = "serde")]
+    {
+        let response = request(format!("/serde/{}", pubkey.to_url_component()));
+        let body = crate::block_on(axum::body::to_bytes(response.into_body(), usize::MAX));
+        assert_eq!(body.unwrap(), pubkey.to_string());
+        assert_eq!(
+            request("/serde/abc".into()).status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,1636 @@
+//! Generate WireGuard keys, compatible with the `genkey`, `genpsk` and `pubkey` subcommands of
+//! wg(8): keys are read from standard input and written to standard output, one per line, and
+//! failures exit with status 1.
+//!
+//! ```sh
+//! wg-keys genkey | tee privkey | wg-keys pubkey > pubkey
+//! ```
+//!
+//! Additionally, `vanity` searches for a private key whose public key starts with a prefix,
+//! using all processors:
+//!
+//! ```sh
+//! wg-keys vanity --ignore-case --timeout 600 home/ > privkey
+//! ```
+//!
+//! and `qr` renders a key or a wg-quick configuration as QR code, for scanning with mobile
+//! clients:
+//!
+//! ```sh
+//! wg-keys qr --format png --output peer.png peer.conf
+//! ```
+//!
+//! and `convert` re-encodes keys, one per line:
+//!
+//! ```sh
+//! wg-keys convert --to hex < pubkeys > pubkeys.hex
+//! ```
+//!
+//! and `genconf` generates the configurations of a server and its peers:
+//!
+//! ```sh
+//! wg-keys genconf --endpoint vpn.example.com:51820 --peer office --peer warehouse --output wg
+//! ```
+//!
+//! and `verify` checks that keys belong together:
+//!
+//! ```sh
+//! wg-keys verify "$(cat pubkey)" < privkey
+//! wg-keys verify --config wg/server.conf wg/office.conf
+//! ```
+//!
+//! and `dh` prints the x25519 shared secret of a private and a public key, for debugging
+//! interoperability with other implementations:
+//!
+//! ```sh
+//! wg-keys dh --i-know-what-im-doing --privkey privkey --pubkey "$(cat peer.pub)"
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use wireguard_keys::allowed_ips::Cidr;
+use wireguard_keys::config::{BuildError, ConfigError, Interface, Peer, TunnelConfig};
+use wireguard_keys::endpoint::Endpoint;
+use wireguard_keys::{Encoding, ParseError, Privkey, Pubkey, Secret};
+use zeroize::Zeroize;
+
+/// Length of a base64-encoded key, including padding.
+const KEY_LEN_BASE64: usize = 44;
+
+/// Reasons for a subcommand to fail, which are reported on standard error.
+#[derive(Debug)]
+enum Error {
+    /// Input is not a base64-encoded key
+    Format,
+    /// Input has more than whitespace after the key
+    Trailing,
+    /// Vanity prefix cannot occur in base64-encoded keys
+    Prefix,
+    /// Vanity search ran out of time or attempts
+    NotFound(u64),
+    /// Input of QR code is neither a key nor a configuration
+    Config(ConfigError),
+    /// Configuration is too large for a QR code
+    TooLarge,
+    /// Binary output would be written to a terminal
+    Terminal,
+    /// Some lines could not be converted
+    Convert(usize),
+    /// Generated configuration is invalid
+    Build(BuildError),
+    /// Network is invalid or too small for all peers
+    Network(String),
+    /// Peer name cannot be used as file name, or is used twice
+    PeerName(String),
+    /// Output file already exists
+    Exists(PathBuf),
+    /// Public key does not belong to the private key
+    Mismatch,
+    /// Configuration file is invalid
+    ConfigFile(PathBuf, ConfigError),
+    /// Verifying configurations found problems
+    Problems(usize),
+    /// Printing a shared secret was not confirmed
+    Unconfirmed,
+    /// Public key is of low order, so the shared secret is all zeroes
+    LowOrder,
+    /// Reading or writing failed
+    Io(io::Error),
+}
+
+impl From<BuildError> for Error {
+    fn from(error: BuildError) -> Self {
+        Error::Build(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Format => write!(f, "Key is not the correct length or format"),
+            Error::Trailing => write!(f, "Trailing characters found after key"),
+            Error::Prefix => write!(f, "Prefix is not valid base64 of at most 43 characters"),
+            Error::NotFound(tried) => write!(f, "No matching key found after {tried} keys"),
+            Error::Config(error) => {
+                write!(
+                    f,
+                    "Input is neither a key nor a valid configuration ({error})"
+                )
+            }
+            Error::TooLarge => write!(f, "Configuration is too large for a QR code"),
+            Error::Terminal => write!(f, "Refusing to write PNG to a terminal, use --output"),
+            Error::Convert(1) => write!(f, "1 line could not be converted"),
+            Error::Convert(lines) => write!(f, "{lines} lines could not be converted"),
+            Error::Build(error) => write!(f, "Invalid configuration: {error}"),
+            Error::Network(network) => {
+                write!(f, "Network {network} is invalid or too small for all peers")
+            }
+            Error::PeerName(name) => write!(f, "Invalid or duplicate peer name {name:?}"),
+            Error::Exists(path) => write!(f, "{} already exists", path.display()),
+            Error::Mismatch => write!(f, "Public key does not belong to the private key"),
+            Error::ConfigFile(path, error) => write!(f, "{}: {error}", path.display()),
+            Error::Problems(1) => write!(f, "1 problem found"),
+            Error::Problems(problems) => write!(f, "{problems} problems found"),
+            Error::Unconfirmed => write!(
+                f,
+                "Shared secrets are sensitive, pass {DH_CONFIRM} to print it anyway"
+            ),
+            Error::LowOrder => write!(f, "Public key is of low order, shared secret is zero"),
+            Error::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let program = args.first().map(String::as_str).unwrap_or("wg-keys");
+    let result = match args.get(1).map(String::as_str) {
+        Some("-h" | "--help" | "help") => {
+            print!("{}", usage(program));
+            return ExitCode::SUCCESS;
+        }
+        Some(command @ ("genkey" | "genpsk" | "pubkey")) if args.len() > 2 => {
+            eprintln!("Usage: {program} {command}");
+            return ExitCode::FAILURE;
+        }
+        Some("genkey") => {
+            warn_world_accessible();
+            genkey(&mut io::stdout().lock())
+        }
+        Some("genpsk") => {
+            warn_world_accessible();
+            genpsk(&mut io::stdout().lock())
+        }
+        Some("pubkey") => pubkey(&mut io::stdin().lock(), &mut io::stdout().lock()),
+        Some("vanity") => match Vanity::parse(&args[2..]) {
+            Some(options) => {
+                warn_world_accessible();
+                vanity(&options, &mut io::stdout().lock())
+            }
+            None => {
+                eprintln!("Usage: {program} vanity {VANITY_USAGE}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Some("qr") => match Qr::parse(&args[2..]) {
+            Some(options) => qr(&options),
+            None => {
+                eprintln!("Usage: {program} qr {QR_USAGE}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Some("convert") => match Convert::parse(&args[2..]) {
+            Some(options) => {
+                let mut report = |line, error| match error {
+                    ParseError::Ambiguous => eprintln!(
+                        "{program}: line {line}: {error}, choose the encoding with --from"
+                    ),
+                    error => eprintln!("{program}: line {line}: {error}"),
+                };
+                match &options.input {
+                    Some(path) => File::open(path).map_err(Error::Io).and_then(|file| {
+                        let mut input = io::BufReader::new(file);
+                        convert(&options, &mut input, &mut io::stdout().lock(), &mut report)
+                    }),
+                    None => convert(
+                        &options,
+                        &mut io::stdin().lock(),
+                        &mut io::stdout().lock(),
+                        &mut report,
+                    ),
+                }
+            }
+            None => {
+                let names: Vec<&str> = Encoding::ALL.iter().map(|e| e.name()).collect();
+                eprintln!("Usage: {program} convert {CONVERT_USAGE}");
+                eprintln!("Available encodings: {}", names.join(", "));
+                return ExitCode::FAILURE;
+            }
+        },
+        Some("genconf") => match Genconf::parse(&args[2..]) {
+            Some(options) => genconf(&options),
+            None => {
+                eprintln!("Usage: {program} genconf {GENCONF_USAGE}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Some("verify") => match args.get(2..).unwrap_or_default() {
+            [pubkey] if pubkey != "--config" => verify(pubkey, &mut io::stdin().lock()),
+            [flag, files @ ..] if flag == "--config" && !files.is_empty() => {
+                verify_configs(files, |problem| eprintln!("{program}: {problem}"))
+            }
+            _ => {
+                eprintln!("Usage: {program} verify {VERIFY_USAGE}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Some("dh") => match Dh::parse(&args[2..]) {
+            Some(options) => dh(&options),
+            None => {
+                eprintln!("Usage: {program} dh {DH_USAGE}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Some(command) => {
+            eprint!("Invalid subcommand: `{command}'\n{}", usage(program));
+            return ExitCode::FAILURE;
+        }
+        None => {
+            eprint!("{}", usage(program));
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{program}: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} <cmd>\n\
+         Available subcommands:\n  \
+         genkey: Generates a new private key and writes it to stdout\n  \
+         genpsk: Generates a new preshared key and writes it to stdout\n  \
+         pubkey: Reads a private key from stdin and writes a public key to stdout\n  \
+         vanity: Generates a private key whose public key starts with a prefix\n  \
+         qr: Reads a key or configuration from stdin or a file and renders it as QR code\n  \
+         convert: Reads keys from stdin or a file, one per line, and writes them re-encoded\n  \
+         genconf: Generates configurations of a server and its peers\n  \
+         verify: Checks that a private key from stdin, or configurations, match public keys\n  \
+         dh: Reads a private key from a file and writes the shared secret with a public key\n"
+    )
+}
+
+/// Arguments of the `vanity` subcommand.
+const VANITY_USAGE: &str =
+    "[--ignore-case] [--threads <n>] [--attempts <n>] [--timeout <seconds>] <prefix>";
+
+/// Options of the `vanity` subcommand.
+#[derive(Debug, PartialEq)]
+struct Vanity {
+    /// Prefix of the base64-encoded public key
+    prefix: String,
+    /// Compare letters in the prefix ignoring case
+    ignore_case: bool,
+    /// Number of threads generating keys
+    threads: usize,
+    /// Give up after trying this many keys
+    attempts: Option<u64>,
+    /// Give up after searching this long
+    timeout: Option<Duration>,
+}
+
+impl Vanity {
+    /// Parse options from the arguments following the subcommand, returning `None` for invalid
+    /// arguments.
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut options = Vanity {
+            prefix: String::new(),
+            ignore_case: false,
+            threads: thread::available_parallelism().map_or(1, usize::from),
+            attempts: None,
+            timeout: None,
+        };
+        let mut prefix = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-i" | "--ignore-case" => options.ignore_case = true,
+                "--threads" => options.threads = args.next()?.parse().ok().filter(|&n| n > 0)?,
+                "--attempts" => options.attempts = Some(args.next()?.parse().ok()?),
+                "--timeout" => {
+                    options.timeout = Some(Duration::from_secs(args.next()?.parse().ok()?))
+                }
+                // `-` does not occur in base64, so this is an unknown option
+                _ if arg.starts_with('-') || prefix.is_some() => return None,
+                _ => prefix = Some(arg.clone()),
+            }
+        }
+        options.prefix = prefix?;
+        Some(options)
+    }
+
+    /// Check if the base64-encoded public key starts with the prefix.
+    fn matches(&self, pubkey: &str) -> bool {
+        let start = &pubkey.as_bytes()[..self.prefix.len()];
+        match self.ignore_case {
+            true => start.eq_ignore_ascii_case(self.prefix.as_bytes()),
+            false => start == self.prefix.as_bytes(),
+        }
+    }
+
+    /// Average number of keys to try until one matches.
+    fn expected_attempts(&self) -> f64 {
+        self.prefix
+            .bytes()
+            .map(|c| match self.ignore_case && c.is_ascii_alphabetic() {
+                true => 32.0,
+                false => 64.0,
+            })
+            .product()
+    }
+}
+
+/// Search for a vanity key and write it, showing progress if standard error is a terminal.
+fn vanity<W: Write>(options: &Vanity, output: &mut W) -> Result<(), Error> {
+    let terminal = io::stderr().is_terminal();
+    if terminal {
+        eprintln!(
+            "Expecting to try about {:.0} keys, using {} thread{}",
+            options.expected_attempts(),
+            options.threads,
+            if options.threads == 1 { "" } else { "s" }
+        );
+    }
+    let result = search(options, |tried, elapsed| {
+        if terminal {
+            let rate = tried as f64 / elapsed.as_secs_f64();
+            eprint!("\r{tried} keys tried, {rate:.0} keys/s");
+        }
+    });
+    if terminal {
+        eprintln!();
+    }
+    let mut privkey = result?;
+    if terminal {
+        eprintln!("Found public key {}", privkey.pubkey().to_base64());
+    }
+    let result = write_key(output, privkey.to_base64());
+    privkey.zeroize();
+    result
+}
+
+/// Generate keys on all threads until the public key of one matches, or the time or attempts
+/// run out. Calls `progress` with the number of keys tried and the elapsed time every second.
+fn search<F: FnMut(u64, Duration)>(options: &Vanity, mut progress: F) -> Result<Privkey, Error> {
+    let valid = |c: u8| c.is_ascii_alphanumeric() || c == b'+' || c == b'/';
+    if options.prefix.len() >= KEY_LEN_BASE64 || !options.prefix.bytes().all(valid) {
+        return Err(Error::Prefix);
+    }
+    let start = Instant::now();
+    let tried = AtomicU64::new(0);
+    let stop = AtomicBool::new(false);
+    let running = AtomicUsize::new(options.threads);
+    let found = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..options.threads {
+            scope.spawn(|| {
+                let mut buffer = [0; KEY_LEN_BASE64];
+                while !stop.load(Ordering::Relaxed) {
+                    let count = tried.fetch_add(1, Ordering::Relaxed);
+                    if options.attempts.is_some_and(|attempts| count >= attempts) {
+                        break;
+                    }
+                    let mut privkey = Privkey::generate();
+                    if options.matches(privkey.pubkey().encode_base64_into(&mut buffer)) {
+                        stop.store(true, Ordering::Relaxed);
+                        found.lock().unwrap().get_or_insert(privkey);
+                    }
+                    privkey.zeroize();
+                }
+                running.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+        let mut report = start + Duration::from_secs(1);
+        while running.load(Ordering::Relaxed) > 0 {
+            thread::sleep(Duration::from_millis(10));
+            let now = Instant::now();
+            if options
+                .timeout
+                .is_some_and(|timeout| now - start >= timeout)
+            {
+                stop.store(true, Ordering::Relaxed);
+            }
+            if now >= report {
+                progress(tried.load(Ordering::Relaxed), now - start);
+                report += Duration::from_secs(1);
+            }
+        }
+    });
+    let tried = tried.into_inner();
+    let tried = options
+        .attempts
+        .map_or(tried, |attempts| tried.min(attempts));
+    found.into_inner().unwrap().ok_or(Error::NotFound(tried))
+}
+
+/// Write a newly generated private key.
+fn genkey<W: Write>(output: &mut W) -> Result<(), Error> {
+    let mut privkey = Privkey::generate();
+    let result = write_key(output, privkey.to_base64());
+    privkey.zeroize();
+    result
+}
+
+/// Write a newly generated preshared key.
+fn genpsk<W: Write>(output: &mut W) -> Result<(), Error> {
+    let mut secret = Secret::generate();
+    let result = write_key(output, secret.to_base64());
+    secret.zeroize();
+    result
+}
+
+/// Read a private key and write its public key. Like wg(8), the key has to be exactly 44
+/// characters of base64, followed by nothing but whitespace.
+fn pubkey<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> Result<(), Error> {
+    let mut data = [0; KEY_LEN_BASE64];
+    let result = read_privkey(input, &mut data);
+    data.zeroize();
+    let mut privkey = result?;
+    let pubkey = privkey.pubkey();
+    privkey.zeroize();
+    write_key(output, pubkey.to_base64())
+}
+
+fn read_privkey<R: BufRead>(
+    input: &mut R,
+    data: &mut [u8; KEY_LEN_BASE64],
+) -> Result<Privkey, Error> {
+    input.read_exact(data).map_err(|error| match error.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Format,
+        _ => Error::Io(error),
+    })?;
+    for byte in input.bytes() {
+        // same characters as isspace(3), NUL bytes are skipped by wg(8) as well
+        if !matches!(byte?, 0 | b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r') {
+            return Err(Error::Trailing);
+        }
+    }
+    let data = std::str::from_utf8(data).map_err(|_| Error::Format)?;
+    Privkey::from_base64(data).map_err(|_| Error::Format)
+}
+
+fn write_key<W: Write>(output: &mut W, mut key: String) -> Result<(), Error> {
+    let result = writeln!(output, "{key}").and_then(|()| output.flush());
+    key.zeroize();
+    Ok(result?)
+}
+
+/// Arguments of the `qr` subcommand.
+const QR_USAGE: &str = "[--format utf8|ansi|svg|png] [--output <file>] [<file>]";
+
+/// Output formats of the `qr` subcommand.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum QrFormat {
+    /// Unicode half blocks, two rows per line
+    Utf8,
+    /// ANSI background colors
+    Ansi,
+    /// SVG image
+    Svg,
+    /// PNG image
+    Png,
+}
+
+impl QrFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "utf8" => Some(QrFormat::Utf8),
+            "ansi" => Some(QrFormat::Ansi),
+            "svg" => Some(QrFormat::Svg),
+            "png" => Some(QrFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+/// Options of the `qr` subcommand.
+#[derive(Debug, PartialEq)]
+struct Qr {
+    /// Output format, by default chosen by the extension of the output file
+    format: QrFormat,
+    /// File to read from instead of standard input
+    input: Option<PathBuf>,
+    /// File to write to instead of standard output
+    output: Option<PathBuf>,
+}
+
+impl Qr {
+    /// Parse options from the arguments following the subcommand, returning `None` for invalid
+    /// arguments.
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut format = None;
+        let mut input = None;
+        let mut output: Option<PathBuf> = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => format = Some(QrFormat::parse(args.next()?)?),
+                "--output" => output = Some(args.next()?.into()),
+                _ if arg.starts_with('-') || input.is_some() => return None,
+                _ => input = Some(arg.into()),
+            }
+        }
+        let extension = output.as_ref().and_then(|output| output.extension());
+        let format = format.unwrap_or(match extension.and_then(|ext| ext.to_str()) {
+            Some("svg") => QrFormat::Svg,
+            Some("png") => QrFormat::Png,
+            _ => QrFormat::Utf8,
+        });
+        Some(Qr {
+            format,
+            input,
+            output,
+        })
+    }
+}
+
+/// Read key or configuration and write it as QR code.
+fn qr(options: &Qr) -> Result<(), Error> {
+    let mut input = String::new();
+    let result = match &options.input {
+        Some(path) => File::open(path).and_then(|mut file| file.read_to_string(&mut input)),
+        None => io::stdin().read_to_string(&mut input),
+    };
+    let result = result
+        .map_err(Error::Io)
+        .and_then(|_| render_qr(&input, options.format));
+    input.zeroize();
+    let mut data = result?;
+    let result = match &options.output {
+        Some(path) => write_private(path, &data),
+        None if options.format == QrFormat::Png && io::stdout().is_terminal() => {
+            Err(Error::Terminal)
+        }
+        None => {
+            warn_world_accessible();
+            let mut stdout = io::stdout().lock();
+            stdout
+                .write_all(&data)
+                .and_then(|()| stdout.flush())
+                .map_err(Error::Io)
+        }
+    };
+    data.zeroize();
+    result
+}
+
+/// Render key or configuration as QR code. Keys of any type are encoded as base64, and
+/// configurations as wg-quick configuration text.
+fn render_qr(input: &str, format: QrFormat) -> Result<Vec<u8>, Error> {
+    // all key types have the same encoding, so this also accepts private and preshared keys
+    if let Ok(mut key) = Pubkey::parse(input.trim()) {
+        let data = match format {
+            QrFormat::Utf8 => format!("{}\n", key.to_qr_terminal()).into_bytes(),
+            QrFormat::Ansi => key.to_qr_ansi().into_bytes(),
+            QrFormat::Svg => key.to_qr_svg().into_bytes(),
+            QrFormat::Png => key.to_qr_png(),
+        };
+        key.zeroize();
+        return Ok(data);
+    }
+    let config = TunnelConfig::parse(input).map_err(Error::Config)?;
+    let data = match format {
+        QrFormat::Utf8 => config
+            .to_qr_terminal()
+            .map(|code| format!("{code}\n").into_bytes()),
+        QrFormat::Ansi => config.to_qr_ansi().map(String::into_bytes),
+        QrFormat::Svg => config.to_qr_svg().map(String::into_bytes),
+        QrFormat::Png => config.to_qr_png(),
+    };
+    data.map_err(|_| Error::TooLarge)
+}
+
+/// Write file which only the owner can read, since QR codes and configurations may contain
+/// private keys. The data is written to a temporary file which is renamed into place, so that
+/// an existing file is replaced along with its permissions, and never left half-written.
+fn write_private(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let temporary = write_temporary(path, data)?;
+    std::fs::rename(&temporary, path).map_err(|error| {
+        let _ = std::fs::remove_file(&temporary);
+        Error::Io(error)
+    })
+}
+
+/// Write data to a new file next to `path`, which only the owner can read, returning its path.
+fn write_temporary(path: &Path, data: &[u8]) -> Result<PathBuf, Error> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let mut temporary = std::ffi::OsString::from(".");
+    temporary.push(name);
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    let temporary = path.with_file_name(temporary);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&temporary)?;
+    if let Err(error) = file.write_all(data).and_then(|_| file.sync_all()) {
+        let _ = std::fs::remove_file(&temporary);
+        return Err(Error::Io(error));
+    }
+    Ok(temporary)
+}
+
+/// Arguments of the `convert` subcommand.
+const CONVERT_USAGE: &str = "[--from auto|<encoding>] --to <encoding> [<file>]";
+
+/// Options of the `convert` subcommand.
+#[derive(Debug, PartialEq)]
+struct Convert {
+    /// Encoding of the input, or `None` to detect it
+    from: Option<Encoding>,
+    /// Encoding of the output
+    to: Encoding,
+    /// File to read from instead of standard input
+    input: Option<PathBuf>,
+}
+
+impl Convert {
+    /// Parse options from the arguments following the subcommand, returning `None` for invalid
+    /// arguments.
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut from = None;
+        let mut to = None;
+        let mut input = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from" => match args.next()?.as_str() {
+                    "auto" => from = None,
+                    name => from = Some(Encoding::from_name(name)?),
+                },
+                "--to" => to = Some(Encoding::from_name(args.next()?)?),
+                _ if arg.starts_with('-') || input.is_some() => return None,
+                _ => input = Some(arg.into()),
+            }
+        }
+        Some(Convert {
+            from,
+            to: to?,
+            input,
+        })
+    }
+}
+
+/// Re-encode keys, one per line. Surrounding whitespace and empty lines are ignored. Lines
+/// which are not valid keys are passed to `report` with their line number, and skipped.
+fn convert<R, W, F>(
+    options: &Convert,
+    input: &mut R,
+    output: &mut W,
+    report: F,
+) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(usize, ParseError),
+{
+    let mut line = String::new();
+    let result = convert_lines(options, input, output, report, &mut line);
+    line.zeroize();
+    result
+}
+
+fn convert_lines<R, W, F>(
+    options: &Convert,
+    input: &mut R,
+    output: &mut W,
+    mut report: F,
+    line: &mut String,
+) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(usize, ParseError),
+{
+    let mut failed = 0;
+    for number in 1.. {
+        line.zeroize();
+        line.clear();
+        if input.read_line(line)? == 0 {
+            break;
+        }
+        let data = line.trim();
+        if data.is_empty() {
+            continue;
+        }
+        // all key types have the same encodings, and public keys are not clamped
+        let key = match options.from {
+            Some(encoding) => Pubkey::decode(data, encoding),
+            None => Pubkey::parse(data),
+        };
+        match key {
+            Ok(mut key) => {
+                let result = write_key(output, key.encode(options.to));
+                key.zeroize();
+                result?;
+            }
+            Err(error) => {
+                report(number, error);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        failed => Err(Error::Convert(failed)),
+    }
+}
+
+/// Arguments of the `genconf` subcommand.
+const GENCONF_USAGE: &str = "--endpoint <host:port> [--network <cidr>] [--peers <n>] \
+    [--peer <name>]... [--allowed-ips <cidr>]... [--dns <server>]... [--keepalive <seconds>] \
+    [--preshared-keys] [--qr] [--output <directory>]";
+
+/// Options of the `genconf` subcommand.
+#[derive(Debug, PartialEq)]
+struct Genconf {
+    /// Endpoint of the server, whose port is also the listen port
+    endpoint: String,
+    /// Network of the tunnel, the server gets the first address and the peers the following
+    network: String,
+    /// Names of the peers, which are also used as file names
+    peers: Vec<String>,
+    /// Networks peers route through the server, by default the tunnel network
+    allowed_ips: Vec<String>,
+    /// DNS servers of the peers
+    dns: Vec<String>,
+    /// Persistent keepalive interval of the peers
+    keepalive: Option<u16>,
+    /// Generate a preshared key for each peer
+    preshared_keys: bool,
+    /// Render peer configurations as QR codes
+    qr: bool,
+    /// Directory to write the configurations to
+    output: PathBuf,
+}
+
+impl Genconf {
+    /// Parse options from the arguments following the subcommand, returning `None` for invalid
+    /// arguments.
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut options = Genconf {
+            endpoint: String::new(),
+            network: "10.0.0.0/24".into(),
+            peers: Vec::new(),
+            allowed_ips: Vec::new(),
+            dns: Vec::new(),
+            keepalive: None,
+            preshared_keys: false,
+            qr: false,
+            output: ".".into(),
+        };
+        let mut endpoint = None;
+        let mut count = 0;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--endpoint" => endpoint = Some(args.next()?.clone()),
+                "--network" => options.network = args.next()?.clone(),
+                "--peers" => count = args.next()?.parse().ok()?,
+                "--peer" => options.peers.push(args.next()?.clone()),
+                "--allowed-ips" => options.allowed_ips.push(args.next()?.clone()),
+                "--dns" => options.dns.push(args.next()?.clone()),
+                "--keepalive" => options.keepalive = Some(args.next()?.parse().ok()?),
+                "--preshared-keys" => options.preshared_keys = true,
+                "--qr" => options.qr = true,
+                "--output" => options.output = args.next()?.into(),
+                _ => return None,
+            }
+        }
+        options.endpoint = endpoint?;
+        options
+            .peers
+            .extend((1..=count).map(|index| format!("peer{index}")));
+        if options.peers.is_empty() {
+            return None;
+        }
+        Some(options)
+    }
+}
+
+/// Address of the host with the index in the network, or `None` if the network is too small.
+/// The last address is left out, since it is the broadcast address in IPv4 networks.
+fn host_address(network: &Cidr, index: u128) -> Option<IpAddr> {
+    let bits = if network.is_ipv4() { 32 } else { 128 };
+    let size = 1u128
+        .checked_shl(bits - u32::from(network.prefix()))
+        .unwrap_or(u128::MAX);
+    if index >= size - 1 {
+        return None;
+    }
+    match network.network().addr() {
+        IpAddr::V4(addr) => Some(Ipv4Addr::from(u32::from(addr) + index as u32).into()),
+        IpAddr::V6(addr) => Some(Ipv6Addr::from(u128::from(addr) + index).into()),
+    }
+}
+
+/// Generate the configuration of a server, and of each peer connecting to it.
+fn generate(options: &Genconf) -> Result<(TunnelConfig, Vec<TunnelConfig>), Error> {
+    let endpoint: Endpoint = options
+        .endpoint
+        .parse()
+        .map_err(|error| BuildError::Endpoint {
+            value: options.endpoint.clone(),
+            error,
+        })?;
+    let network: Cidr = options
+        .network
+        .parse()
+        .map_err(|_| Error::Network(options.network.clone()))?;
+    let host = |index| host_address(&network, index).ok_or(Error::Network(network.to_string()));
+    let host_prefix = if network.is_ipv4() { 32 } else { 128 };
+    for (index, name) in options.peers.iter().enumerate() {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+        if name.is_empty()
+            || name.starts_with('.')
+            || !name.chars().all(valid)
+            || name == "server"
+            || options.peers[..index].contains(name)
+        {
+            return Err(Error::PeerName(name.clone()));
+        }
+    }
+
+    let server_privkey = Privkey::generate();
+    let server = Interface::builder()
+        .private_key(server_privkey)
+        .address(format!("{}/{}", host(1)?, network.prefix()))
+        .listen_port(endpoint.port())
+        .build()?;
+    let mut server = TunnelConfig::new(server);
+    let mut peers = Vec::new();
+    for (index, name) in options.peers.iter().enumerate() {
+        let address = host(index as u128 + 2)?;
+        let privkey = Privkey::generate();
+        let preshared_key = options.preshared_keys.then(Secret::generate);
+
+        let mut peer = Peer::builder()
+            .name(name.clone())
+            .pubkey(privkey.pubkey())
+            .allowed_ip(format!("{address}/{host_prefix}"));
+        if let Some(preshared_key) = preshared_key {
+            peer = peer.preshared_key(preshared_key);
+        }
+        server.peers.push(peer.build()?);
+
+        let mut interface = Interface::builder()
+            .private_key(privkey)
+            .address(format!("{address}/{}", network.prefix()));
+        for dns in &options.dns {
+            interface = interface.dns(dns);
+        }
+        let mut peer = Peer::builder()
+            .name("server")
+            .pubkey(server_privkey.pubkey())
+            .endpoint(&options.endpoint);
+        if options.allowed_ips.is_empty() {
+            peer = peer.allowed_ip(network.network());
+        }
+        for allowed_ip in &options.allowed_ips {
+            peer = peer.allowed_ip(allowed_ip);
+        }
+        if let Some(preshared_key) = preshared_key {
+            peer = peer.preshared_key(preshared_key);
+        }
+        if let Some(keepalive) = options.keepalive {
+            peer = peer.keepalive(keepalive);
+        }
+        let mut config = TunnelConfig::new(interface.build()?);
+        config.peers.push(peer.build()?);
+        peers.push(config);
+    }
+    Ok((server, peers))
+}
+
+/// Generate configurations and write them to the output directory, as `server.conf` and a file
+/// named after each peer. Existing files are never replaced.
+fn genconf(options: &Genconf) -> Result<(), Error> {
+    let (server, peers) = generate(options)?;
+    let mut files = vec![(
+        options.output.join("server.conf"),
+        server.to_string().into_bytes(),
+    )];
+    for (name, config) in options.peers.iter().zip(&peers) {
+        files.push((
+            options.output.join(format!("{name}.conf")),
+            config.to_string().into_bytes(),
+        ));
+        if options.qr {
+            let code = config.to_qr_png().map_err(|_| Error::TooLarge)?;
+            files.push((options.output.join(format!("{name}.png")), code));
+        }
+    }
+    let result = write_files(&options.output, &files);
+    for (_, data) in &mut files {
+        data.zeroize();
+    }
+    result
+}
+
+/// Write files which only the owner can read, either all of them or none at all. Existing
+/// files are never replaced.
+fn write_files(directory: &Path, files: &[(PathBuf, Vec<u8>)]) -> Result<(), Error> {
+    std::fs::create_dir_all(directory)?;
+    // check first, so that nothing is written if any file exists
+    if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        return Err(Error::Exists(path.clone()));
+    }
+    // only link the files into place once all of them were written, and unlike renaming,
+    // linking fails if a file was created in the meantime
+    let mut temporaries = Vec::new();
+    let mut linked = Vec::new();
+    let mut result = Ok(());
+    for (path, data) in files {
+        match write_temporary(path, data) {
+            Ok(temporary) => temporaries.push(temporary),
+            Err(error) => {
+                result = Err(error);
+                break;
+            }
+        }
+    }
+    if result.is_ok() {
+        for ((path, _), temporary) in files.iter().zip(&temporaries) {
+            match std::fs::hard_link(temporary, path) {
+                Ok(()) => linked.push(path),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                    result = Err(Error::Exists(path.clone()));
+                    break;
+                }
+                Err(error) => {
+                    result = Err(Error::Io(error));
+                    break;
+                }
+            }
+        }
+    }
+    for temporary in &temporaries {
+        let _ = std::fs::remove_file(temporary);
+    }
+    if result.is_err() {
+        for path in linked {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    result
+}
+
+/// Arguments of the `verify` subcommand.
+const VERIFY_USAGE: &str = "<pubkey> | --config <file>...";
+
+/// Read a private key like `pubkey` does, and check that the public key belongs to it.
+fn verify<R: BufRead>(pubkey: &str, input: &mut R) -> Result<(), Error> {
+    let pubkey = Pubkey::parse(pubkey).map_err(|_| Error::Format)?;
+    let mut data = [0; KEY_LEN_BASE64];
+    let result = read_privkey(input, &mut data);
+    data.zeroize();
+    let mut privkey = result?;
+    let matches = privkey.matches(&pubkey);
+    privkey.zeroize();
+    match matches {
+        true => Ok(()),
+        false => Err(Error::Mismatch),
+    }
+}
+
+/// Check configuration files, reporting each problem to `report`.
+fn verify_configs<F: FnMut(String)>(files: &[String], report: F) -> Result<(), Error> {
+    let mut configs = Vec::new();
+    for file in files {
+        let mut data = std::fs::read_to_string(file)?;
+        let config = TunnelConfig::parse(&data);
+        data.zeroize();
+        let config = config.map_err(|error| Error::ConfigFile(file.into(), error))?;
+        configs.push((file.as_str(), config));
+    }
+    match check_configs(&configs, report) {
+        0 => Ok(()),
+        problems => Err(Error::Problems(problems)),
+    }
+}
+
+/// Check configurations on their own using [TunnelConfig::validate], and against each other:
+/// peering is mutual, so a configuration listing the public key of another one has to be
+/// listed by it as well. Otherwise, one of the two has a peer with the public key of the wrong
+/// private key. Returns the number of problems, which are passed to `report`.
+fn check_configs<F: FnMut(String)>(configs: &[(&str, TunnelConfig)], mut report: F) -> usize {
+    let mut problems = 0;
+    let mut problem = |message| {
+        report(message);
+        problems += 1;
+    };
+    let lists = |config: &TunnelConfig, other: &TunnelConfig| {
+        let privkey = &other.interface.private_key;
+        config
+            .peers
+            .iter()
+            .any(|peer| privkey.matches(&peer.public_key))
+    };
+    for (index, (name, config)) in configs.iter().enumerate() {
+        for finding in config.validate() {
+            problem(format!("{name}: {finding}"));
+        }
+        for (other_name, other) in &configs[index + 1..] {
+            match (lists(config, other), lists(other, config)) {
+                (true, false) => problem(format!(
+                    "{name} has {other_name} as peer, but not the other way around"
+                )),
+                (false, true) => problem(format!(
+                    "{other_name} has {name} as peer, but not the other way around"
+                )),
+                _ => {}
+            }
+        }
+    }
+    problems
+}
+
+/// Flag confirming that the `dh` subcommand should print the shared secret.
+const DH_CONFIRM: &str = "--i-know-what-im-doing";
+
+/// Arguments of the `dh` subcommand.
+const DH_USAGE: &str = "--i-know-what-im-doing --privkey <file> --pubkey <pubkey>";
+
+/// Options of the `dh` subcommand.
+#[derive(Debug, PartialEq)]
+struct Dh {
+    /// File to read the private key from, or `-` for standard input
+    privkey: PathBuf,
+    /// Public key of the other side, in any encoding
+    pubkey: String,
+    /// Whether printing the shared secret was confirmed
+    confirmed: bool,
+}
+
+impl Dh {
+    /// Parse options from the arguments following the subcommand, returning `None` for invalid
+    /// arguments.
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut privkey = None;
+        let mut pubkey = None;
+        let mut confirmed = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--privkey" => privkey = Some(args.next()?.into()),
+                "--pubkey" => pubkey = Some(args.next()?.clone()),
+                DH_CONFIRM => confirmed = true,
+                _ => return None,
+            }
+        }
+        Some(Dh {
+            privkey: privkey?,
+            pubkey: pubkey?,
+            confirmed,
+        })
+    }
+}
+
+fn dh(options: &Dh) -> Result<(), Error> {
+    if !options.confirmed {
+        return Err(Error::Unconfirmed);
+    }
+    let pubkey = Pubkey::parse(&options.pubkey).map_err(|_| Error::Format)?;
+    warn_world_accessible();
+    let mut output = io::stdout().lock();
+    if options.privkey.as_os_str() == "-" {
+        shared_secret(&pubkey, &mut io::stdin().lock(), &mut output)
+    } else {
+        let mut input = io::BufReader::new(File::open(&options.privkey)?);
+        shared_secret(&pubkey, &mut input, &mut output)
+    }
+}
+
+/// Read a private key like `pubkey` does, and write the shared secret with the public key.
+fn shared_secret<R: BufRead, W: Write>(
+    pubkey: &Pubkey,
+    input: &mut R,
+    output: &mut W,
+) -> Result<(), Error> {
+    let mut data = [0; KEY_LEN_BASE64];
+    let result = read_privkey(input, &mut data);
+    data.zeroize();
+    let mut privkey = result?;
+    let secret = privkey.diffie_hellman(pubkey);
+    privkey.zeroize();
+    match secret.is_zero() {
+        true => Err(Error::LowOrder),
+        false => write_key(output, base64::encode(secret.as_bytes())),
+    }
+}
+
+/// Warn when writing a key to a file other users can read, like wg(8) does.
+#[cfg(unix)]
+fn warn_world_accessible() {
+    use std::os::fd::AsFd;
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = io::stdout()
+        .as_fd()
+        .try_clone_to_owned()
+        .and_then(|fd| std::fs::File::from(fd).metadata());
+    if let Ok(metadata) = metadata {
+        if metadata.is_file() && metadata.permissions().mode() & 0o007 != 0 {
+            eprintln!("Warning: writing to world accessible file.");
+            eprintln!("Consider setting the umask to 077 and trying again.");
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_world_accessible() {}
+
+#[test]
+fn test_genkey() {
+    let mut output = Vec::new();
+    genkey(&mut output).unwrap();
+    assert_eq!(output.len(), KEY_LEN_BASE64 + 1);
+    assert_eq!(output.last(), Some(&b'\n'));
+    let key = std::str::from_utf8(&output[..KEY_LEN_BASE64]).unwrap();
+    assert!(Privkey::from_base64(key).is_ok());
+
+    let mut output = Vec::new();
+    genpsk(&mut output).unwrap();
+    let key = std::str::from_utf8(&output[..KEY_LEN_BASE64]).unwrap();
+    assert!(Secret::from_base64(key).is_ok());
+}
+
+#[test]
+fn test_pubkey() {
+    let privkey = Privkey::generate();
+    let expected = format!("{}\n", privkey.pubkey().to_base64());
+    let run = |input: &str| {
+        let mut output = Vec::new();
+        pubkey(&mut input.as_bytes(), &mut output).map(|()| String::from_utf8(output).unwrap())
+    };
+    assert_eq!(run(&privkey.to_base64()).unwrap(), expected);
+    assert_eq!(
+        run(&format!("{}\r\n \t\0", privkey.to_base64())).unwrap(),
+        expected
+    );
+    assert!(matches!(
+        run(&format!("{}\nx", privkey.to_base64())),
+        Err(Error::Trailing)
+    ));
+    assert!(matches!(run(&privkey.to_base64()[1..]), Err(Error::Format)));
+    assert!(matches!(
+        run(&format!(" {}", &privkey.to_base64()[1..])),
+        Err(Error::Format)
+    ));
+    assert!(matches!(run(&"!".repeat(44)), Err(Error::Format)));
+    assert!(matches!(run(&"a".repeat(64)), Err(Error::Trailing)));
+}
+
+#[test]
+fn test_vanity_parse() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let options = Vanity::parse(&args(&["-i", "--threads", "3", "--timeout", "10", "abc"]));
+    assert_eq!(
+        options,
+        Some(Vanity {
+            prefix: "abc".into(),
+            ignore_case: true,
+            threads: 3,
+            attempts: None,
+            timeout: Some(Duration::from_secs(10)),
+        })
+    );
+    let options = Vanity::parse(&args(&["abc", "--attempts", "100"])).unwrap();
+    assert_eq!(options.attempts, Some(100));
+    assert!(!options.ignore_case);
+    assert!(Vanity::parse(&args(&[])).is_none());
+    assert!(Vanity::parse(&args(&["abc", "def"])).is_none());
+    assert!(Vanity::parse(&args(&["--threads", "0", "abc"])).is_none());
+    assert!(Vanity::parse(&args(&["--attempts"])).is_none());
+    assert!(Vanity::parse(&args(&["--verbose", "abc"])).is_none());
+}
+
+#[test]
+fn test_vanity_search() {
+    let mut options = Vanity {
+        prefix: "A".into(),
+        ignore_case: false,
+        threads: 2,
+        attempts: None,
+        timeout: None,
+    };
+    let privkey = search(&options, |_, _| {}).unwrap();
+    assert!(privkey.pubkey().to_base64().starts_with('A'));
+
+    options.prefix = "x/".into();
+    options.ignore_case = true;
+    assert!(options.matches("X/abc"));
+    assert!(!options.matches("X+abc"));
+    assert_eq!(options.expected_attempts(), 32.0 * 64.0);
+    let privkey = search(&options, |_, _| {}).unwrap();
+    assert!(privkey.pubkey().to_base64()[..2].eq_ignore_ascii_case("x/"));
+
+    options.prefix = "AAAAAAAAAA".into();
+    options.attempts = Some(100);
+    assert!(matches!(
+        search(&options, |_, _| {}),
+        Err(Error::NotFound(100))
+    ));
+    options.attempts = None;
+    options.timeout = Some(Duration::ZERO);
+    assert!(matches!(
+        search(&options, |_, _| {}),
+        Err(Error::NotFound(_))
+    ));
+
+    options.prefix = "a-b".into();
+    assert!(matches!(search(&options, |_, _| {}), Err(Error::Prefix)));
+    options.prefix = "A".repeat(KEY_LEN_BASE64);
+    assert!(matches!(search(&options, |_, _| {}), Err(Error::Prefix)));
+}
+
+#[test]
+fn test_qr_parse() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        Qr::parse(&args(&[])),
+        Some(Qr {
+            format: QrFormat::Utf8,
+            input: None,
+            output: None,
+        })
+    );
+    assert_eq!(
+        Qr::parse(&args(&["--output", "peer.png", "peer.conf"])),
+        Some(Qr {
+            format: QrFormat::Png,
+            input: Some("peer.conf".into()),
+            output: Some("peer.png".into()),
+        })
+    );
+    let options = Qr::parse(&args(&["--format", "ansi", "--output", "peer.svg"])).unwrap();
+    assert_eq!(options.format, QrFormat::Ansi);
+    assert!(Qr::parse(&args(&["--format", "jpeg"])).is_none());
+    assert!(Qr::parse(&args(&["--output"])).is_none());
+    assert!(Qr::parse(&args(&["a.conf", "b.conf"])).is_none());
+}
+
+#[test]
+fn test_render_qr() {
+    let privkey = Privkey::generate();
+    let key = format!("{}\n", privkey.to_base64());
+    let utf8 = String::from_utf8(render_qr(&key, QrFormat::Utf8).unwrap()).unwrap();
+    assert_eq!(
+        utf8,
+        format!("{}\n", Pubkey::new(*privkey).to_qr_terminal())
+    );
+    assert!(render_qr(&key, QrFormat::Ansi)
+        .unwrap()
+        .starts_with(b"\x1b["));
+    assert!(render_qr(&key, QrFormat::Png)
+        .unwrap()
+        .starts_with(b"\x89PNG"));
+
+    let config = format!(
+        "[Interface]\nPrivateKey = {}\n\n[Peer]\nPublicKey = {}\nAllowedIPs = 0.0.0.0/0\n",
+        privkey.to_base64(),
+        Privkey::generate().pubkey().to_base64()
+    );
+    let svg = render_qr(&config, QrFormat::Svg).unwrap();
+    assert!(String::from_utf8(svg).unwrap().contains("<svg"));
+    assert!(matches!(
+        render_qr("[Peer]\n", QrFormat::Utf8),
+        Err(Error::Config(_))
+    ));
+    let peers = format!(
+        "\n[Peer]\nPublicKey = {}\n",
+        Privkey::generate().pubkey().to_base64()
+    )
+    .repeat(100);
+    assert!(matches!(
+        render_qr(&format!("{config}{peers}"), QrFormat::Utf8),
+        Err(Error::TooLarge)
+    ));
+}
+
+#[test]
+fn test_convert_parse() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        Convert::parse(&args(&["--from", "hex", "--to", "base64", "keys"])),
+        Some(Convert {
+            from: Some(Encoding::Hex),
+            to: Encoding::Base64,
+            input: Some("keys".into()),
+        })
+    );
+    let options = Convert::parse(&args(&["--from", "auto", "--to", "BASE58"])).unwrap();
+    assert_eq!(options.from, None);
+    assert_eq!(options.to, Encoding::Base58);
+    assert!(Convert::parse(&args(&[])).is_none());
+    assert!(Convert::parse(&args(&["--to", "rot13"])).is_none());
+    assert!(Convert::parse(&args(&["--to", "hex", "--from", "morse"])).is_none());
+}
+
+#[test]
+fn test_convert() {
+    let first = Privkey::generate().pubkey();
+    let second = Secret::generate();
+    let options = Convert {
+        from: None,
+        to: Encoding::Hex,
+        input: None,
+    };
+    let input = format!(
+        "{}\r\n\n  {}  \nnot a key\n{}",
+        first.to_base64(),
+        second.to_proquint(),
+        second.to_base64_urlsafe()
+    );
+    let mut output = Vec::new();
+    let mut errors = Vec::new();
+    let result = convert(
+        &options,
+        &mut input.as_bytes(),
+        &mut output,
+        |line, error| errors.push((line, error)),
+    );
+    assert!(matches!(result, Err(Error::Convert(1))));
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        format!(
+            "{}\n{}\n{}\n",
+            first.to_hex(),
+            second.to_hex(),
+            second.to_hex()
+        )
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, 4);
+
+    let options = Convert {
+        from: Some(Encoding::Hex),
+        to: Encoding::ZBase32,
+        input: None,
+    };
+    let input = format!("{}\n", first.to_hex());
+    let mut output = Vec::new();
+    convert(&options, &mut input.as_bytes(), &mut output, |_, _| {}).unwrap();
+    assert_eq!(output, format!("{}\n", first.to_zbase32()).into_bytes());
+    let input = format!("{}\n", first.to_base64());
+    let result = convert(&options, &mut input.as_bytes(), &mut Vec::new(), |_, _| {});
+    assert!(matches!(result, Err(Error::Convert(1))));
+}
+
+#[test]
+fn test_genconf_parse() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let options = Genconf::parse(&args(&[
+        "--endpoint",
+        "vpn.example.com:51820",
+        "--peer",
+        "office",
+        "--peers",
+        "2",
+        "--dns",
+        "10.0.0.1",
+        "--keepalive",
+        "25",
+        "--qr",
+    ]))
+    .unwrap();
+    assert_eq!(options.endpoint, "vpn.example.com:51820");
+    assert_eq!(options.network, "10.0.0.0/24");
+    assert_eq!(options.peers, vec!["office", "peer1", "peer2"]);
+    assert_eq!(options.dns, vec!["10.0.0.1"]);
+    assert_eq!(options.keepalive, Some(25));
+    assert!(options.qr && !options.preshared_keys);
+    assert_eq!(options.output, PathBuf::from("."));
+    assert!(Genconf::parse(&args(&["--peers", "2"])).is_none());
+    assert!(Genconf::parse(&args(&["--endpoint", "vpn:51820"])).is_none());
+    assert!(Genconf::parse(&args(&["--endpoint", "vpn:51820", "--peers", "x"])).is_none());
+}
+
+#[test]
+fn test_write_files() {
+    let directory =
+        std::env::temp_dir().join(format!("wireguard-keys-write-{}", std::process::id()));
+    let file = |name: &str, data: &str| (directory.join(name), data.as_bytes().to_vec());
+    write_files(&directory, &[file("a.conf", "a"), file("b.conf", "b")]).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(directory.join("b.conf")).unwrap(),
+        "b"
+    );
+    assert!(matches!(
+        write_files(&directory, &[file("c.conf", "c"), file("a.conf", "new")]),
+        Err(Error::Exists(_))
+    ));
+    assert!(!directory.join("c.conf").exists());
+    assert_eq!(
+        std::fs::read_to_string(directory.join("a.conf")).unwrap(),
+        "a"
+    );
+
+    // replacing a file also replaces its permissions
+    let path = directory.join("a.conf");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    }
+    write_private(&path, b"new").unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 2);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_host_address() {
+    let network: Cidr = "10.0.0.0/30".parse().unwrap();
+    assert_eq!(host_address(&network, 1), Some("10.0.0.1".parse().unwrap()));
+    assert_eq!(host_address(&network, 2), Some("10.0.0.2".parse().unwrap()));
+    assert_eq!(host_address(&network, 3), None);
+    let network: Cidr = "fd00::1/64".parse().unwrap();
+    assert_eq!(host_address(&network, 2), Some("fd00::2".parse().unwrap()));
+    let network: Cidr = "::/0".parse().unwrap();
+    assert_eq!(host_address(&network, 1), Some("::1".parse().unwrap()));
+}
+
+#[test]
+fn test_genconf_generate() {
+    let mut options = Genconf {
+        endpoint: "vpn.example.com:51821".into(),
+        network: "10.1.0.0/24".into(),
+        peers: vec!["office".into(), "warehouse".into()],
+        allowed_ips: Vec::new(),
+        dns: vec!["10.1.0.1".into()],
+        keepalive: Some(25),
+        preshared_keys: true,
+        qr: false,
+        output: ".".into(),
+    };
+    let (server, peers) = generate(&options).unwrap();
+    assert_eq!(server.interface.listen_port, Some(51821));
+    assert_eq!(server.interface.address[0].to_string(), "10.1.0.1/24");
+    assert_eq!(server.peers.len(), 2);
+    assert_eq!(peers.len(), 2);
+    for ((entry, peer), address) in server
+        .peers
+        .iter()
+        .zip(&peers)
+        .zip(["10.1.0.2", "10.1.0.3"])
+    {
+        assert_eq!(entry.public_key, peer.interface.private_key.pubkey());
+        assert_eq!(entry.allowed_ips.to_string(), format!("{address}/32"));
+        assert_eq!(
+            peer.interface.address[0].to_string(),
+            format!("{address}/24")
+        );
+        assert_eq!(peer.interface.dns, vec!["10.1.0.1"]);
+        let server_entry = &peer.peers[0];
+        assert_eq!(
+            server_entry.public_key,
+            server.interface.private_key.pubkey()
+        );
+        assert_eq!(server_entry.preshared_key, entry.preshared_key);
+        assert!(entry.preshared_key.is_some());
+        assert_eq!(server_entry.allowed_ips.to_string(), "10.1.0.0/24");
+        assert_eq!(server_entry.endpoint.as_ref().unwrap().port(), 51821);
+        assert_eq!(server_entry.persistent_keepalive, Some(25));
+        assert_eq!(TunnelConfig::parse(&peer.to_string()).unwrap(), *peer);
+    }
+    assert_eq!(TunnelConfig::parse(&server.to_string()).unwrap(), server);
+
+    options.allowed_ips = vec!["0.0.0.0/0".into()];
+    let (_, peers) = generate(&options).unwrap();
+    assert_eq!(peers[0].peers[0].allowed_ips.to_string(), "0.0.0.0/0");
+    options.allowed_ips = vec!["nowhere".into()];
+    assert!(matches!(generate(&options), Err(Error::Build(_))));
+    options.allowed_ips.clear();
+
+    options.network = "10.1.0.0/30".into();
+    assert!(matches!(generate(&options), Err(Error::Network(_))));
+    options.network = "10.1.0.0/29".into();
+    options.peers.push("office".into());
+    assert!(matches!(generate(&options), Err(Error::PeerName(_))));
+    options.peers = vec!["../etc".into()];
+    assert!(matches!(generate(&options), Err(Error::PeerName(_))));
+    options.peers = vec!["server".into()];
+    assert!(matches!(generate(&options), Err(Error::PeerName(_))));
+}
+
+#[test]
+fn test_verify() {
+    let privkey = Privkey::generate();
+    let input = format!("{}\n", privkey.to_base64());
+    let pubkey = privkey.pubkey();
+    assert!(verify(&pubkey.to_base64(), &mut input.as_bytes()).is_ok());
+    assert!(verify(&pubkey.to_hex(), &mut input.as_bytes()).is_ok());
+    let other = Privkey::generate().pubkey().to_base64();
+    assert!(matches!(
+        verify(&other, &mut input.as_bytes()),
+        Err(Error::Mismatch)
+    ));
+    assert!(matches!(
+        verify("abc", &mut input.as_bytes()),
+        Err(Error::Format)
+    ));
+    assert!(matches!(
+        verify(&other, &mut "abc".as_bytes()),
+        Err(Error::Format)
+    ));
+}
+
+#[test]
+fn test_check_configs() {
+    let options = Genconf {
+        endpoint: "vpn.example.com:51820".into(),
+        network: "10.0.0.0/24".into(),
+        peers: vec!["office".into(), "warehouse".into()],
+        allowed_ips: Vec::new(),
+        dns: Vec::new(),
+        keepalive: None,
+        preshared_keys: false,
+        qr: false,
+        output: ".".into(),
+    };
+    let (server, peers) = generate(&options).unwrap();
+    let mut configs = vec![
+        ("server.conf", server),
+        ("office.conf", peers[0].clone()),
+        ("warehouse.conf", peers[1].clone()),
+    ];
+    let mut problems = Vec::new();
+    assert_eq!(check_configs(&configs, |problem| problems.push(problem)), 0);
+
+    // the server lists a public key which belongs to a different private key
+    configs[0].1.peers[1].public_key = Privkey::generate().pubkey();
+    let own_key = configs[1].1.interface.private_key.pubkey();
+    configs[1].1.peers.push(Peer::new(own_key));
+    assert_eq!(check_configs(&configs, |problem| problems.push(problem)), 2);
+    assert_eq!(
+        problems,
+        vec![
+            "warehouse.conf has server.conf as peer, but not the other way around",
+            "office.conf: peer 1 has the public key of the interface",
+        ]
+    );
+}
+
+#[test]
+fn test_dh_parse() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        Dh::parse(&args(&["--privkey", "-", "--pubkey", "abc", DH_CONFIRM])),
+        Some(Dh {
+            privkey: "-".into(),
+            pubkey: "abc".into(),
+            confirmed: true,
+        })
+    );
+    assert!(
+        !Dh::parse(&args(&["--pubkey", "abc", "--privkey", "key"]))
+            .unwrap()
+            .confirmed
+    );
+    assert_eq!(Dh::parse(&args(&["--privkey", "key", DH_CONFIRM])), None);
+    assert_eq!(Dh::parse(&args(&["--privkey", "key", "--pubkey"])), None);
+    assert_eq!(Dh::parse(&args(&["--pubkey", "abc", "key"])), None);
+
+    let options = Dh::parse(&args(&["--privkey", "-", "--pubkey", "abc"])).unwrap();
+    assert!(matches!(dh(&options), Err(Error::Unconfirmed)));
+}
+
+#[test]
+fn test_shared_secret() {
+    let privkey = Privkey::generate();
+    let other = Privkey::generate();
+    let input = format!("{}\n", privkey.to_base64());
+    let mut output = Vec::new();
+    shared_secret(&other.pubkey(), &mut input.as_bytes(), &mut output).unwrap();
+    let expected = other.diffie_hellman(&privkey.pubkey());
+    let expected = base64::encode(expected.as_bytes());
+    assert_eq!(output, format!("{}\n", expected).into_bytes());
+
+    let mut output = Vec::new();
+    assert!(matches!(
+        shared_secret(&Pubkey::new([0; 32]), &mut input.as_bytes(), &mut output),
+        Err(Error::LowOrder)
+    ));
+    assert!(matches!(
+        shared_secret(&other.pubkey(), &mut "abc".as_bytes(), &mut output),
+        Err(Error::Format)
+    ));
+    assert!(output.is_empty());
+}
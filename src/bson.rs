@@ -0,0 +1,183 @@
+//! Conversions between keys and [BSON](https://docs.rs/bson) binary values, for storing keys in
+//! MongoDB. Keys are always stored with the generic binary subtype, and reading keys with any
+//! other subtype fails, so that keys cannot be mixed up with UUIDs or encrypted values.
+//!
+//! Fields, including the document `_id`, can be stored as binary with [as_binary]:
+//!
+//! ```
+//! use bson::{doc, spec::BinarySubtype, Binary, Bson};
+//! use serde::{Deserialize, Serialize};
+//! use wireguard_keys::Pubkey;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Peer {
+//!     #[serde(rename = "_id", with = "wireguard_keys::bson::as_binary")]
+//!     pubkey: Pubkey,
+//!     name: String,
+//! }
+//!
+//! let pubkey = Pubkey::new([1; 32]);
+//! let peer = bson::to_document(&Peer { pubkey, name: "laptop".into() }).unwrap();
+//! assert_eq!(peer.get("_id"), Some(&Bson::Binary(pubkey.into())));
+//! assert_eq!(pubkey.id_filter(), doc! { "_id": Binary::from(pubkey) });
+//! ```
+
+use crate::{ParseError, Privkey, Pubkey, Secret, Token32};
+use bson::spec::BinarySubtype;
+use bson::{doc, Binary, Bson, Document};
+use thiserror::Error;
+
+/// Possible errors that can be generated when converting BSON values to keys.
+#[derive(Error, Debug)]
+pub enum BsonError {
+    /// Value is not binary
+    #[error("value is not binary")]
+    Type,
+    /// Binary value does not have the generic subtype
+    #[error("unexpected binary subtype {0:?}")]
+    Subtype(BinarySubtype),
+    /// Binary value is not a valid key
+    #[error("error parsing key")]
+    Parse(#[from] ParseError),
+}
+
+macro_rules! impl_bson {
+    ($type:ty) => {
+        impl From<$type> for Binary {
+            fn from(key: $type) -> Self {
+                Binary {
+                    subtype: BinarySubtype::Generic,
+                    bytes: key.to_vec(),
+                }
+            }
+        }
+
+        impl From<$type> for Bson {
+            fn from(key: $type) -> Self {
+                Bson::Binary(key.into())
+            }
+        }
+
+        impl TryFrom<&Binary> for $type {
+            type Error = BsonError;
+            fn try_from(binary: &Binary) -> Result<Self, Self::Error> {
+                if binary.subtype != BinarySubtype::Generic {
+                    return Err(BsonError::Subtype(binary.subtype));
+                }
+                Ok(<$type>::try_from(&binary.bytes[..])?)
+            }
+        }
+
+        impl TryFrom<&Bson> for $type {
+            type Error = BsonError;
+            fn try_from(value: &Bson) -> Result<Self, Self::Error> {
+                match value {
+                    Bson::Binary(binary) => binary.try_into(),
+                    _ => Err(BsonError::Type),
+                }
+            }
+        }
+    };
+}
+
+impl_bson!(Pubkey);
+impl_bson!(Privkey);
+impl_bson!(Secret);
+impl_bson!(Token32);
+
+impl Pubkey {
+    /// Filter matching the document with this key as `_id`, for use in MongoDB queries.
+    pub fn id_filter(&self) -> Document {
+        doc! { "_id": Binary::from(*self) }
+    }
+}
+
+/// Serialize and deserialize keys as BSON binary with the generic subtype, for use with
+/// `#[serde(with = "wireguard_keys::bson::as_binary")]`.
+pub mod as_binary {
+    use super::*;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialize key as BSON binary.
+    pub fn serialize<K, S>(key: &K, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Clone + Into<Binary>,
+        S: Serializer,
+    {
+        key.clone().into().serialize(serializer)
+    }
+
+    /// Deserialize key from BSON binary, which must have the generic subtype.
+    pub fn deserialize<'de, K, D>(deserializer: D) -> Result<K, D::Error>
+    where
+        K: for<'a> TryFrom<&'a Binary, Error = BsonError>,
+        D: Deserializer<'de>,
+    {
+        let mut binary = Binary::deserialize(deserializer)?;
+        let result = K::try_from(&binary).map_err(D::Error::custom);
+        zeroize::Zeroize::zeroize(&mut binary.bytes);
+        result
+    }
+}
+
+#[test]
+fn test_bson_binary() {
+    let pubkey = Pubkey::new([1; 32]);
+    let binary = Binary::from(pubkey);
+    assert_eq!(binary.subtype, BinarySubtype::Generic);
+    assert_eq!(Pubkey::try_from(&binary).unwrap(), pubkey);
+    assert_eq!(Pubkey::try_from(&Bson::from(pubkey)).unwrap(), pubkey);
+    let uuid = Binary {
+        subtype: BinarySubtype::Uuid,
+        bytes: vec![1; 32],
+    };
+    assert!(matches!(
+        Secret::try_from(&uuid),
+        Err(BsonError::Subtype(BinarySubtype::Uuid))
+    ));
+    let short = Binary {
+        subtype: BinarySubtype::Generic,
+        bytes: vec![1; 16],
+    };
+    assert!(matches!(
+        Privkey::try_from(&short),
+        Err(BsonError::Parse(ParseError::Length))
+    ));
+    assert!(matches!(
+        Token32::try_from(&Bson::String("abc".into())),
+        Err(BsonError::Type)
+    ));
+}
+
+#[test]
+fn test_bson_document() {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Peer {
+        #[serde(rename = "_id", with = "as_binary")]
+        pubkey: Pubkey,
+        #[serde(with = "as_binary")]
+        preshared_key: Secret,
+    }
+
+    let peer = Peer {
+        pubkey: Pubkey::new([1; 32]),
+        preshared_key: Secret::new([2; 32]),
+    };
+    let document = bson::to_document(&peer).unwrap();
+    assert_eq!(
+        document,
+        doc! {
+            "_id": Binary::from(peer.pubkey),
+            "preshared_key": Binary::from(peer.preshared_key),
+        }
+    );
+    assert_eq!(bson::from_document::<Peer>(document).unwrap(), peer);
+    let mismatched = doc! {
+        "_id": Binary { subtype: BinarySubtype::Uuid, bytes: vec![1; 32] },
+        "preshared_key": Binary::from(peer.preshared_key),
+    };
+    assert!(bson::from_document::<Peer>(mismatched).is_err());
+}
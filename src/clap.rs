@@ -0,0 +1,187 @@
+//! Typed command line arguments for [clap](https://docs.rs/clap). The key types can be used with
+//! `value_parser!`, and invalid keys are reported with the encodings which are accepted:
+//!
+//! ```
+//! use clap::{value_parser, Arg, Command};
+//! use wireguard_keys::Pubkey;
+//!
+//! let command = Command::new("peer").arg(
+//!     Arg::new("pubkey")
+//!         .long("pubkey")
+//!         .value_parser(value_parser!(Pubkey)),
+//! );
+//! let matches = command
+//!     .try_get_matches_from(["peer", "--pubkey", "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg="])
+//!     .unwrap();
+//! let pubkey: &Pubkey = matches.get_one("pubkey").unwrap();
+//! ```
+//!
+//! Error messages never include the rejected value, since it might be a private key. Keep in mind
+//! that clap keeps its own copies of parsed values and of the arguments, which are not zeroized.
+//! Private keys should rather be read from files or standard input.
+
+use crate::{Encoding, ParseError, Privkey, Pubkey, Secret, Token32};
+use clap::builder::{TypedValueParser, ValueParserFactory};
+use clap::error::ErrorKind;
+use clap::{Arg, Command};
+use std::ffi::OsStr;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// Parser for arguments containing keys of type `K`, in any enabled encoding.
+#[derive(Debug)]
+pub struct KeyValueParser<K>(PhantomData<fn() -> K>);
+
+impl<K> KeyValueParser<K> {
+    /// Create new parser.
+    pub fn new() -> Self {
+        KeyValueParser(PhantomData)
+    }
+}
+
+impl<K> Default for KeyValueParser<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> Clone for KeyValueParser<K> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<K> TypedValueParser for KeyValueParser<K>
+where
+    K: FromStr<Err = ParseError> + Clone + Send + Sync + 'static,
+{
+    type Value = K;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let arg = arg.map(|arg| format!(" for '{arg}'")).unwrap_or_default();
+        let value = value.to_str().ok_or_else(|| {
+            clap::Error::raw(ErrorKind::InvalidUtf8, format!("invalid UTF-8{arg}\n")).with_cmd(cmd)
+        })?;
+        value.parse().map_err(|error| {
+            let message = format!("invalid key{arg}: {error}\n\n{}\n", expected());
+            clap::Error::raw(ErrorKind::ValueValidation, message).with_cmd(cmd)
+        })
+    }
+}
+
+/// Describe the encodings accepted when parsing keys.
+fn expected() -> String {
+    let names: Vec<&str> = Encoding::ALL.iter().copied().map(encoding_name).collect();
+    match names.split_last() {
+        None => "no encodings are enabled".into(),
+        Some((last, [])) => format!("expected a key in {last}"),
+        Some((last, names)) => format!("expected a key in {} or {last}", names.join(", ")),
+    }
+}
+
+fn encoding_name(encoding: Encoding) -> &'static str {
+    match encoding {
+        #[cfg(feature = "hex")]
+        Encoding::Hex => "hex",
+        #[cfg(feature = "hex")]
+        Encoding::HexUpper => "uppercase hex",
+        #[cfg(feature = "hex")]
+        Encoding::Fingerprint => "colon-separated hex",
+        #[cfg(feature = "base64")]
+        Encoding::Base64 => "base64",
+        #[cfg(feature = "base64")]
+        Encoding::Base64Urlsafe => "urlsafe base64",
+        #[cfg(feature = "base64")]
+        Encoding::Base64Unpadded => "unpadded base64",
+        #[cfg(feature = "base32")]
+        Encoding::Base32 => "base32",
+        #[cfg(feature = "base32")]
+        Encoding::ZBase32 => "z-base-32",
+        #[cfg(feature = "base58")]
+        Encoding::Base58 => "base58",
+        #[cfg(feature = "proquint")]
+        Encoding::Proquint => "proquints",
+    }
+}
+
+macro_rules! impl_clap {
+    ($type:ty) => {
+        impl ValueParserFactory for $type {
+            type Parser = KeyValueParser<$type>;
+
+            fn value_parser() -> Self::Parser {
+                KeyValueParser::new()
+            }
+        }
+    };
+}
+
+impl_clap!(Pubkey);
+impl_clap!(Privkey);
+impl_clap!(Secret);
+impl_clap!(Token32);
+
+#[test]
+fn test_clap_value_parser() {
+    use clap::value_parser;
+
+    let command = Command::new("peer")
+        .arg(
+            Arg::new("pubkey")
+                .long("pubkey")
+                .value_parser(value_parser!(Pubkey)),
+        )
+        .arg(
+            Arg::new("preshared-key")
+                .long("preshared-key")
+                .value_parser(value_parser!(Secret)),
+        );
+    #[cfg(feature = "base64")]
+    {
+        let pubkey = Pubkey::new([0xfb; 32]);
+        let secret = Secret::new([0xfc; 32]);
+        let matches = command
+            .clone()
+            .try_get_matches_from([
+                "peer",
+                "--pubkey",
+                &pubkey.to_base64(),
+                "--preshared-key",
+                &secret.to_base64(),
+            ])
+            .unwrap();
+        assert_eq!(matches.get_one::<Pubkey>("pubkey"), Some(&pubkey));
+        assert_eq!(matches.get_one::<Secret>("preshared-key"), Some(&secret));
+    }
+
+    let error = command
+        .try_get_matches_from(["peer", "--preshared-key", "secret-value"])
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ValueValidation);
+    let message = error.to_string();
+    assert!(message.contains("invalid key for '--preshared-key <preshared-key>'"));
+    assert!(message.contains(&expected()));
+    assert!(!message.contains("secret-value"));
+}
+
+#[test]
+fn test_clap_expected() {
+    let expected = expected();
+    #[cfg(feature = "base64")]
+    assert!(expected.contains("urlsafe base64"));
+    #[cfg(feature = "hex")]
+    assert!(expected.starts_with("expected a key in hex, "));
+    #[cfg(not(any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )))]
+    assert_eq!(expected, "no encodings are enabled");
+}
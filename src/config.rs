@@ -0,0 +1,1390 @@
+//! Typed model of wg-quick configuration files. A configuration consists of a single
+//! `[Interface]` section and any number of `[Peer]` sections, which are represented by the
+//! [Interface] and [Peer] types respectively and aggregated in a [TunnelConfig].
+//!
+//! Parsing follows the rules of `wg` and `wg-quick`: section names and keys are
+//! case-insensitive, everything after a `#` is a comment, and list-valued keys such as
+//! `Address` or `AllowedIPs` may be given multiple times. A comment on the line directly above
+//! a `[Peer]` header is taken to be the name of that peer.
+//!
+//! Configurations are written using the [Display](std::fmt::Display) implementations. The
+//! output is canonical: keys are always written in the same order, lists are joined on a single
+//! line and peers are written in the order they appear in, so that the same configuration
+//! always produces the same text.
+//!
+//! [TunnelConfig::validate] checks a configuration for likely mistakes, such as duplicate peers
+//! or overlapping allowed IPs.
+//!
+//! [TunnelConfig::diff] computes a structured list of changes between two configurations,
+//! which unlike a textual diff distinguishes key rotations from formatting changes.
+//!
+//! Sections can also be created programmatically using [Interface::builder] and
+//! [Peer::builder], which validate all values when building.
+
+use crate::allowed_ips::{AllowedIps, Cidr, CidrError};
+use crate::endpoint::{Endpoint, EndpointError};
+use crate::{Privkey, Pubkey, Secret};
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::{FromStr, ParseBoolError};
+use thiserror::Error;
+
+/// Possible errors that can be generated when parsing wg-quick configuration files.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Line is neither a section header nor a key-value pair
+    #[error("line {line}: syntax error")]
+    Syntax { line: usize },
+    /// Section is not known
+    #[error("line {line}: unknown section {section:?}")]
+    Section { line: usize, section: String },
+    /// Key-value pair before the first section
+    #[error("line {line}: key outside of section")]
+    NoSection { line: usize },
+    /// Key is not valid in this section
+    #[error("line {line}: unknown key {key:?}")]
+    Key { line: usize, key: String },
+    /// Value of key is invalid
+    #[error("line {line}: invalid value for {key}")]
+    Value {
+        line: usize,
+        key: String,
+        #[source]
+        error: ValueError,
+    },
+    /// Configuration has more than one interface section
+    #[error("line {line}: duplicate interface section")]
+    DuplicateInterface { line: usize },
+    /// Configuration has no interface section
+    #[error("missing interface section")]
+    MissingInterface,
+    /// Section is missing a required key
+    #[error("line {line}: section is missing {key}")]
+    Missing { line: usize, key: &'static str },
+}
+
+/// Reasons for a value in a configuration file to be invalid, the source of
+/// [ConfigError::Value].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// Key is not valid in any encoding. Details are left out, since they could reveal
+    /// characters of a private key.
+    #[error("invalid key")]
+    Key,
+    /// Number is not valid or out of range
+    #[error(transparent)]
+    Number(#[from] ParseIntError),
+    /// Boolean is neither `true` nor `false`
+    #[error(transparent)]
+    Bool(#[from] ParseBoolError),
+    /// Address or network is not valid
+    #[error(transparent)]
+    Cidr(#[from] CidrError),
+    /// Endpoint is not valid
+    #[error(transparent)]
+    Endpoint(#[from] EndpointError),
+    /// Value is neither a number nor `off`
+    #[error("expected number or off")]
+    Off,
+}
+
+/// Possible errors that can be generated when building configuration sections.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// Required field was not set
+    #[error("missing {0}")]
+    Missing(&'static str),
+    /// Allowed IP is not a valid network
+    #[error("invalid allowed IP {value:?}")]
+    AllowedIp {
+        value: String,
+        #[source]
+        error: CidrError,
+    },
+    /// Endpoint is not a valid endpoint
+    #[error("invalid endpoint {value:?}")]
+    Endpoint {
+        value: String,
+        #[source]
+        error: EndpointError,
+    },
+    /// Address is not a valid address in CIDR notation
+    #[error("invalid address {value:?}")]
+    Address {
+        value: String,
+        #[source]
+        error: CidrError,
+    },
+    /// DNS server or search domain is empty or contains separators
+    #[error("invalid DNS entry {0:?}")]
+    Dns(String),
+    /// MTU is too small for the addresses of the interface, or too large
+    #[error("invalid MTU {0}")]
+    Mtu(u32),
+    /// Hook command spans multiple lines
+    #[error("hook command contains a line break")]
+    Hook,
+    /// Routing table is empty or contains control characters
+    #[error("invalid routing table {0:?}")]
+    Table(String),
+}
+
+/// Interface section of a wg-quick configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interface {
+    /// Private key of the interface.
+    pub private_key: Privkey,
+    /// Addresses assigned to the interface.
+    pub address: Vec<Cidr>,
+    /// Port to listen on, chosen randomly if not set.
+    pub listen_port: Option<u16>,
+    /// Firewall mark for outgoing packets.
+    pub fwmark: Option<u32>,
+    /// DNS servers and search domains.
+    pub dns: Vec<String>,
+    /// MTU of the interface.
+    pub mtu: Option<u32>,
+    /// Routing table to add routes to.
+    pub table: Option<String>,
+    /// Commands executed before bringing the interface up.
+    pub pre_up: Vec<String>,
+    /// Commands executed after bringing the interface up.
+    pub post_up: Vec<String>,
+    /// Commands executed before bringing the interface down.
+    pub pre_down: Vec<String>,
+    /// Commands executed after bringing the interface down.
+    pub post_down: Vec<String>,
+    /// Save the configuration on shutdown.
+    pub save_config: bool,
+}
+
+impl Interface {
+    /// Create new interface section with the given private key and no other settings.
+    pub fn new(private_key: Privkey) -> Self {
+        Interface {
+            private_key,
+            address: Vec::new(),
+            listen_port: None,
+            fwmark: None,
+            dns: Vec::new(),
+            mtu: None,
+            table: None,
+            pre_up: Vec::new(),
+            post_up: Vec::new(),
+            pre_down: Vec::new(),
+            post_down: Vec::new(),
+            save_config: false,
+        }
+    }
+
+    /// Create builder for an interface section. Values are validated when calling
+    /// [build](InterfaceBuilder::build).
+    pub fn builder() -> InterfaceBuilder {
+        InterfaceBuilder::default()
+    }
+
+    /// First setting which only wg-quick can apply, such as hook commands. Used by the
+    /// exporters to other network managers.
+    pub(crate) fn wg_quick_setting(&self) -> Option<&'static str> {
+        [
+            ("PreUp", &self.pre_up),
+            ("PostUp", &self.post_up),
+            ("PreDown", &self.pre_down),
+            ("PostDown", &self.post_down),
+        ]
+        .into_iter()
+        .find(|(_, commands)| !commands.is_empty())
+        .map(|(setting, _)| setting)
+        .or(self.save_config.then_some("SaveConfig"))
+    }
+}
+
+/// Minimum MTU of interfaces with only IPv4 addresses.
+const MIN_MTU_IPV4: u32 = 576;
+
+/// Minimum MTU of interfaces with IPv6 addresses.
+const MIN_MTU_IPV6: u32 = 1280;
+
+/// Maximum MTU of interfaces.
+const MAX_MTU: u32 = 65535;
+
+/// Builder for [Interface] sections.
+#[derive(Clone, Debug, Default)]
+pub struct InterfaceBuilder {
+    private_key: Option<Privkey>,
+    address: Vec<String>,
+    listen_port: Option<u16>,
+    fwmark: Option<u32>,
+    dns: Vec<String>,
+    mtu: Option<u32>,
+    table: Option<String>,
+    pre_up: Vec<String>,
+    post_up: Vec<String>,
+    pre_down: Vec<String>,
+    post_down: Vec<String>,
+    save_config: bool,
+}
+
+impl InterfaceBuilder {
+    /// Set private key of the interface, which is required.
+    pub fn private_key(mut self, private_key: Privkey) -> Self {
+        self.private_key = Some(private_key);
+        self
+    }
+
+    /// Add address, given as a [Cidr] or in CIDR notation.
+    pub fn address(mut self, address: impl ToString) -> Self {
+        self.address.push(address.to_string());
+        self
+    }
+
+    /// Set port to listen on, where `0` chooses one randomly.
+    pub fn listen_port(mut self, port: u16) -> Self {
+        self.listen_port = Some(port).filter(|port| *port != 0);
+        self
+    }
+
+    /// Set firewall mark for outgoing packets, where `0` disables it.
+    pub fn fwmark(mut self, fwmark: u32) -> Self {
+        self.fwmark = Some(fwmark).filter(|fwmark| *fwmark != 0);
+        self
+    }
+
+    /// Add DNS server or search domain.
+    pub fn dns(mut self, dns: impl ToString) -> Self {
+        self.dns.push(dns.to_string());
+        self
+    }
+
+    /// Set MTU of the interface.
+    pub fn mtu(mut self, mtu: u32) -> Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    /// Set routing table to add routes to.
+    pub fn table(mut self, table: impl Into<String>) -> Self {
+        self.table = Some(table.into());
+        self
+    }
+
+    /// Add command executed before bringing the interface up.
+    pub fn pre_up(mut self, command: impl Into<String>) -> Self {
+        self.pre_up.push(command.into());
+        self
+    }
+
+    /// Add command executed after bringing the interface up.
+    pub fn post_up(mut self, command: impl Into<String>) -> Self {
+        self.post_up.push(command.into());
+        self
+    }
+
+    /// Add command executed before bringing the interface down.
+    pub fn pre_down(mut self, command: impl Into<String>) -> Self {
+        self.pre_down.push(command.into());
+        self
+    }
+
+    /// Add command executed after bringing the interface down.
+    pub fn post_down(mut self, command: impl Into<String>) -> Self {
+        self.post_down.push(command.into());
+        self
+    }
+
+    /// Save the configuration on shutdown.
+    pub fn save_config(mut self, save_config: bool) -> Self {
+        self.save_config = save_config;
+        self
+    }
+
+    /// Validate values and build interface section. The wg-quick text of the section can be
+    /// produced with its [Display](fmt::Display) implementation.
+    pub fn build(self) -> Result<Interface, BuildError> {
+        let private_key = self.private_key.ok_or(BuildError::Missing("private key"))?;
+        let address: Vec<Cidr> = self
+            .address
+            .into_iter()
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|error| BuildError::Address { value, error })
+            })
+            .collect::<Result<_, _>>()?;
+        if let Some(entry) = self.dns.iter().find(|entry| {
+            entry.is_empty() || entry.contains(|c: char| c == ',' || c.is_whitespace())
+        }) {
+            return Err(BuildError::Dns(entry.clone()));
+        }
+        if let Some(mtu) = self.mtu {
+            let min = if address.iter().any(Cidr::is_ipv6) {
+                MIN_MTU_IPV6
+            } else {
+                MIN_MTU_IPV4
+            };
+            if !(min..=MAX_MTU).contains(&mtu) {
+                return Err(BuildError::Mtu(mtu));
+            }
+        }
+        let hooks = [&self.pre_up, &self.post_up, &self.pre_down, &self.post_down];
+        if hooks
+            .iter()
+            .flat_map(|commands| commands.iter())
+            .any(|command| command.contains(['\r', '\n']))
+        {
+            return Err(BuildError::Hook);
+        }
+        if let Some(table) = &self.table {
+            if table.trim().is_empty() || table.contains(char::is_control) {
+                return Err(BuildError::Table(table.clone()));
+            }
+        }
+        Ok(Interface {
+            private_key,
+            address,
+            listen_port: self.listen_port,
+            fwmark: self.fwmark,
+            dns: self.dns,
+            mtu: self.mtu,
+            table: self.table,
+            pre_up: self.pre_up,
+            post_up: self.post_up,
+            pre_down: self.pre_down,
+            post_down: self.post_down,
+            save_config: self.save_config,
+        })
+    }
+}
+
+/// Peer section of a wg-quick configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Peer {
+    /// Name of the peer, written as a comment above the section.
+    pub name: Option<String>,
+    /// Public key of the peer.
+    pub public_key: Pubkey,
+    /// Optional preshared key.
+    pub preshared_key: Option<Secret>,
+    /// Addresses the peer is allowed to send from, and which are routed to it.
+    pub allowed_ips: AllowedIps,
+    /// Endpoint of the peer.
+    pub endpoint: Option<Endpoint>,
+    /// Interval in seconds at which keepalive packets are sent.
+    pub persistent_keepalive: Option<u16>,
+}
+
+impl Peer {
+    /// Create new peer section with the given public key and no other settings.
+    pub fn new(public_key: Pubkey) -> Self {
+        Peer {
+            name: None,
+            public_key,
+            preshared_key: None,
+            allowed_ips: AllowedIps::new(),
+            endpoint: None,
+            persistent_keepalive: None,
+        }
+    }
+
+    /// Create builder for a peer section. Values are validated when calling
+    /// [build](PeerBuilder::build).
+    pub fn builder() -> PeerBuilder {
+        PeerBuilder::default()
+    }
+}
+
+/// Builder for [Peer] sections.
+#[derive(Clone, Debug, Default)]
+pub struct PeerBuilder {
+    name: Option<String>,
+    public_key: Option<Pubkey>,
+    preshared_key: Option<Secret>,
+    allowed_ips: Vec<String>,
+    endpoint: Option<String>,
+    persistent_keepalive: Option<u16>,
+}
+
+impl PeerBuilder {
+    /// Set name of the peer.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set public key of the peer, which is required.
+    pub fn pubkey(mut self, public_key: Pubkey) -> Self {
+        self.public_key = Some(public_key);
+        self
+    }
+
+    /// Set preshared key.
+    pub fn preshared_key(mut self, preshared_key: Secret) -> Self {
+        self.preshared_key = Some(preshared_key);
+        self
+    }
+
+    /// Add allowed IP, given as a [Cidr] or in CIDR notation.
+    pub fn allowed_ip(mut self, allowed_ip: impl ToString) -> Self {
+        self.allowed_ips.push(allowed_ip.to_string());
+        self
+    }
+
+    /// Set endpoint, given as an [Endpoint] or as `host:port`.
+    pub fn endpoint(mut self, endpoint: impl ToString) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Set persistent keepalive interval in seconds, where `0` disables it.
+    pub fn keepalive(mut self, interval: u16) -> Self {
+        self.persistent_keepalive = Some(interval).filter(|interval| *interval != 0);
+        self
+    }
+
+    /// Validate values and build peer section.
+    pub fn build(self) -> Result<Peer, BuildError> {
+        let public_key = self.public_key.ok_or(BuildError::Missing("public key"))?;
+        let allowed_ips = self
+            .allowed_ips
+            .into_iter()
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|error| BuildError::AllowedIp { value, error })
+            })
+            .collect::<Result<_, _>>()?;
+        let endpoint = self
+            .endpoint
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|error| BuildError::Endpoint { value, error })
+            })
+            .transpose()?;
+        Ok(Peer {
+            name: self.name,
+            public_key,
+            preshared_key: self.preshared_key,
+            allowed_ips,
+            endpoint,
+            persistent_keepalive: self.persistent_keepalive,
+        })
+    }
+}
+
+/// Complete wg-quick configuration, consisting of an interface and its peers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TunnelConfig {
+    /// Interface section.
+    pub interface: Interface,
+    /// Peer sections.
+    pub peers: Vec<Peer>,
+}
+
+/// Problem found when validating a [TunnelConfig]. Peers are identified by their index in
+/// [TunnelConfig::peers].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// Two peers have the same public key
+    #[error("peers {first} and {second} have the same public key")]
+    DuplicatePeer { first: usize, second: usize },
+    /// Peer has the public key of the interface itself
+    #[error("peer {peer} has the public key of the interface")]
+    OwnKey { peer: usize },
+    /// Allowed IPs of two peers overlap, so traffic is only routed to one of them
+    #[error(
+        "allowed IP {first_network} of peer {first} overlaps {second_network} of peer {second}"
+    )]
+    OverlappingAllowedIps {
+        first: usize,
+        first_network: Cidr,
+        second: usize,
+        second_network: Cidr,
+    },
+    /// Peer has a persistent keepalive but no endpoint to send it to
+    #[error("peer {peer} has a persistent keepalive but no endpoint")]
+    MissingEndpoint { peer: usize },
+}
+
+/// Difference between two [TunnelConfig]s, as produced by [TunnelConfig::diff]. Peers are
+/// identified by their public key, and private keys are never included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Private key of the interface was rotated, given as the old and new public keys
+    PrivateKeyRotated { old: Pubkey, new: Pubkey },
+    /// Setting of the interface other than the private key changed, such as `ListenPort`
+    InterfaceSetting(&'static str),
+    /// Peer was added
+    PeerAdded(Pubkey),
+    /// Peer was removed
+    PeerRemoved(Pubkey),
+    /// Preshared key of the peer was added, removed or changed
+    PresharedKeyChanged(Pubkey),
+    /// Allowed IPs of the peer changed
+    AllowedIpsChanged {
+        peer: Pubkey,
+        added: Vec<Cidr>,
+        removed: Vec<Cidr>,
+    },
+    /// Endpoint of the peer changed
+    EndpointChanged {
+        peer: Pubkey,
+        old: Option<Endpoint>,
+        new: Option<Endpoint>,
+    },
+    /// Persistent keepalive interval of the peer changed
+    KeepaliveChanged {
+        peer: Pubkey,
+        old: Option<u16>,
+        new: Option<u16>,
+    },
+}
+
+/// Compare interface settings other than the private key.
+fn diff_interface(old: &Interface, new: &Interface, changes: &mut Vec<Change>) {
+    let mut compare = |setting, changed: bool| {
+        if changed {
+            changes.push(Change::InterfaceSetting(setting));
+        }
+    };
+    compare("Address", !same_items(&old.address, &new.address));
+    compare("ListenPort", old.listen_port != new.listen_port);
+    compare("FwMark", old.fwmark != new.fwmark);
+    compare("DNS", old.dns != new.dns);
+    compare("MTU", old.mtu != new.mtu);
+    compare("Table", old.table != new.table);
+    compare("PreUp", old.pre_up != new.pre_up);
+    compare("PostUp", old.post_up != new.post_up);
+    compare("PreDown", old.pre_down != new.pre_down);
+    compare("PostDown", old.post_down != new.post_down);
+    compare("SaveConfig", old.save_config != new.save_config);
+}
+
+/// Compare settings of a peer which is present in both configurations.
+fn diff_peer(old: &Peer, new: &Peer, changes: &mut Vec<Change>) {
+    let peer = new.public_key;
+    if old.preshared_key != new.preshared_key {
+        changes.push(Change::PresharedKeyChanged(peer));
+    }
+    let added: Vec<Cidr> = new
+        .allowed_ips
+        .iter()
+        .filter(|cidr| !old.allowed_ips.iter().any(|old| old == *cidr))
+        .copied()
+        .collect();
+    let removed: Vec<Cidr> = old
+        .allowed_ips
+        .iter()
+        .filter(|cidr| !new.allowed_ips.iter().any(|new| new == *cidr))
+        .copied()
+        .collect();
+    if !added.is_empty() || !removed.is_empty() {
+        changes.push(Change::AllowedIpsChanged {
+            peer,
+            added,
+            removed,
+        });
+    }
+    if old.endpoint != new.endpoint {
+        changes.push(Change::EndpointChanged {
+            peer,
+            old: old.endpoint.clone(),
+            new: new.endpoint.clone(),
+        });
+    }
+    if old.persistent_keepalive != new.persistent_keepalive {
+        changes.push(Change::KeepaliveChanged {
+            peer,
+            old: old.persistent_keepalive,
+            new: new.persistent_keepalive,
+        });
+    }
+}
+
+/// Determine if two lists contain the same items, ignoring order.
+fn same_items<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+    a.iter().all(|item| b.contains(item)) && b.iter().all(|item| a.contains(item))
+}
+
+/// Section currently being parsed. Keys are optional until the section is complete.
+enum Section {
+    None,
+    Interface {
+        line: usize,
+        private_key: Option<Privkey>,
+        interface: Interface,
+    },
+    Peer {
+        line: usize,
+        public_key: Option<Pubkey>,
+        peer: Peer,
+    },
+}
+
+/// Write a list of values as a single key, if it is not empty.
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, key: &str, values: &[T]) -> fmt::Result {
+    if values.is_empty() {
+        return Ok(());
+    }
+    let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+    writeln!(f, "{} = {}", key, values.join(", "))
+}
+
+/// Split comma-separated list, as used by `DNS`.
+fn parse_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+}
+
+/// Parse firewall mark, which may be decimal, hexadecimal or `off`.
+fn parse_fwmark(value: &str) -> Option<Option<u32>> {
+    if value.eq_ignore_ascii_case("off") {
+        Some(None)
+    } else if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u32::from_str_radix(hex, 16).ok().map(Some)
+    } else {
+        value.parse().ok().map(Some)
+    }
+}
+
+/// Parse persistent keepalive interval, where `off` and `0` both disable it.
+fn parse_keepalive(value: &str) -> Option<Option<u16>> {
+    if value.eq_ignore_ascii_case("off") {
+        Some(None)
+    } else {
+        value
+            .parse()
+            .ok()
+            .map(|interval| if interval == 0 { None } else { Some(interval) })
+    }
+}
+
+/// Maximum length of Linux interface names.
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/// Check that the name is a valid Linux interface name.
+pub(crate) fn valid_interface_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_INTERFACE_NAME_LEN
+        && name != "."
+        && name != ".."
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_graphic() && byte != b'/' && byte != b':')
+}
+
+impl Section {
+    fn set(&mut self, line: usize, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = |error: ValueError| ConfigError::Value {
+            line,
+            key: key.to_string(),
+            error,
+        };
+        let lower = key.to_ascii_lowercase();
+        match self {
+            Section::None => return Err(ConfigError::NoSection { line }),
+            Section::Interface {
+                private_key,
+                interface,
+                ..
+            } => match lower.as_str() {
+                "privatekey" => {
+                    *private_key =
+                        Some(Privkey::parse(value).map_err(|_| invalid(ValueError::Key))?)
+                }
+                "address" => {
+                    let address: AllowedIps =
+                        value.parse().map_err(|error| invalid(error.into()))?;
+                    interface.address.extend(address)
+                }
+                "listenport" => {
+                    interface.listen_port =
+                        Some(value.parse().map_err(|error| invalid(error.into()))?)
+                }
+                "fwmark" => {
+                    interface.fwmark =
+                        parse_fwmark(value).ok_or_else(|| invalid(ValueError::Off))?
+                }
+                "dns" => interface.dns.extend(parse_list(value)),
+                "mtu" => {
+                    interface.mtu = Some(value.parse().map_err(|error| invalid(error.into()))?)
+                }
+                "table" => interface.table = Some(value.to_string()),
+                "preup" => interface.pre_up.push(value.to_string()),
+                "postup" => interface.post_up.push(value.to_string()),
+                "predown" => interface.pre_down.push(value.to_string()),
+                "postdown" => interface.post_down.push(value.to_string()),
+                "saveconfig" => {
+                    interface.save_config = value.parse().map_err(|error| invalid(error.into()))?
+                }
+                _ => {
+                    return Err(ConfigError::Key {
+                        line,
+                        key: key.to_string(),
+                    })
+                }
+            },
+            Section::Peer {
+                public_key, peer, ..
+            } => match lower.as_str() {
+                "publickey" => {
+                    *public_key = Some(Pubkey::parse(value).map_err(|_| invalid(ValueError::Key))?)
+                }
+                "presharedkey" => {
+                    let secret = Secret::parse(value).map_err(|_| invalid(ValueError::Key))?;
+                    peer.preshared_key = Some(secret)
+                }
+                "allowedips" => {
+                    let allowed_ips: AllowedIps =
+                        value.parse().map_err(|error| invalid(error.into()))?;
+                    peer.allowed_ips.extend(allowed_ips)
+                }
+                "endpoint" => {
+                    peer.endpoint = Some(value.parse().map_err(|error| invalid(error.into()))?)
+                }
+                "persistentkeepalive" => {
+                    peer.persistent_keepalive =
+                        parse_keepalive(value).ok_or_else(|| invalid(ValueError::Off))?
+                }
+                _ => {
+                    return Err(ConfigError::Key {
+                        line,
+                        key: key.to_string(),
+                    })
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Finish parsing this section, adding it to the interface or peers.
+    fn finish(
+        self,
+        interface: &mut Option<Interface>,
+        peers: &mut Vec<Peer>,
+    ) -> Result<(), ConfigError> {
+        match self {
+            Section::None => {}
+            Section::Interface {
+                line,
+                private_key,
+                interface: mut section,
+            } => {
+                section.private_key = private_key.ok_or(ConfigError::Missing {
+                    line,
+                    key: "PrivateKey",
+                })?;
+                *interface = Some(section);
+            }
+            Section::Peer {
+                line,
+                public_key,
+                mut peer,
+            } => {
+                peer.public_key = public_key.ok_or(ConfigError::Missing {
+                    line,
+                    key: "PublicKey",
+                })?;
+                peers.push(peer);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TunnelConfig {
+    /// Create new configuration for the given interface, without peers.
+    pub fn new(interface: Interface) -> Self {
+        TunnelConfig {
+            interface,
+            peers: Vec::new(),
+        }
+    }
+
+    /// Check configuration for problems which are not syntax errors, but which are likely
+    /// mistakes. Returns an empty list if nothing was found.
+    pub fn validate(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let own_key = self.interface.private_key.pubkey();
+        for (index, peer) in self.peers.iter().enumerate() {
+            if peer.public_key == own_key {
+                findings.push(Finding::OwnKey { peer: index });
+            }
+            if peer.persistent_keepalive.is_some() && peer.endpoint.is_none() {
+                findings.push(Finding::MissingEndpoint { peer: index });
+            }
+            for (other_index, other) in self.peers.iter().enumerate().skip(index + 1) {
+                if peer.public_key == other.public_key {
+                    findings.push(Finding::DuplicatePeer {
+                        first: index,
+                        second: other_index,
+                    });
+                }
+                for (network, other_network) in peer.allowed_ips.overlapping(&other.allowed_ips) {
+                    findings.push(Finding::OverlappingAllowedIps {
+                        first: index,
+                        first_network: network,
+                        second: other_index,
+                        second_network: other_network,
+                    });
+                }
+            }
+        }
+        findings
+    }
+
+    /// Compute the changes needed to go from this configuration to another one. Differences
+    /// that do not change the behaviour of the tunnel, such as the order of peers or allowed
+    /// IPs and the names of peers, are ignored.
+    pub fn diff(&self, other: &TunnelConfig) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let old_key = self.interface.private_key.pubkey();
+        let new_key = other.interface.private_key.pubkey();
+        if old_key != new_key {
+            changes.push(Change::PrivateKeyRotated {
+                old: old_key,
+                new: new_key,
+            });
+        }
+        diff_interface(&self.interface, &other.interface, &mut changes);
+        for peer in &self.peers {
+            if !other
+                .peers
+                .iter()
+                .any(|new| new.public_key == peer.public_key)
+            {
+                changes.push(Change::PeerRemoved(peer.public_key));
+            }
+        }
+        for peer in &other.peers {
+            match self
+                .peers
+                .iter()
+                .find(|old| old.public_key == peer.public_key)
+            {
+                Some(old) => diff_peer(old, peer, &mut changes),
+                None => changes.push(Change::PeerAdded(peer.public_key)),
+            }
+        }
+        changes
+    }
+
+    /// Parse wg-quick configuration.
+    pub fn parse(data: &str) -> Result<Self, ConfigError> {
+        let mut interface = None;
+        let mut peers = Vec::new();
+        let mut section = Section::None;
+        let mut comment = None;
+        for (index, line) in data.lines().enumerate() {
+            let line_number = index + 1;
+            let line = match line.find('#') {
+                Some(start) => {
+                    let text = line[start + 1..].trim();
+                    if line[..start].trim().is_empty() && !text.is_empty() {
+                        comment = Some(text.to_string());
+                        continue;
+                    }
+                    &line[..start]
+                }
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                comment = None;
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section.finish(&mut interface, &mut peers)?;
+                section = match name.trim().to_ascii_lowercase().as_str() {
+                    "interface" if interface.is_some() => {
+                        return Err(ConfigError::DuplicateInterface { line: line_number })
+                    }
+                    "interface" => Section::Interface {
+                        line: line_number,
+                        private_key: None,
+                        interface: Interface::new(Privkey::new([0; 32])),
+                    },
+                    "peer" => Section::Peer {
+                        line: line_number,
+                        public_key: None,
+                        peer: Peer {
+                            name: comment.take(),
+                            ..Peer::new(Pubkey::new([0; 32]))
+                        },
+                    },
+                    _ => {
+                        return Err(ConfigError::Section {
+                            line: line_number,
+                            section: name.to_string(),
+                        })
+                    }
+                };
+                continue;
+            }
+            comment = None;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(ConfigError::Syntax { line: line_number })?;
+            section.set(line_number, key.trim(), value.trim())?;
+        }
+        section.finish(&mut interface, &mut peers)?;
+        Ok(TunnelConfig {
+            interface: interface.ok_or(ConfigError::MissingInterface)?,
+            peers,
+        })
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Interface]")?;
+        writeln!(f, "PrivateKey = {}", self.private_key.to_base64())?;
+        write_list(f, "Address", &self.address)?;
+        if let Some(port) = self.listen_port {
+            writeln!(f, "ListenPort = {}", port)?;
+        }
+        if let Some(fwmark) = self.fwmark {
+            writeln!(f, "FwMark = 0x{:x}", fwmark)?;
+        }
+        write_list(f, "DNS", &self.dns)?;
+        if let Some(mtu) = self.mtu {
+            writeln!(f, "MTU = {}", mtu)?;
+        }
+        if let Some(table) = &self.table {
+            writeln!(f, "Table = {}", table)?;
+        }
+        for (key, commands) in [
+            ("PreUp", &self.pre_up),
+            ("PostUp", &self.post_up),
+            ("PreDown", &self.pre_down),
+            ("PostDown", &self.post_down),
+        ] {
+            for command in commands {
+                writeln!(f, "{} = {}", key, command)?;
+            }
+        }
+        if self.save_config {
+            writeln!(f, "SaveConfig = true")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.name {
+            writeln!(f, "# {}", name.replace(['\r', '\n'], " "))?;
+        }
+        writeln!(f, "[Peer]")?;
+        writeln!(f, "PublicKey = {}", self.public_key.to_base64())?;
+        if let Some(preshared_key) = &self.preshared_key {
+            writeln!(f, "PresharedKey = {}", preshared_key.to_base64())?;
+        }
+        write_list(f, "AllowedIPs", &self.allowed_ips)?;
+        if let Some(endpoint) = &self.endpoint {
+            writeln!(f, "Endpoint = {}", endpoint)?;
+        }
+        if let Some(interval) = self.persistent_keepalive {
+            writeln!(f, "PersistentKeepalive = {}", interval)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TunnelConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.interface)?;
+        for peer in &self.peers {
+            write!(f, "\n{}", peer)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TunnelConfig {
+    type Err = ConfigError;
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        TunnelConfig::parse(data)
+    }
+}
+
+#[cfg(test)]
+const TEST_CONFIG: &str = "
+[Interface]
+# server key
+PrivateKey = yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+Address = 10.0.0.1/24, fd00::1/64
+ListenPort = 51820
+FwMark = 0x1234
+PostUp = iptables -A FORWARD -i %i -j ACCEPT
+
+[Peer]
+PublicKey = xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+PresharedKey = /UwcSPg38hW/D9Y3tcS1FOV0K1wuURMbS0sesJEP5ak=
+AllowedIPs = 10.0.0.2/32
+AllowedIPs = fd00::2/128
+Endpoint = vpn.example.com:51820
+PersistentKeepalive = 25
+
+# laptop
+[peer]
+publickey = TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0=
+persistentkeepalive = off
+";
+
+#[test]
+fn test_config_parse() {
+    let config = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    let interface = &config.interface;
+    assert_eq!(
+        interface.private_key,
+        Privkey::parse("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=").unwrap()
+    );
+    assert_eq!(
+        interface.address,
+        vec![
+            "10.0.0.1/24".parse().unwrap(),
+            "fd00::1/64".parse().unwrap()
+        ]
+    );
+    assert_eq!(interface.listen_port, Some(51820));
+    assert_eq!(interface.fwmark, Some(0x1234));
+    assert_eq!(
+        interface.post_up,
+        vec!["iptables -A FORWARD -i %i -j ACCEPT"]
+    );
+    assert_eq!(config.peers.len(), 2);
+    let peer = &config.peers[0];
+    assert_eq!(
+        peer.public_key,
+        Pubkey::parse("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=").unwrap()
+    );
+    assert!(peer.preshared_key.is_some());
+    assert_eq!(peer.allowed_ips.to_string(), "10.0.0.2/32, fd00::2/128");
+    assert_eq!(
+        peer.endpoint,
+        Some("vpn.example.com:51820".parse().unwrap())
+    );
+    assert_eq!(peer.persistent_keepalive, Some(25));
+    assert_eq!(peer.name, None);
+    assert_eq!(config.peers[1].persistent_keepalive, None);
+    assert_eq!(config.peers[1].name.as_deref(), Some("laptop"));
+}
+
+#[test]
+fn test_config_write() {
+    let config = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    let expected = "\
+[Interface]
+PrivateKey = yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+Address = 10.0.0.1/24, fd00::1/64
+ListenPort = 51820
+FwMark = 0x1234
+PostUp = iptables -A FORWARD -i %i -j ACCEPT
+
+[Peer]
+PublicKey = xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+PresharedKey = /UwcSPg38hW/D9Y3tcS1FOV0K1wuURMbS0sesJEP5ak=
+AllowedIPs = 10.0.0.2/32, fd00::2/128
+Endpoint = vpn.example.com:51820
+PersistentKeepalive = 25
+
+# laptop
+[Peer]
+PublicKey = TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0=
+";
+    assert_eq!(config.to_string(), expected);
+    assert_eq!(TunnelConfig::parse(expected).unwrap(), config);
+}
+
+#[test]
+fn test_config_parse_invalid() {
+    assert_eq!(TunnelConfig::parse(""), Err(ConfigError::MissingInterface));
+    assert_eq!(
+        TunnelConfig::parse("ListenPort = 1"),
+        Err(ConfigError::NoSection { line: 1 })
+    );
+    assert_eq!(
+        TunnelConfig::parse("[Interface]\nListenPort = 1"),
+        Err(ConfigError::Missing {
+            line: 1,
+            key: "PrivateKey"
+        })
+    );
+    assert_eq!(
+        TunnelConfig::parse("[Interface]\nListenPort = 100000"),
+        Err(ConfigError::Value {
+            line: 2,
+            key: "ListenPort".into(),
+            error: "100000".parse::<u16>().unwrap_err().into()
+        })
+    );
+    assert_eq!(
+        TunnelConfig::parse("[Interface]\nFoo = bar"),
+        Err(ConfigError::Key {
+            line: 2,
+            key: "Foo".into()
+        })
+    );
+    assert_eq!(
+        TunnelConfig::parse("[Interface]\nListenPort"),
+        Err(ConfigError::Syntax { line: 2 })
+    );
+    assert_eq!(
+        TunnelConfig::parse("[Interface]\nAddress = 10.0.0.1/33"),
+        Err(ConfigError::Value {
+            line: 2,
+            key: "Address".into(),
+            error: ValueError::Cidr(CidrError::Prefix)
+        })
+    );
+    assert_eq!(
+        TunnelConfig::parse("[Peer]\nEndpoint = vpn.example.com"),
+        Err(ConfigError::Value {
+            line: 2,
+            key: "Endpoint".into(),
+            error: ValueError::Endpoint(EndpointError::MissingPort)
+        })
+    );
+    let error = TunnelConfig::parse("[Interface]\nMTU = big").unwrap_err();
+    let source = std::error::Error::source(&error).unwrap();
+    assert_eq!(source.to_string(), "invalid digit found in string");
+    assert_eq!(
+        TunnelConfig::parse("[Interface]\nPrivateKey = !"),
+        Err(ConfigError::Value {
+            line: 2,
+            key: "PrivateKey".into(),
+            error: ValueError::Key
+        })
+    );
+    assert_eq!(
+        TunnelConfig::parse("[Wireguard]"),
+        Err(ConfigError::Section {
+            line: 1,
+            section: "Wireguard".into()
+        })
+    );
+}
+
+#[test]
+fn test_peer_builder() {
+    let public_key = Pubkey::parse("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=").unwrap();
+    let peer = Peer::builder()
+        .name("laptop")
+        .pubkey(public_key)
+        .allowed_ip("10.0.0.2/32")
+        .allowed_ip("fd00::2/128".parse::<Cidr>().unwrap())
+        .endpoint("vpn.example.com:51820")
+        .keepalive(25)
+        .build()
+        .unwrap();
+    assert_eq!(
+        peer.to_string(),
+        "\
+# laptop
+[Peer]
+PublicKey = xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+AllowedIPs = 10.0.0.2/32, fd00::2/128
+Endpoint = vpn.example.com:51820
+PersistentKeepalive = 25
+"
+    );
+    assert_eq!(
+        Peer::builder().allowed_ip("10.0.0.2/32").build(),
+        Err(BuildError::Missing("public key"))
+    );
+    assert_eq!(
+        Peer::builder()
+            .pubkey(public_key)
+            .allowed_ip("10.0.0.2/40")
+            .build(),
+        Err(BuildError::AllowedIp {
+            value: "10.0.0.2/40".into(),
+            error: CidrError::Prefix
+        })
+    );
+    assert_eq!(
+        Peer::builder()
+            .pubkey(public_key)
+            .endpoint("vpn.example.com")
+            .build(),
+        Err(BuildError::Endpoint {
+            value: "vpn.example.com".into(),
+            error: EndpointError::MissingPort
+        })
+    );
+    let error = Peer::builder()
+        .pubkey(public_key)
+        .allowed_ip("10.0.0.2/40")
+        .build()
+        .unwrap_err();
+    let source = std::error::Error::source(&error).unwrap();
+    assert_eq!(source.to_string(), "invalid prefix length");
+}
+
+#[test]
+fn test_interface_builder() {
+    let private_key = Privkey::parse("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=").unwrap();
+    let interface = Interface::builder()
+        .private_key(private_key)
+        .address("10.0.0.1/24")
+        .address("fd00::1/64".parse::<Cidr>().unwrap())
+        .listen_port(51820)
+        .dns("10.0.0.53")
+        .mtu(1420)
+        .post_up("iptables -A FORWARD -i %i -j ACCEPT")
+        .build()
+        .unwrap();
+    let mut config = TunnelConfig::new(interface);
+    config.peers.push(
+        Peer::builder()
+            .pubkey(Pubkey::parse("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=").unwrap())
+            .allowed_ip("10.0.0.2/32")
+            .build()
+            .unwrap(),
+    );
+    assert_eq!(
+        config.to_string(),
+        "\
+[Interface]
+PrivateKey = yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+Address = 10.0.0.1/24, fd00::1/64
+ListenPort = 51820
+DNS = 10.0.0.53
+MTU = 1420
+PostUp = iptables -A FORWARD -i %i -j ACCEPT
+
+[Peer]
+PublicKey = xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+AllowedIPs = 10.0.0.2/32
+"
+    );
+}
+
+#[test]
+fn test_interface_builder_invalid() {
+    let builder = Interface::builder().private_key(Privkey::generate());
+    assert_eq!(
+        Interface::builder().mtu(1420).build(),
+        Err(BuildError::Missing("private key"))
+    );
+    assert_eq!(
+        builder.clone().address("10.0.0.1").build().unwrap().address,
+        vec!["10.0.0.1/32".parse().unwrap()]
+    );
+    assert_eq!(
+        builder.clone().address("10.0.0.1/24/1").build(),
+        Err(BuildError::Address {
+            value: "10.0.0.1/24/1".into(),
+            error: CidrError::Prefix
+        })
+    );
+    assert_eq!(
+        builder.clone().dns("10.0.0.53, 10.0.0.54").build(),
+        Err(BuildError::Dns("10.0.0.53, 10.0.0.54".into()))
+    );
+    // IPv6 needs a larger MTU than IPv4
+    assert!(builder
+        .clone()
+        .address("10.0.0.1/24")
+        .mtu(1000)
+        .build()
+        .is_ok());
+    assert_eq!(
+        builder.clone().address("fd00::1/64").mtu(1000).build(),
+        Err(BuildError::Mtu(1000))
+    );
+    assert_eq!(
+        builder.clone().mtu(100_000).build(),
+        Err(BuildError::Mtu(100_000))
+    );
+    assert_eq!(
+        builder.clone().post_up("echo up\necho injected").build(),
+        Err(BuildError::Hook)
+    );
+    assert!(builder.clone().table("off").build().is_ok());
+    assert_eq!(
+        builder.table("off\nPostUp = cmd").build(),
+        Err(BuildError::Table("off\nPostUp = cmd".into()))
+    );
+}
+
+#[test]
+fn test_config_validate() {
+    let config = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    assert_eq!(config.validate(), vec![]);
+
+    let mut config = config;
+    let own_key = config.interface.private_key.pubkey();
+    config.peers[1].allowed_ips = "10.0.0.0/24".parse().unwrap();
+    config.peers[1].persistent_keepalive = Some(25);
+    config.peers.push(Peer::new(own_key));
+    config.peers.push(Peer::new(config.peers[0].public_key));
+    assert_eq!(
+        config.validate(),
+        vec![
+            Finding::OverlappingAllowedIps {
+                first: 0,
+                first_network: "10.0.0.2/32".parse().unwrap(),
+                second: 1,
+                second_network: "10.0.0.0/24".parse().unwrap(),
+            },
+            Finding::DuplicatePeer {
+                first: 0,
+                second: 3
+            },
+            Finding::MissingEndpoint { peer: 1 },
+            Finding::OwnKey { peer: 2 },
+        ]
+    );
+}
+
+#[test]
+fn test_config_diff() {
+    let old = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    let mut new = old.clone();
+    // reordering does not change anything
+    new.peers.reverse();
+    new.interface.address.reverse();
+    assert_eq!(old.diff(&new), vec![]);
+
+    new.interface.private_key = Privkey::generate();
+    new.interface.listen_port = None;
+    let laptop = new.peers.remove(0).public_key;
+    let added = Pubkey::generate();
+    new.peers.push(Peer::new(added));
+    let server = &mut new.peers[0];
+    server.preshared_key = None;
+    server.allowed_ips = "10.0.0.2/32, 10.1.0.0/16".parse().unwrap();
+    server.persistent_keepalive = None;
+    let server = server.public_key;
+    assert_eq!(
+        old.diff(&new),
+        vec![
+            Change::PrivateKeyRotated {
+                old: old.interface.private_key.pubkey(),
+                new: new.interface.private_key.pubkey(),
+            },
+            Change::InterfaceSetting("ListenPort"),
+            Change::PeerRemoved(laptop),
+            Change::PresharedKeyChanged(server),
+            Change::AllowedIpsChanged {
+                peer: server,
+                added: vec!["10.1.0.0/16".parse().unwrap()],
+                removed: vec!["fd00::2/128".parse().unwrap()],
+            },
+            Change::KeepaliveChanged {
+                peer: server,
+                old: Some(25),
+                new: None,
+            },
+            Change::PeerAdded(added),
+        ]
+    );
+}
@@ -0,0 +1,256 @@
+//! Encoding of keys as [COSE_Key](https://www.rfc-editor.org/rfc/rfc9052#section-7) structures
+//! in CBOR, for exchanging keys with systems built on COSE, such as constrained IoT devices.
+//!
+//! WireGuard keys are X25519 keys, which COSE represents as octet key pairs (OKP) on the X25519
+//! curve ([RFC 9053](https://www.rfc-editor.org/rfc/rfc9053#section-7.2)). Public keys only
+//! carry the `x` parameter, private keys carry both the public key as `x` and the private key
+//! as `d`:
+//!
+//! ```
+//! # use wireguard_keys::Privkey;
+//! let privkey = Privkey::generate();
+//! let encoded = privkey.pubkey().to_cose_key();
+//! assert_eq!(wireguard_keys::Pubkey::from_cose_key(&encoded).unwrap(), privkey.pubkey());
+//! ```
+//!
+//! Other parameters, such as a key ID or algorithm, are ignored when decoding. Keys embedded in
+//! other CBOR structures through serde are written as byte strings when the `serde-bytes`
+//! feature is enabled.
+
+use crate::{ParseError, Privkey, Pubkey};
+use ciborium::value::{Integer, Value};
+use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Label of the key type parameter.
+pub const LABEL_KTY: i64 = 1;
+
+/// Label of the curve parameter of octet key pairs.
+pub const LABEL_CRV: i64 = -1;
+
+/// Label of the public key parameter of octet key pairs.
+pub const LABEL_X: i64 = -2;
+
+/// Label of the private key parameter of octet key pairs.
+pub const LABEL_D: i64 = -4;
+
+/// Key type of octet key pairs.
+pub const KTY_OKP: i64 = 1;
+
+/// Curve identifier of X25519.
+pub const CRV_X25519: i64 = 4;
+
+/// Possible errors that can be generated when decoding COSE keys.
+#[derive(Error, Debug)]
+pub enum CoseError {
+    /// Data is not valid CBOR
+    #[error("invalid CBOR: {0}")]
+    Cbor(String),
+    /// Data is not a map
+    #[error("COSE key is not a map")]
+    Map,
+    /// Key type is not OKP
+    #[error("key type is not OKP")]
+    KeyType,
+    /// Curve is not X25519
+    #[error("curve is not X25519")]
+    Curve,
+    /// Required parameter is missing or has the wrong type
+    #[error("missing parameter {0:?}")]
+    Missing(&'static str),
+    /// Parameter is not a valid key
+    #[error("error parsing key")]
+    Parse(#[from] ParseError),
+    /// Public key does not belong to private key
+    #[error("public key does not match private key")]
+    Mismatch,
+}
+
+/// Encode COSE key map with the given parameters, in deterministic order.
+fn encode(x: &Pubkey, d: Option<&Privkey>) -> Vec<u8> {
+    let mut map = vec![
+        (Value::from(LABEL_KTY), Value::from(KTY_OKP)),
+        (Value::from(LABEL_CRV), Value::from(CRV_X25519)),
+        (Value::from(LABEL_X), Value::Bytes(x.to_vec())),
+    ];
+    if let Some(d) = d {
+        map.push((Value::from(LABEL_D), Value::Bytes(d.to_vec())));
+    }
+    let mut value = Value::Map(map);
+    let mut encoded = Vec::new();
+    ciborium::into_writer(&value, &mut encoded).expect("writing to vec cannot fail");
+    if let Value::Map(map) = &mut value {
+        zeroize_map(map);
+    }
+    encoded
+}
+
+/// Decode COSE key map, checking the key type and curve, and returning all parameters.
+fn decode(data: &[u8]) -> Result<Vec<(Value, Value)>, CoseError> {
+    let value: Value =
+        ciborium::from_reader(data).map_err(|error| CoseError::Cbor(error.to_string()))?;
+    let Value::Map(map) = value else {
+        return Err(CoseError::Map);
+    };
+    if integer_param(&map, LABEL_KTY, "kty")? != KTY_OKP {
+        return Err(CoseError::KeyType);
+    }
+    if integer_param(&map, LABEL_CRV, "crv")? != CRV_X25519 {
+        return Err(CoseError::Curve);
+    }
+    Ok(map)
+}
+
+/// Look up parameter by label.
+fn param(map: &[(Value, Value)], label: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(key, _)| key.as_integer() == Some(Integer::from(label)))
+        .map(|(_, value)| value)
+}
+
+/// Look up integer parameter by label.
+fn integer_param(map: &[(Value, Value)], label: i64, name: &'static str) -> Result<i64, CoseError> {
+    param(map, label)
+        .and_then(Value::as_integer)
+        .and_then(|value| i64::try_from(value).ok())
+        .ok_or(CoseError::Missing(name))
+}
+
+/// Look up byte string parameter by label.
+fn bytes_param<'a>(
+    map: &'a [(Value, Value)],
+    label: i64,
+    name: &'static str,
+) -> Result<&'a [u8], CoseError> {
+    param(map, label)
+        .and_then(Value::as_bytes)
+        .map(Vec::as_slice)
+        .ok_or(CoseError::Missing(name))
+}
+
+/// Zeroize all byte strings of a decoded map, which may contain private keys.
+fn zeroize_map(map: &mut [(Value, Value)]) {
+    for (_, value) in map {
+        if let Value::Bytes(bytes) = value {
+            bytes.zeroize();
+        }
+    }
+}
+
+impl Pubkey {
+    /// Encode key as COSE key in CBOR.
+    pub fn to_cose_key(&self) -> Vec<u8> {
+        encode(self, None)
+    }
+
+    /// Decode key from COSE key in CBOR. Private keys are accepted too, in which case only
+    /// the public key is returned.
+    pub fn from_cose_key(data: &[u8]) -> Result<Self, CoseError> {
+        let mut map = decode(data)?;
+        let result = bytes_param(&map, LABEL_X, "x").and_then(|x| Ok(Pubkey::try_from(x)?));
+        zeroize_map(&mut map);
+        result
+    }
+}
+
+impl Privkey {
+    /// Encode key as COSE key in CBOR, including the public key.
+    pub fn to_cose_key(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(encode(&self.pubkey(), Some(self)))
+    }
+
+    /// Decode key from COSE key in CBOR. If the public key is present, it must belong to the
+    /// private key.
+    pub fn from_cose_key(data: &[u8]) -> Result<Self, CoseError> {
+        let mut map = decode(data)?;
+        let result = bytes_param(&map, LABEL_D, "d").and_then(|d| {
+            let privkey = Privkey::try_from(d)?;
+            if param(&map, LABEL_X).is_some() {
+                let pubkey = Pubkey::try_from(bytes_param(&map, LABEL_X, "x")?)?;
+                if pubkey != privkey.pubkey() {
+                    return Err(CoseError::Mismatch);
+                }
+            }
+            Ok(privkey)
+        });
+        zeroize_map(&mut map);
+        result
+    }
+}
+
+#[test]
+fn test_cose_pubkey() {
+    let pubkey = Pubkey::new([0xab; 32]);
+    let encoded = pubkey.to_cose_key();
+    let mut expected = vec![0xa3, 0x01, 0x01, 0x20, 0x04, 0x21, 0x58, 0x20];
+    expected.extend([0xab; 32]);
+    assert_eq!(encoded, expected);
+    assert_eq!(Pubkey::from_cose_key(&encoded).unwrap(), pubkey);
+}
+
+#[test]
+fn test_cose_privkey() {
+    let privkey = Privkey::generate();
+    let encoded = privkey.to_cose_key();
+    assert_eq!(Privkey::from_cose_key(&encoded).unwrap(), privkey);
+    assert_eq!(Pubkey::from_cose_key(&encoded).unwrap(), privkey.pubkey());
+    assert!(matches!(
+        Privkey::from_cose_key(&privkey.pubkey().to_cose_key()),
+        Err(CoseError::Missing("d"))
+    ));
+    let other = Privkey::generate();
+    let mut mismatched = Vec::new();
+    let map = vec![
+        (Value::from(LABEL_KTY), Value::from(KTY_OKP)),
+        (Value::from(LABEL_CRV), Value::from(CRV_X25519)),
+        (Value::from(LABEL_X), Value::Bytes(other.pubkey().to_vec())),
+        (Value::from(LABEL_D), Value::Bytes(privkey.to_vec())),
+    ];
+    ciborium::into_writer(&Value::Map(map), &mut mismatched).unwrap();
+    assert!(matches!(
+        Privkey::from_cose_key(&mismatched),
+        Err(CoseError::Mismatch)
+    ));
+}
+
+#[test]
+fn test_cose_invalid() {
+    let encode = |value: Value| {
+        let mut encoded = Vec::new();
+        ciborium::into_writer(&value, &mut encoded).unwrap();
+        encoded
+    };
+    let map = |kty: i64, crv: i64, x: Vec<u8>| {
+        Value::Map(vec![
+            (Value::from(LABEL_KTY), Value::from(kty)),
+            (Value::from(LABEL_CRV), Value::from(crv)),
+            (Value::from(LABEL_X), Value::Bytes(x)),
+            (Value::from(4), Value::Bytes(b"kid".to_vec())),
+        ])
+    };
+    assert!(matches!(
+        Pubkey::from_cose_key(&[0xff]),
+        Err(CoseError::Cbor(_))
+    ));
+    assert!(matches!(
+        Pubkey::from_cose_key(&encode(Value::Bytes(vec![0; 32]))),
+        Err(CoseError::Map)
+    ));
+    assert!(matches!(
+        Pubkey::from_cose_key(&encode(map(2, CRV_X25519, vec![0; 32]))),
+        Err(CoseError::KeyType)
+    ));
+    // Ed25519
+    assert!(matches!(
+        Pubkey::from_cose_key(&encode(map(KTY_OKP, 6, vec![0; 32]))),
+        Err(CoseError::Curve)
+    ));
+    assert!(matches!(
+        Pubkey::from_cose_key(&encode(map(KTY_OKP, CRV_X25519, vec![0; 31]))),
+        Err(CoseError::Parse(ParseError::Length))
+    ));
+    assert_eq!(
+        Pubkey::from_cose_key(&encode(map(KTY_OKP, CRV_X25519, vec![7; 32]))).unwrap(),
+        Pubkey::new([7; 32])
+    );
+}
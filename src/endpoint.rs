@@ -0,0 +1,252 @@
+//! Typed representation of peer endpoints. WireGuard endpoints are written as `host:port`,
+//! where the host is an IPv4 address, an IPv6 address in brackets or a DNS name. Unlike
+//! [SocketAddr], an [Endpoint] can hold DNS names, which are only resolved when needed using
+//! [Endpoint::resolve], or [Endpoint::resolve_async] when the `tokio` feature is enabled.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Maximum length of a DNS name.
+const MAX_DOMAIN_LEN: usize = 253;
+
+/// Possible errors that can be generated when parsing endpoints.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EndpointError {
+    /// Endpoint has no port
+    #[error("missing port")]
+    MissingPort,
+    /// Port is not a valid number
+    #[error("invalid port")]
+    Port,
+    /// Host is neither an IP address nor a DNS name
+    #[error("invalid host")]
+    Host,
+}
+
+/// Host part of an endpoint.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Host {
+    /// IPv4 or IPv6 address
+    Ip(IpAddr),
+    /// DNS name, resolved when connecting
+    Domain(Domain),
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Ip(IpAddr::V6(addr)) => write!(f, "[{}]", addr),
+            Host::Ip(addr) => write!(f, "{}", addr),
+            Host::Domain(domain) => write!(f, "{}", domain),
+        }
+    }
+}
+
+/// Validated DNS name. Can only be constructed by parsing, so it never contains characters
+/// which would break the configuration files it is written to.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Domain(String);
+
+impl Domain {
+    /// DNS name as string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Domain {
+    type Err = EndpointError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let valid = !value.is_empty()
+            && value.len() <= MAX_DOMAIN_LEN
+            && value.split('.').enumerate().all(|(index, label)| {
+                // allow a trailing dot for fully-qualified names
+                (label.is_empty() && index > 0 && value.ends_with('.'))
+                    || (!label.is_empty()
+                        && label.len() <= 63
+                        && !label.starts_with('-')
+                        && !label.ends_with('-')
+                        && label.bytes().all(|byte| {
+                            byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_')
+                        }))
+            });
+        if !valid {
+            return Err(EndpointError::Host);
+        }
+        Ok(Domain(value.to_string()))
+    }
+}
+
+/// Endpoint of a peer, consisting of a host and a port.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Endpoint {
+    host: Host,
+    port: u16,
+}
+
+impl Endpoint {
+    /// Create endpoint from host and port.
+    pub fn new(host: Host, port: u16) -> Self {
+        Endpoint { host, port }
+    }
+
+    /// Host of this endpoint.
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+
+    /// Port of this endpoint.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Socket address of this endpoint, if the host is an IP address.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self.host {
+            Host::Ip(addr) => Some(SocketAddr::new(addr, self.port)),
+            Host::Domain(_) => None,
+        }
+    }
+
+    /// Resolve endpoint to socket addresses, blocking on DNS lookups if the host is a name.
+    pub fn resolve(&self) -> io::Result<Vec<SocketAddr>> {
+        match &self.host {
+            Host::Ip(addr) => Ok(vec![SocketAddr::new(*addr, self.port)]),
+            Host::Domain(domain) => Ok((domain.as_str(), self.port).to_socket_addrs()?.collect()),
+        }
+    }
+
+    /// Resolve endpoint to socket addresses without blocking, using the tokio resolver.
+    #[cfg(feature = "tokio")]
+    pub async fn resolve_async(&self) -> io::Result<Vec<SocketAddr>> {
+        match &self.host {
+            Host::Ip(addr) => Ok(vec![SocketAddr::new(*addr, self.port)]),
+            Host::Domain(domain) => Ok(tokio::net::lookup_host((domain.as_str(), self.port))
+                .await?
+                .collect()),
+        }
+    }
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(addr: SocketAddr) -> Self {
+        Endpoint::new(Host::Ip(addr.ip()), addr.port())
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = EndpointError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (host, port) = if let Some(rest) = value.strip_prefix('[') {
+            let (addr, port) = rest.split_once(']').ok_or(EndpointError::Host)?;
+            let addr = addr.parse().map_err(|_| EndpointError::Host)?;
+            let port = port.strip_prefix(':').ok_or(EndpointError::MissingPort)?;
+            (Host::Ip(IpAddr::V6(addr)), port)
+        } else {
+            let (host, port) = value.rsplit_once(':').ok_or(EndpointError::MissingPort)?;
+            let host = match host.parse() {
+                Ok(addr) => Host::Ip(IpAddr::V4(addr)),
+                Err(_) => Host::Domain(host.parse()?),
+            };
+            (host, port)
+        };
+        let port = port.parse().map_err(|_| EndpointError::Port)?;
+        Ok(Endpoint::new(host, port))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Endpoint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Endpoint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[test]
+fn test_endpoint_parse() {
+    let endpoint: Endpoint = "192.0.2.1:51820".parse().unwrap();
+    assert_eq!(endpoint.host(), &Host::Ip("192.0.2.1".parse().unwrap()));
+    assert_eq!(endpoint.port(), 51820);
+    let endpoint: Endpoint = "[2001:db8::1]:51820".parse().unwrap();
+    assert_eq!(endpoint.host(), &Host::Ip("2001:db8::1".parse().unwrap()));
+    assert_eq!(endpoint.to_string(), "[2001:db8::1]:51820");
+    let endpoint: Endpoint = "vpn.example.com:443".parse().unwrap();
+    assert_eq!(
+        endpoint.host(),
+        &Host::Domain("vpn.example.com".parse().unwrap())
+    );
+    assert_eq!(endpoint.socket_addr(), None);
+    assert_eq!(endpoint.to_string(), "vpn.example.com:443");
+}
+
+#[test]
+fn test_endpoint_parse_invalid() {
+    assert_eq!(
+        "vpn.example.com".parse::<Endpoint>(),
+        Err(EndpointError::MissingPort)
+    );
+    assert_eq!(
+        "[2001:db8::1]".parse::<Endpoint>(),
+        Err(EndpointError::MissingPort)
+    );
+    assert_eq!(
+        "192.0.2.1:70000".parse::<Endpoint>(),
+        Err(EndpointError::Port)
+    );
+    assert_eq!(
+        "2001:db8::1:51820".parse::<Endpoint>(),
+        Err(EndpointError::Host)
+    );
+    assert_eq!(
+        "bad host:51820".parse::<Endpoint>(),
+        Err(EndpointError::Host)
+    );
+    assert_eq!(":51820".parse::<Endpoint>(), Err(EndpointError::Host));
+    assert_eq!(
+        "vpn.example.com\nPostUp = cmd".parse::<Domain>(),
+        Err(EndpointError::Host)
+    );
+}
+
+#[test]
+fn test_endpoint_resolve() {
+    let endpoint: Endpoint = "[::1]:51820".parse().unwrap();
+    assert_eq!(
+        endpoint.resolve().unwrap(),
+        vec!["[::1]:51820".parse::<SocketAddr>().unwrap()]
+    );
+    let addr: SocketAddr = "127.0.0.1:51820".parse().unwrap();
+    assert_eq!(Endpoint::from(addr).socket_addr(), Some(addr));
+}
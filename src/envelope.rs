@@ -0,0 +1,169 @@
+//! Fixed-size envelopes for exchanging key material over side channels, such as during
+//! enrollment. Every [Frame] is exactly [FRAME_LEN] bytes long regardless of its contents, and
+//! the unused part of a frame is filled with random bytes, so that an observer cannot tell keys
+//! apart from other payloads by their size. Dummy frames, which carry no payload at all, can be
+//! interleaved with real ones to hide when key material is actually being sent.
+//!
+//! ```text
+//! +------+------------+---------+------------------+
+//! | kind | length, BE | payload | random padding   |
+//! | 1    | 2          | length  | FRAME_LEN - 3 -  |
+//! +------+------------+---------+------------------+
+//! ```
+//!
+//! Envelopes only hide sizes, they do not encrypt anything: the side channel is expected to
+//! provide confidentiality. Note that sealing and opening take time proportional to the payload
+//! length, so they do not hide it from an observer who can time them.
+
+use rand_core::{OsRng, RngCore};
+use thiserror::Error;
+use zeroize::Zeroize;
+
+/// Length (in bytes) of every frame.
+pub const FRAME_LEN: usize = 256;
+
+/// Length (in bytes) of the frame header.
+const HEADER_LEN: usize = 3;
+
+/// Maximum length (in bytes) of the payload of a frame.
+pub const MAX_PAYLOAD_LEN: usize = FRAME_LEN - HEADER_LEN;
+
+const KIND_DUMMY: u8 = 0;
+const KIND_PAYLOAD: u8 = 1;
+
+/// Possible errors that can be generated when sealing or opening envelopes.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// Payload does not fit into a frame
+    #[error("payload of {0} bytes does not fit into a frame")]
+    TooLong(usize),
+    /// Frame does not have the fixed frame length
+    #[error("frame has length {0}, expected {FRAME_LEN}")]
+    Length(usize),
+    /// Frame kind is not known
+    #[error("unknown frame kind {0}")]
+    Kind(u8),
+    /// Payload length in the frame header exceeds the frame
+    #[error("invalid payload length")]
+    PayloadLength,
+}
+
+/// Fixed-size frame carrying a payload or random padding. Cleared on drop.
+#[derive(Clone)]
+pub struct Frame([u8; FRAME_LEN]);
+
+impl Frame {
+    /// Seal payload into a frame, padding it with random bytes.
+    pub fn seal(payload: &[u8]) -> Result<Self, EnvelopeError> {
+        if payload.len() > MAX_PAYLOAD_LEN {
+            return Err(EnvelopeError::TooLong(payload.len()));
+        }
+        let mut frame = Self::random();
+        frame.0[0] = KIND_PAYLOAD;
+        frame.0[1..HEADER_LEN].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+        frame.0[HEADER_LEN..HEADER_LEN + payload.len()].copy_from_slice(payload);
+        Ok(frame)
+    }
+
+    /// Create dummy frame, which contains only random bytes and is ignored when opened.
+    pub fn dummy() -> Self {
+        let mut frame = Self::random();
+        frame.0[0] = KIND_DUMMY;
+        frame
+    }
+
+    fn random() -> Self {
+        let mut data = [0; FRAME_LEN];
+        OsRng.fill_bytes(&mut data);
+        Frame(data)
+    }
+
+    /// Parse frame received from a side channel.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
+        let data: [u8; FRAME_LEN] = data
+            .try_into()
+            .map_err(|_| EnvelopeError::Length(data.len()))?;
+        Ok(Frame(data))
+    }
+
+    /// Bytes of this frame, to be sent over a side channel.
+    pub fn as_bytes(&self) -> &[u8; FRAME_LEN] {
+        &self.0
+    }
+
+    /// Determine if this is a dummy frame.
+    pub fn is_dummy(&self) -> bool {
+        self.0[0] == KIND_DUMMY
+    }
+
+    /// Payload of this frame, or `None` for dummy frames.
+    pub fn open(&self) -> Result<Option<&[u8]>, EnvelopeError> {
+        let length = u16::from_be_bytes([self.0[1], self.0[2]]) as usize;
+        match self.0[0] {
+            KIND_DUMMY => Ok(None),
+            KIND_PAYLOAD if length <= MAX_PAYLOAD_LEN => {
+                Ok(Some(&self.0[HEADER_LEN..HEADER_LEN + length]))
+            }
+            KIND_PAYLOAD => Err(EnvelopeError::PayloadLength),
+            kind => Err(EnvelopeError::Kind(kind)),
+        }
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Frame")
+            .field("dummy", &self.is_dummy())
+            .finish_non_exhaustive()
+    }
+}
+
+#[test]
+fn test_frame_seal_open() {
+    use crate::Privkey;
+    let privkey = Privkey::generate();
+    let frame = Frame::seal(&privkey.pubkey()[..]).unwrap();
+    assert_eq!(frame.as_bytes().len(), FRAME_LEN);
+    let received = Frame::from_bytes(frame.as_bytes()).unwrap();
+    assert!(!received.is_dummy());
+    assert_eq!(received.open().unwrap(), Some(&privkey.pubkey()[..]));
+
+    let empty = Frame::seal(&[]).unwrap();
+    assert_eq!(empty.open().unwrap(), Some(&[][..]));
+    let full = Frame::seal(&[7; MAX_PAYLOAD_LEN]).unwrap();
+    assert_eq!(full.open().unwrap(), Some(&[7; MAX_PAYLOAD_LEN][..]));
+}
+
+#[test]
+fn test_frame_dummy() {
+    let frame = Frame::dummy();
+    assert!(frame.is_dummy());
+    assert_eq!(frame.open().unwrap(), None);
+    assert_ne!(Frame::dummy().as_bytes(), frame.as_bytes());
+}
+
+#[test]
+fn test_frame_invalid() {
+    assert_eq!(
+        Frame::seal(&[0; MAX_PAYLOAD_LEN + 1]).unwrap_err(),
+        EnvelopeError::TooLong(MAX_PAYLOAD_LEN + 1)
+    );
+    assert_eq!(
+        Frame::from_bytes(&[0; 32]).unwrap_err(),
+        EnvelopeError::Length(32)
+    );
+    let mut data = [0; FRAME_LEN];
+    data[0] = 9;
+    let frame = Frame::from_bytes(&data).unwrap();
+    assert_eq!(frame.open(), Err(EnvelopeError::Kind(9)));
+    data[0] = KIND_PAYLOAD;
+    data[1] = 0xff;
+    let frame = Frame::from_bytes(&data).unwrap();
+    assert_eq!(frame.open(), Err(EnvelopeError::PayloadLength));
+}
@@ -0,0 +1,363 @@
+//! C interface for embedding this crate into applications written in other languages. Keys are
+//! passed as buffers of [WG_KEY_LEN] bytes, strings are NUL-terminated and every function returns
+//! a [WgStatus]. The header `include/wireguard_keys.h` is generated by cbindgen:
+//!
+//! ```sh
+//! cbindgen --config cbindgen.toml --output include/wireguard_keys.h src/ffi.rs
+//! ```
+//!
+//! To get a library which can be linked, build the crate as static or dynamic library:
+//!
+//! ```sh
+//! cargo rustc --release --features ffi --crate-type staticlib
+//! ```
+//!
+//! Functions never write to their outputs unless they succeed, and intermediate copies of
+//! private keys and shared secrets are zeroized, but the buffers of the caller are not.
+
+use crate::{Encoding, ParseError, Privkey, Pubkey, Secret};
+use std::ffi::{c_char, c_int, CStr};
+use zeroize::Zeroize;
+
+/// Length of keys in bytes.
+pub const WG_KEY_LEN: usize = 32;
+
+/// Size of a buffer which holds a key in any encoding, including the terminating NUL.
+pub const WG_ENCODED_MAX_LEN: usize = 96;
+
+/// Result of the functions of the C interface. The values are stable, new ones are only ever
+/// appended.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WgStatus {
+    /// Success
+    Ok = 0,
+    /// A pointer argument is null
+    NullPointer = 1,
+    /// String is not a key in any enabled encoding, or not valid UTF-8
+    Parse = 2,
+    /// String is a valid key in several encodings, with different results
+    Ambiguous = 3,
+    /// Encoding name is unknown, or the encoding is not enabled
+    Encoding = 4,
+    /// Output buffer is too small for the encoded key
+    BufferTooSmall = 5,
+    /// Public key is of low order, so the shared secret is all zeroes
+    LowOrder = 6,
+}
+
+impl WgStatus {
+    /// Describe the raw status code, which may be any value passed in from C.
+    fn message(status: c_int) -> &'static CStr {
+        match status {
+            0 => c"success",
+            1 => c"null pointer argument",
+            2 => c"invalid key",
+            3 => c"ambiguous encoding",
+            4 => c"unknown encoding",
+            5 => c"buffer too small",
+            6 => c"public key of low order",
+            _ => c"unknown status",
+        }
+    }
+}
+
+impl From<ParseError> for WgStatus {
+    fn from(error: ParseError) -> Self {
+        match error {
+            ParseError::Ambiguous => WgStatus::Ambiguous,
+            _ => WgStatus::Parse,
+        }
+    }
+}
+
+/// Read a key from a buffer of [WG_KEY_LEN] bytes.
+unsafe fn read_key(key: *const u8) -> Result<[u8; WG_KEY_LEN], WgStatus> {
+    match key.is_null() {
+        true => Err(WgStatus::NullPointer),
+        false => Ok(key.cast::<[u8; WG_KEY_LEN]>().read_unaligned()),
+    }
+}
+
+/// Write a key to a buffer of [WG_KEY_LEN] bytes, and zeroize the copy.
+unsafe fn write_key(output: *mut u8, mut key: [u8; WG_KEY_LEN]) {
+    output.copy_from_nonoverlapping(key.as_ptr(), WG_KEY_LEN);
+    key.zeroize();
+}
+
+/// Run the function and convert its result to a status code.
+fn status<F: FnOnce() -> Result<(), WgStatus>>(function: F) -> WgStatus {
+    match function() {
+        Ok(()) => WgStatus::Ok,
+        Err(status) => status,
+    }
+}
+
+/// Describe the status code. The string is static and must not be freed. Takes an integer
+/// rather than a [WgStatus], as C allows passing values which are not valid variants.
+#[no_mangle]
+pub extern "C" fn wg_status_message(status: c_int) -> *const c_char {
+    WgStatus::message(status).as_ptr()
+}
+
+/// Generate a new private key into `privkey`.
+///
+/// # Safety
+///
+/// `privkey` has to be null or valid for writing [WG_KEY_LEN] bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wg_generate_privkey(privkey: *mut u8) -> WgStatus {
+    if privkey.is_null() {
+        return WgStatus::NullPointer;
+    }
+    write_key(privkey, *Privkey::generate());
+    WgStatus::Ok
+}
+
+/// Generate a new preshared key into `secret`.
+///
+/// # Safety
+///
+/// `secret` has to be null or valid for writing [WG_KEY_LEN] bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wg_generate_psk(secret: *mut u8) -> WgStatus {
+    if secret.is_null() {
+        return WgStatus::NullPointer;
+    }
+    write_key(secret, *Secret::generate());
+    WgStatus::Ok
+}
+
+/// Derive the public key of `privkey` into `pubkey`.
+///
+/// # Safety
+///
+/// `privkey` has to be null or valid for reading [WG_KEY_LEN] bytes, and `pubkey` null or valid
+/// for writing [WG_KEY_LEN] bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wg_derive_pubkey(privkey: *const u8, pubkey: *mut u8) -> WgStatus {
+    status(|| {
+        let mut privkey = Privkey::new(read_key(privkey)?);
+        if pubkey.is_null() {
+            privkey.zeroize();
+            return Err(WgStatus::NullPointer);
+        }
+        write_key(pubkey, *privkey.pubkey());
+        privkey.zeroize();
+        Ok(())
+    })
+}
+
+/// Compute the x25519 shared secret of `privkey` and `pubkey` into `shared`, see
+/// [Privkey::diffie_hellman].
+///
+/// # Safety
+///
+/// `privkey` and `pubkey` have to be null or valid for reading [WG_KEY_LEN] bytes, and `shared`
+/// null or valid for writing [WG_KEY_LEN] bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wg_dh(privkey: *const u8, pubkey: *const u8, shared: *mut u8) -> WgStatus {
+    status(|| {
+        let pubkey = Pubkey::new(read_key(pubkey)?);
+        let mut privkey = Privkey::new(read_key(privkey)?);
+        let secret = privkey.diffie_hellman(&pubkey);
+        privkey.zeroize();
+        match (shared.is_null(), secret.is_zero()) {
+            (true, _) => Err(WgStatus::NullPointer),
+            (_, true) => Err(WgStatus::LowOrder),
+            _ => {
+                write_key(shared, *secret.as_bytes());
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Parse a key of any type from the NUL-terminated `string` into `key`, detecting the encoding
+/// like [Pubkey::parse].
+///
+/// # Safety
+///
+/// `string` has to be null or a valid NUL-terminated string, and `key` null or valid for
+/// writing [WG_KEY_LEN] bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wg_parse_key(string: *const c_char, key: *mut u8) -> WgStatus {
+    status(|| {
+        if string.is_null() || key.is_null() {
+            return Err(WgStatus::NullPointer);
+        }
+        let string = CStr::from_ptr(string)
+            .to_str()
+            .map_err(|_| WgStatus::Parse)?;
+        write_key(key, *Secret::parse(string)?);
+        Ok(())
+    })
+}
+
+/// Encode `key` as NUL-terminated string into `buffer` of `length` bytes. The encoding is given
+/// by name, such as `base64` or `hex`, see [Encoding::from_name]. A buffer of
+/// [WG_ENCODED_MAX_LEN] bytes is large enough for every encoding.
+///
+/// # Safety
+///
+/// `key` has to be null or valid for reading [WG_KEY_LEN] bytes, `encoding` null or a valid
+/// NUL-terminated string, and `buffer` null or valid for writing `length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wg_encode_key(
+    key: *const u8,
+    encoding: *const c_char,
+    buffer: *mut c_char,
+    length: usize,
+) -> WgStatus {
+    status(|| {
+        if encoding.is_null() || buffer.is_null() {
+            return Err(WgStatus::NullPointer);
+        }
+        let encoding = CStr::from_ptr(encoding).to_str().ok();
+        let encoding = encoding
+            .and_then(Encoding::from_name)
+            .ok_or(WgStatus::Encoding)?;
+        let mut secret = Secret::new(read_key(key)?);
+        let mut encoded = secret.encode(encoding);
+        secret.zeroize();
+        let result = match encoded.len() < length {
+            true => {
+                let buffer = buffer.cast::<u8>();
+                buffer.copy_from_nonoverlapping(encoded.as_ptr(), encoded.len());
+                buffer.add(encoded.len()).write(0);
+                Ok(())
+            }
+            false => Err(WgStatus::BufferTooSmall),
+        };
+        encoded.zeroize();
+        result
+    })
+}
+
+#[test]
+fn test_ffi_keys() {
+    let mut privkey = [0; WG_KEY_LEN];
+    let mut pubkey = [0; WG_KEY_LEN];
+    unsafe {
+        assert_eq!(wg_generate_privkey(privkey.as_mut_ptr()), WgStatus::Ok);
+        assert_eq!(
+            wg_derive_pubkey(privkey.as_ptr(), pubkey.as_mut_ptr()),
+            WgStatus::Ok
+        );
+    }
+    assert_eq!(Privkey::new(privkey).pubkey(), Pubkey::new(pubkey));
+
+    let mut secret = [0; WG_KEY_LEN];
+    unsafe {
+        assert_eq!(wg_generate_psk(secret.as_mut_ptr()), WgStatus::Ok);
+    }
+    assert_ne!(secret, [0; WG_KEY_LEN]);
+
+    let other = Privkey::generate();
+    let mut shared = [0; WG_KEY_LEN];
+    unsafe {
+        assert_eq!(
+            wg_dh(
+                privkey.as_ptr(),
+                other.pubkey().as_ptr(),
+                shared.as_mut_ptr()
+            ),
+            WgStatus::Ok
+        );
+    }
+    assert_eq!(
+        &shared,
+        other.diffie_hellman(&Pubkey::new(pubkey)).as_bytes()
+    );
+    let zero = [0; WG_KEY_LEN];
+    let mut untouched = [1; WG_KEY_LEN];
+    unsafe {
+        assert_eq!(
+            wg_dh(privkey.as_ptr(), zero.as_ptr(), untouched.as_mut_ptr()),
+            WgStatus::LowOrder
+        );
+        assert_eq!(
+            wg_derive_pubkey(std::ptr::null(), pubkey.as_mut_ptr()),
+            WgStatus::NullPointer
+        );
+        assert_eq!(
+            wg_dh(privkey.as_ptr(), pubkey.as_ptr(), std::ptr::null_mut()),
+            WgStatus::NullPointer
+        );
+        assert_eq!(
+            wg_generate_privkey(std::ptr::null_mut()),
+            WgStatus::NullPointer
+        );
+    }
+    assert_eq!(untouched, [1; WG_KEY_LEN]);
+}
+
+#[test]
+fn test_ffi_encoding() {
+    let key = Secret::generate();
+    let mut buffer = [0 as c_char; WG_ENCODED_MAX_LEN];
+    for &encoding in Encoding::ALL {
+        let name = std::ffi::CString::new(encoding.name()).unwrap();
+        let status = unsafe {
+            wg_encode_key(
+                key.as_ptr(),
+                name.as_ptr(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            )
+        };
+        assert_eq!(status, WgStatus::Ok);
+        let encoded = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(encoded.to_str().unwrap(), key.encode(encoding));
+
+        let mut parsed = [0; WG_KEY_LEN];
+        // short base58 strings can be valid unpadded base64, which takes precedence
+        #[cfg(feature = "base58")]
+        if encoding == Encoding::Base58
+            && Secret::from_base64_unpadded(encoded.to_str().unwrap()).is_ok()
+        {
+            continue;
+        }
+        // lowercase strings can be valid in both base32 alphabets
+        match unsafe { wg_parse_key(encoded.as_ptr(), parsed.as_mut_ptr()) } {
+            WgStatus::Ok => assert_eq!(parsed, *key),
+            WgStatus::Ambiguous => {}
+            status => panic!("error parsing {encoding:?}: {status:?}"),
+        }
+    }
+
+    let encoded = key.to_base64();
+    unsafe {
+        assert_eq!(
+            wg_encode_key(
+                key.as_ptr(),
+                c"base64".as_ptr(),
+                buffer.as_mut_ptr(),
+                encoded.len()
+            ),
+            WgStatus::BufferTooSmall
+        );
+        assert_eq!(
+            wg_encode_key(
+                key.as_ptr(),
+                c"rot13".as_ptr(),
+                buffer.as_mut_ptr(),
+                buffer.len()
+            ),
+            WgStatus::Encoding
+        );
+        let mut parsed = [0; WG_KEY_LEN];
+        assert_eq!(
+            wg_parse_key(c"abc".as_ptr(), parsed.as_mut_ptr()),
+            WgStatus::Parse
+        );
+        assert_eq!(
+            CStr::from_ptr(wg_status_message(WgStatus::LowOrder as c_int)).to_str(),
+            Ok("public key of low order")
+        );
+        assert_eq!(
+            CStr::from_ptr(wg_status_message(42)).to_str(),
+            Ok("unknown status")
+        );
+    }
+}
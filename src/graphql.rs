@@ -0,0 +1,203 @@
+//! GraphQL scalars for public keys and preshared keys, for async-graphql and juniper. Both
+//! use the same scalar names. Private keys deliberately are not scalars, so that they cannot
+//! be returned from a GraphQL API by accident.
+
+use crate::{Pubkey, Secret};
+#[cfg(feature = "async-graphql")]
+use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
+
+#[cfg(feature = "async-graphql")]
+#[Scalar(name = "WireGuardPubkey")]
+/// WireGuard public key, written as base64 like `wg pubkey` does. Input values are validated
+/// and may use any encoding supported by the server.
+impl ScalarType for Pubkey {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match value {
+            Value::String(data) => Ok(data.parse()?),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn is_valid(value: &Value) -> bool {
+        matches!(value, Value::String(data) if data.parse::<Pubkey>().is_ok())
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+#[cfg(feature = "async-graphql")]
+#[Scalar(name = "WireGuardPresharedKey")]
+/// WireGuard preshared key, written as base64 like `wg genpsk` does. Input values are
+/// validated and may use any encoding supported by the server.
+impl ScalarType for Secret {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match value {
+            Value::String(mut data) => {
+                let result = data.parse().map_err(InputValueError::custom);
+                zeroize::Zeroize::zeroize(&mut data);
+                result
+            }
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn is_valid(value: &Value) -> bool {
+        matches!(value, Value::String(data) if data.parse::<Secret>().is_ok())
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+/// WireGuard public key, written as base64 like `wg pubkey` does. Input values are validated
+/// and may use any encoding supported by the server.
+#[cfg(feature = "juniper")]
+#[juniper::graphql_scalar(with = pubkey_scalar, parse_token(String))]
+type WireGuardPubkey = Pubkey;
+
+#[cfg(feature = "juniper")]
+mod pubkey_scalar {
+    use super::*;
+    use juniper::{InputValue, ScalarValue, Value};
+
+    pub(super) fn to_output<S: ScalarValue>(value: &WireGuardPubkey) -> Value<S> {
+        Value::scalar(value.to_string())
+    }
+
+    pub(super) fn from_input<S: ScalarValue>(
+        value: &InputValue<S>,
+    ) -> Result<WireGuardPubkey, String> {
+        value
+            .as_string_value()
+            .ok_or_else(|| format!("Expected `String`, found: {value}"))
+            .and_then(|data| data.parse().map_err(|error| format!("{error}")))
+    }
+}
+
+/// WireGuard preshared key, written as base64 like `wg genpsk` does. Input values are
+/// validated and may use any encoding supported by the server.
+#[cfg(feature = "juniper")]
+#[juniper::graphql_scalar(with = secret_scalar, parse_token(String))]
+type WireGuardPresharedKey = Secret;
+
+#[cfg(feature = "juniper")]
+mod secret_scalar {
+    use super::*;
+    use juniper::{InputValue, ScalarValue, Value};
+
+    pub(super) fn to_output<S: ScalarValue>(value: &WireGuardPresharedKey) -> Value<S> {
+        Value::scalar(value.to_string())
+    }
+
+    pub(super) fn from_input<S: ScalarValue>(
+        value: &InputValue<S>,
+    ) -> Result<WireGuardPresharedKey, String> {
+        value
+            .as_string_value()
+            .ok_or_else(|| format!("Expected `String`, found: {value}"))
+            .and_then(|data| data.parse().map_err(|error| format!("{error}")))
+    }
+}
+
+#[cfg(feature = "async-graphql")]
+#[cfg(any(
+    feature = "hex",
+    feature = "base64",
+    feature = "base32",
+    feature = "base58",
+    feature = "proquint"
+))]
+#[test]
+fn test_graphql_scalar() {
+    let pubkey = Pubkey::new([1; 32]);
+    let secret = Secret::new([2; 32]);
+    assert_eq!(pubkey.to_value(), Value::String(pubkey.to_string()));
+    assert_eq!(
+        <Pubkey as ScalarType>::parse(pubkey.to_value()).unwrap(),
+        pubkey
+    );
+    assert_eq!(
+        <Secret as ScalarType>::parse(secret.to_value()).unwrap(),
+        secret
+    );
+    assert!(Pubkey::is_valid(&pubkey.to_value()));
+    assert!(!Pubkey::is_valid(&Value::String("abc".into())));
+    assert!(!Secret::is_valid(&Value::Number(1.into())));
+    assert!(<Pubkey as ScalarType>::parse(Value::String("abc".into())).is_err());
+    assert!(<Secret as ScalarType>::parse(Value::Null).is_err());
+}
+
+#[cfg(all(feature = "async-graphql", feature = "base64"))]
+#[test]
+fn test_graphql_schema() {
+    use crate::block_on;
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn peer(&self, pubkey: Pubkey) -> Pubkey {
+            pubkey
+        }
+    }
+
+    let schema = Schema::new(Query, EmptyMutation, EmptySubscription);
+    assert!(schema.sdl().contains("scalar WireGuardPubkey"));
+    let pubkey = Pubkey::new([1; 32]);
+    let query = format!("{{ peer(pubkey: \"{}\") }}", pubkey);
+    let response = block_on(schema.execute(query));
+    assert!(response.errors.is_empty());
+    assert_eq!(
+        response.data.into_json().unwrap()["peer"],
+        pubkey.to_string()
+    );
+    let response = block_on(schema.execute("{ peer(pubkey: \"abc\") }"));
+    assert_eq!(response.errors.len(), 1);
+}
+
+#[cfg(all(feature = "juniper", feature = "base64"))]
+#[test]
+fn test_juniper_schema() {
+    use juniper::{graphql_object, graphql_value, EmptyMutation, EmptySubscription, RootNode};
+
+    struct Query;
+
+    #[graphql_object]
+    impl Query {
+        fn peer(pubkey: Pubkey, preshared_key: Option<Secret>) -> Pubkey {
+            let _ = preshared_key;
+            pubkey
+        }
+    }
+
+    let schema = RootNode::new(
+        Query,
+        EmptyMutation::<()>::new(),
+        EmptySubscription::<()>::new(),
+    );
+    let query = "{ __type(name: \"WireGuardPresharedKey\") { kind } }";
+    let (value, _) = juniper::execute_sync(query, None, &schema, &Default::default(), &()).unwrap();
+    assert_eq!(value, graphql_value!({ "__type": { "kind": "SCALAR" } }));
+    let pubkey = Pubkey::new([1; 32]);
+    let query = format!(
+        "{{ peer(pubkey: \"{}\", presharedKey: \"{}\") }}",
+        pubkey,
+        Secret::new([2; 32])
+    );
+    let (value, errors) =
+        juniper::execute_sync(&query, None, &schema, &Default::default(), &()).unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(value, graphql_value!({ "peer": (pubkey.to_string()) }));
+    let result = juniper::execute_sync(
+        "{ peer(pubkey: \"abc\") }",
+        None,
+        &schema,
+        &Default::default(),
+        &(),
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,329 @@
+//! Parsing of WireGuard handshake messages as seen on the wire, and verification of their
+//! `mac1` field. Every handshake message carries a `mac1` which is keyed with the public key of
+//! the receiver, so it can be used to determine which known peer a captured message was sent
+//! to without having any private keys.
+//!
+//! The [pcap] module uses this to extract handshakes from packet captures.
+
+use crate::Pubkey;
+use blake2::digest::consts::U16;
+use blake2::digest::{FixedOutput, KeyInit, Mac, Update};
+use blake2::{Blake2s256, Blake2sMac, Digest};
+
+pub mod pcap;
+
+/// Length (in bytes) of a handshake initiation message.
+pub const INITIATION_LEN: usize = 148;
+
+/// Length (in bytes) of a handshake response message.
+pub const RESPONSE_LEN: usize = 92;
+
+/// Length (in bytes) of the `mac1` and `mac2` fields.
+pub const MAC_LEN: usize = 16;
+
+/// Label used to derive the `mac1` key from the receiver's public key.
+const LABEL_MAC1: &[u8] = b"mac1----";
+
+/// Message type of handshake initiations.
+const TYPE_INITIATION: u8 = 1;
+
+/// Message type of handshake responses.
+const TYPE_RESPONSE: u8 = 2;
+
+/// Key used to compute `mac1` of messages sent to a specific peer. Deriving it requires a hash
+/// computation, so it should be computed once per peer when checking many messages.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Mac1Key([u8; 32]);
+
+impl Mac1Key {
+    /// Derive the `mac1` key for messages sent to the peer with this public key.
+    pub fn new(pubkey: &Pubkey) -> Self {
+        let mut hash = Blake2s256::new();
+        Digest::update(&mut hash, LABEL_MAC1);
+        Digest::update(&mut hash, &pubkey[..]);
+        Mac1Key(hash.finalize().into())
+    }
+
+    /// Compute `mac1` over the given message contents.
+    pub fn compute(&self, data: &[u8]) -> [u8; MAC_LEN] {
+        let mut mac = <Blake2sMac<U16> as KeyInit>::new_from_slice(&self.0)
+            .expect("mac1 key has valid length");
+        Update::update(&mut mac, data);
+        mac.finalize_fixed().into()
+    }
+
+    /// Verify `mac1` over the given message contents in constant time.
+    pub fn verify(&self, data: &[u8], mac1: &[u8; MAC_LEN]) -> bool {
+        let mut mac = <Blake2sMac<U16> as KeyInit>::new_from_slice(&self.0)
+            .expect("mac1 key has valid length");
+        Update::update(&mut mac, data);
+        mac.verify_slice(mac1).is_ok()
+    }
+}
+
+impl From<&Pubkey> for Mac1Key {
+    fn from(pubkey: &Pubkey) -> Self {
+        Mac1Key::new(pubkey)
+    }
+}
+
+/// Kind of handshake message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    /// Handshake initiation, sent by the initiator to the responder.
+    Initiation,
+    /// Handshake response, sent by the responder to the initiator.
+    Response,
+}
+
+/// Handshake message as seen on the wire.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HandshakeMessage<'a> {
+    kind: MessageKind,
+    data: &'a [u8],
+}
+
+impl<'a> HandshakeMessage<'a> {
+    /// Parse handshake message from the payload of a UDP packet. Returns `None` if the payload
+    /// is not a WireGuard handshake initiation or response.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < 4 || data[1..4] != [0, 0, 0] {
+            return None;
+        }
+        let kind = match (data[0], data.len()) {
+            (TYPE_INITIATION, INITIATION_LEN) => MessageKind::Initiation,
+            (TYPE_RESPONSE, RESPONSE_LEN) => MessageKind::Response,
+            _ => return None,
+        };
+        Some(HandshakeMessage { kind, data })
+    }
+
+    /// Kind of this message.
+    pub fn kind(&self) -> MessageKind {
+        self.kind
+    }
+
+    /// Raw bytes of this message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn index(&self, offset: usize) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.data[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    /// Index chosen by the sender of this message to identify the session.
+    pub fn sender_index(&self) -> u32 {
+        self.index(4)
+    }
+
+    /// Index chosen by the initiator, which a response is replying to.
+    pub fn receiver_index(&self) -> Option<u32> {
+        match self.kind {
+            MessageKind::Initiation => None,
+            MessageKind::Response => Some(self.index(8)),
+        }
+    }
+
+    /// Unencrypted ephemeral public key of the sender.
+    pub fn ephemeral(&self) -> Pubkey {
+        let offset = match self.kind {
+            MessageKind::Initiation => 8,
+            MessageKind::Response => 12,
+        };
+        let mut key = [0; 32];
+        key.copy_from_slice(&self.data[offset..offset + 32]);
+        Pubkey::new(key)
+    }
+
+    /// Offset of the `mac1` field, which covers all bytes before it.
+    fn mac1_offset(&self) -> usize {
+        self.data.len() - 2 * MAC_LEN
+    }
+
+    /// The `mac1` field of this message.
+    pub fn mac1(&self) -> [u8; MAC_LEN] {
+        let offset = self.mac1_offset();
+        let mut mac1 = [0; MAC_LEN];
+        mac1.copy_from_slice(&self.data[offset..offset + MAC_LEN]);
+        mac1
+    }
+
+    /// The `mac2` field of this message.
+    pub fn mac2(&self) -> [u8; MAC_LEN] {
+        let offset = self.mac1_offset() + MAC_LEN;
+        let mut mac2 = [0; MAC_LEN];
+        mac2.copy_from_slice(&self.data[offset..offset + MAC_LEN]);
+        mac2
+    }
+
+    /// Determine if this message was sent to the peer with the given `mac1` key.
+    pub fn verify_mac1(&self, key: &Mac1Key) -> bool {
+        key.verify(&self.data[..self.mac1_offset()], &self.mac1())
+    }
+}
+
+/// Set of known peers with precomputed `mac1` keys, used to identify which peer captured
+/// handshake messages were sent to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KnownPeers {
+    keys: Vec<(Pubkey, Mac1Key)>,
+}
+
+impl KnownPeers {
+    /// Create empty set of known peers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a peer, precomputing its `mac1` key.
+    pub fn insert(&mut self, pubkey: Pubkey) {
+        self.keys.push((pubkey, Mac1Key::new(&pubkey)));
+    }
+
+    /// Number of known peers.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Determine if there are no known peers.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Identify the known peer which a handshake message was sent to.
+    pub fn receiver(&self, message: &HandshakeMessage<'_>) -> Option<Pubkey> {
+        self.keys
+            .iter()
+            .find(|(_, key)| message.verify_mac1(key))
+            .map(|(pubkey, _)| *pubkey)
+    }
+}
+
+impl FromIterator<Pubkey> for KnownPeers {
+    fn from_iter<I: IntoIterator<Item = Pubkey>>(iter: I) -> Self {
+        let mut peers = KnownPeers::new();
+        for pubkey in iter {
+            peers.insert(pubkey);
+        }
+        peers
+    }
+}
+
+impl<'a> FromIterator<&'a Pubkey> for KnownPeers {
+    fn from_iter<I: IntoIterator<Item = &'a Pubkey>>(iter: I) -> Self {
+        iter.into_iter().copied().collect()
+    }
+}
+
+/// Identify the responder of a captured handshake initiation by checking its `mac1` against the
+/// known peers. Returns `None` if the data is not a handshake initiation or was not sent to any
+/// of the known peers.
+///
+/// This takes the whole initiation rather than only its `mac1`, since `mac1` is a MAC over the
+/// preceding bytes of the message and can not be checked without them.
+pub fn identify_responder(initiation: &[u8], candidates: &KnownPeers) -> Option<Pubkey> {
+    let message = HandshakeMessage::parse(initiation)?;
+    if message.kind() != MessageKind::Initiation {
+        return None;
+    }
+    candidates.receiver(&message)
+}
+
+#[cfg(test)]
+pub(crate) fn test_message(kind: MessageKind) -> Vec<u8> {
+    let (len, message_type) = match kind {
+        MessageKind::Initiation => (INITIATION_LEN, TYPE_INITIATION),
+        MessageKind::Response => (RESPONSE_LEN, TYPE_RESPONSE),
+    };
+    let mut data = vec![0; len];
+    data[0] = message_type;
+    for (i, byte) in data.iter_mut().enumerate().take(len - 2 * MAC_LEN).skip(4) {
+        *byte = i as u8;
+    }
+    data
+}
+
+#[cfg(test)]
+const TEST_PUBKEY: [u8; 32] = [
+    0xc5, 0x32, 0x01, 0x03, 0x9a, 0xdb, 0xa1, 0x4b, 0xe7, 0x1f, 0x88, 0x6d, 0xa1, 0xd8, 0xdb, 0xe9,
+    0xee, 0xbd, 0xed, 0x08, 0xcb, 0x11, 0x1b, 0x75, 0x34, 0x00, 0x78, 0x99, 0x9a, 0xa9, 0xf0, 0x38,
+];
+
+#[test]
+fn test_mac1_initiation() {
+    let key = Mac1Key::new(&Pubkey::new(TEST_PUBKEY));
+    let mut data = test_message(MessageKind::Initiation);
+    let mac1 = key.compute(&data[..116]);
+    assert_eq!(
+        mac1,
+        [
+            0xd2, 0xc8, 0x70, 0x2b, 0xf9, 0x21, 0x5f, 0xca, 0x2a, 0xd8, 0x08, 0x93, 0xd2, 0x3b,
+            0x5c, 0xcb,
+        ]
+    );
+    data[116..132].copy_from_slice(&mac1);
+    let message = HandshakeMessage::parse(&data).unwrap();
+    assert_eq!(message.kind(), MessageKind::Initiation);
+    assert_eq!(message.sender_index(), 0x07060504);
+    assert_eq!(message.receiver_index(), None);
+    assert_eq!(message.ephemeral()[0], 8);
+    assert!(message.verify_mac1(&key));
+    assert!(!message.verify_mac1(&Mac1Key::new(&Pubkey::generate())));
+}
+
+#[test]
+fn test_mac1_response() {
+    let key = Mac1Key::new(&Pubkey::new(TEST_PUBKEY));
+    let mut data = test_message(MessageKind::Response);
+    let mac1 = key.compute(&data[..60]);
+    assert_eq!(
+        mac1,
+        [
+            0xe2, 0x3b, 0xe5, 0x13, 0x94, 0x8c, 0xa4, 0xc0, 0x13, 0xc4, 0x31, 0x1e, 0x2b, 0x19,
+            0x42, 0x63,
+        ]
+    );
+    data[60..76].copy_from_slice(&mac1);
+    let message = HandshakeMessage::parse(&data).unwrap();
+    assert_eq!(message.kind(), MessageKind::Response);
+    assert_eq!(message.receiver_index(), Some(0x0b0a0908));
+    assert_eq!(message.ephemeral()[0], 12);
+    assert!(message.verify_mac1(&key));
+}
+
+#[test]
+fn test_handshake_parse_invalid() {
+    assert!(HandshakeMessage::parse(&[]).is_none());
+    assert!(HandshakeMessage::parse(&[1, 0, 0, 0]).is_none());
+    let mut data = test_message(MessageKind::Initiation);
+    data[2] = 1;
+    assert!(HandshakeMessage::parse(&data).is_none());
+    let mut data = test_message(MessageKind::Response);
+    data[0] = 4;
+    assert!(HandshakeMessage::parse(&data).is_none());
+}
+
+#[test]
+fn test_identify_responder() {
+    let responder = Pubkey::new(TEST_PUBKEY);
+    let others: Vec<Pubkey> = (0..10).map(|_| Pubkey::generate()).collect();
+    let mut candidates: KnownPeers = others.iter().collect();
+    let mut data = test_message(MessageKind::Initiation);
+    let mac1 = Mac1Key::new(&responder).compute(&data[..116]);
+    data[116..132].copy_from_slice(&mac1);
+    assert_eq!(identify_responder(&data, &candidates), None);
+    candidates.insert(responder);
+    assert_eq!(candidates.len(), 11);
+    assert_eq!(identify_responder(&data, &candidates), Some(responder));
+    assert_eq!(identify_responder(&data[..100], &candidates), None);
+
+    let mut data = test_message(MessageKind::Response);
+    let mac1 = Mac1Key::new(&responder).compute(&data[..60]);
+    data[60..76].copy_from_slice(&mac1);
+    assert_eq!(identify_responder(&data, &candidates), None);
+    let message = HandshakeMessage::parse(&data).unwrap();
+    assert_eq!(candidates.receiver(&message), Some(responder));
+}
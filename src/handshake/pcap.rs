@@ -0,0 +1,329 @@
+//! Extraction of WireGuard handshakes from packet captures in the classic pcap format. This is
+//! intended for incident response: given a capture and the public keys of known peers, it
+//! reports every handshake message along with the known peer it was sent to, if any.
+//!
+//! Captures with Ethernet, Linux cooked (SLL and SLL2), BSD loopback and raw IP link types are
+//! supported. Fragmented IP packets and IPv6 extension headers are skipped.
+
+use super::{HandshakeMessage, KnownPeers, MessageKind};
+use crate::Pubkey;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Possible errors that can be generated when reading packet captures.
+#[derive(Error, Debug)]
+pub enum PcapError {
+    /// Error reading capture
+    #[error("error reading capture")]
+    Io(#[from] std::io::Error),
+    /// Capture does not start with a pcap magic number
+    #[error("not a pcap capture")]
+    Magic,
+    /// Link type of capture is not supported
+    #[error("unsupported link type {0}")]
+    LinkType(u32),
+    /// Capture ends in the middle of a record
+    #[error("truncated capture")]
+    Truncated,
+}
+
+/// Handshake message extracted from a packet capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedHandshake {
+    /// Time at which the packet was captured, relative to the Unix epoch.
+    pub timestamp: Duration,
+    /// Source address of the packet.
+    pub source: SocketAddr,
+    /// Destination address of the packet.
+    pub destination: SocketAddr,
+    /// Kind of handshake message.
+    pub kind: MessageKind,
+    /// Index chosen by the sender to identify the session.
+    pub sender_index: u32,
+    /// For responses, the index of the initiation being replied to.
+    pub receiver_index: Option<u32>,
+    /// Ephemeral public key of the sender.
+    pub ephemeral: Pubkey,
+    /// Known peer which the message was sent to, determined by verifying `mac1`.
+    pub receiver: Option<Pubkey>,
+}
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+
+const PROTOCOL_UDP: u8 = 17;
+
+/// Maximum size of a single record, to prevent malicious captures from exhausting memory.
+const MAX_RECORD_LEN: usize = 256 * 1024;
+
+fn read_u16_be(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Parse link layer header, returning the IP packet.
+fn link_payload(link_type: u32, big_endian: bool, data: &[u8]) -> Option<&[u8]> {
+    match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = read_u16_be(data, offset)?;
+            while ethertype == ETHERTYPE_VLAN {
+                offset += 4;
+                ethertype = read_u16_be(data, offset)?;
+            }
+            match ethertype {
+                ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => data.get(offset + 2..),
+                _ => None,
+            }
+        }
+        LINKTYPE_LINUX_SLL => data.get(16..),
+        LINKTYPE_LINUX_SLL2 => data.get(20..),
+        LINKTYPE_NULL => {
+            let bytes = data.get(0..4)?;
+            let family = if big_endian {
+                u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            } else {
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            };
+            // AF_INET is 2 everywhere, AF_INET6 differs between operating systems.
+            match family {
+                2 | 10 | 24 | 28 | 30 => data.get(4..),
+                _ => None,
+            }
+        }
+        _ => Some(data),
+    }
+}
+
+/// Parse IP and UDP headers, returning source, destination and UDP payload.
+fn udp_payload(data: &[u8]) -> Option<(SocketAddr, SocketAddr, &[u8])> {
+    let (source, destination, udp) = match data.first()? >> 4 {
+        4 => {
+            let header_len = ((data[0] & 0x0f) as usize) * 4;
+            let fragment = read_u16_be(data, 6)? & 0x3fff;
+            if data.get(9)? != &PROTOCOL_UDP || fragment != 0 || header_len < 20 {
+                return None;
+            }
+            let total_len = read_u16_be(data, 2)? as usize;
+            let source: [u8; 4] = data.get(12..16)?.try_into().ok()?;
+            let destination: [u8; 4] = data.get(16..20)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv4Addr::from(source)),
+                IpAddr::from(Ipv4Addr::from(destination)),
+                data.get(header_len..total_len.max(header_len).min(data.len()))?,
+            )
+        }
+        6 => {
+            if data.get(6)? != &PROTOCOL_UDP {
+                return None;
+            }
+            let source: [u8; 16] = data.get(8..24)?.try_into().ok()?;
+            let destination: [u8; 16] = data.get(24..40)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv6Addr::from(source)),
+                IpAddr::from(Ipv6Addr::from(destination)),
+                data.get(40..)?,
+            )
+        }
+        _ => return None,
+    };
+    let source_port = read_u16_be(udp, 0)?;
+    let destination_port = read_u16_be(udp, 2)?;
+    let udp_len = read_u16_be(udp, 4)? as usize;
+    let payload = udp.get(8..udp_len.max(8).min(udp.len()))?;
+    Some((
+        SocketAddr::new(source, source_port),
+        SocketAddr::new(destination, destination_port),
+        payload,
+    ))
+}
+
+/// Read exactly `buffer.len()` bytes, returning `false` on a clean end of file.
+fn read_record<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<bool, PcapError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(PcapError::Truncated),
+            Ok(count) => filled += count,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(true)
+}
+
+/// Extract all WireGuard handshake messages from a pcap capture. Every message is checked
+/// against the `mac1` keys of the known peers to determine which peer it was sent to.
+pub fn extract_handshakes<R: Read>(
+    mut reader: R,
+    candidates: &KnownPeers,
+) -> Result<Vec<CapturedHandshake>, PcapError> {
+    let mut header = [0; 24];
+    if !read_record(&mut reader, &mut header)? {
+        return Err(PcapError::Magic);
+    }
+    let (big_endian, nanos) = match header[0..4] {
+        [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+        [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+        [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+        [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+        _ => return Err(PcapError::Magic),
+    };
+    let read_u32 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+    let link_type = read_u32(&header[20..24]) & 0x0fff_ffff;
+    match link_type {
+        LINKTYPE_NULL | LINKTYPE_ETHERNET | LINKTYPE_RAW | LINKTYPE_LINUX_SLL | LINKTYPE_IPV4
+        | LINKTYPE_IPV6 | LINKTYPE_LINUX_SLL2 => {}
+        other => return Err(PcapError::LinkType(other)),
+    }
+
+    let mut handshakes = Vec::new();
+    let mut record = [0; 16];
+    let mut data = Vec::new();
+    while read_record(&mut reader, &mut record)? {
+        let seconds = read_u32(&record[0..4]) as u64;
+        let fraction = read_u32(&record[4..8]);
+        let length = read_u32(&record[8..12]) as usize;
+        if length > MAX_RECORD_LEN {
+            return Err(PcapError::Truncated);
+        }
+        data.resize(length, 0);
+        if !read_record(&mut reader, &mut data)? && length > 0 {
+            return Err(PcapError::Truncated);
+        }
+
+        let packet = match link_payload(link_type, big_endian, &data) {
+            Some(packet) => packet,
+            None => continue,
+        };
+        let (source, destination, payload) = match udp_payload(packet) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        let message = match HandshakeMessage::parse(payload) {
+            Some(message) => message,
+            None => continue,
+        };
+        let timestamp = if nanos {
+            Duration::new(seconds, fraction)
+        } else {
+            Duration::new(seconds, 0) + Duration::from_micros(fraction as u64)
+        };
+        handshakes.push(CapturedHandshake {
+            timestamp,
+            source,
+            destination,
+            kind: message.kind(),
+            sender_index: message.sender_index(),
+            receiver_index: message.receiver_index(),
+            ephemeral: message.ephemeral(),
+            receiver: candidates.receiver(&message),
+        });
+    }
+    Ok(handshakes)
+}
+
+#[cfg(test)]
+fn test_capture(packets: &[Vec<u8>]) -> Vec<u8> {
+    let mut capture = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+    capture.extend_from_slice(&[0; 8]);
+    capture.extend_from_slice(&65535u32.to_le_bytes());
+    capture.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    for (i, packet) in packets.iter().enumerate() {
+        capture.extend_from_slice(&(1_600_000_000 + i as u32).to_le_bytes());
+        capture.extend_from_slice(&500u32.to_le_bytes());
+        capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        capture.extend_from_slice(packet);
+    }
+    capture
+}
+
+#[cfg(test)]
+fn test_ipv4_packet(payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0; 12];
+    packet.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+    let total_len = 20 + 8 + payload.len() as u16;
+    packet.extend_from_slice(&[0x45, 0]);
+    packet.extend_from_slice(&total_len.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0x40, 0, 64, PROTOCOL_UDP, 0, 0]);
+    packet.extend_from_slice(&[192, 168, 1, 2, 192, 168, 1, 1]);
+    packet.extend_from_slice(&40000u16.to_be_bytes());
+    packet.extend_from_slice(&51820u16.to_be_bytes());
+    packet.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+#[test]
+fn test_extract_handshakes() {
+    let responder = crate::Privkey::generate().pubkey();
+    let other = crate::Privkey::generate().pubkey();
+    let mut initiation = super::test_message(MessageKind::Initiation);
+    let mac1 = super::Mac1Key::new(&responder).compute(&initiation[..116]);
+    initiation[116..132].copy_from_slice(&mac1);
+    let response = super::test_message(MessageKind::Response);
+    let capture = test_capture(&[
+        test_ipv4_packet(&initiation),
+        test_ipv4_packet(&[4, 0, 0, 0, 1, 2, 3]),
+        test_ipv4_packet(&response),
+    ]);
+
+    let handshakes =
+        extract_handshakes(&capture[..], &[other, responder].iter().collect()).unwrap();
+    assert_eq!(handshakes.len(), 2);
+    let first = &handshakes[0];
+    assert_eq!(first.kind, MessageKind::Initiation);
+    assert_eq!(
+        first.source,
+        "192.168.1.2:40000".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        first.destination,
+        "192.168.1.1:51820".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(first.timestamp, Duration::new(1_600_000_000, 500_000));
+    assert_eq!(first.receiver, Some(responder));
+    assert_eq!(handshakes[1].kind, MessageKind::Response);
+    assert_eq!(handshakes[1].receiver, None);
+}
+
+#[test]
+fn test_extract_handshakes_invalid() {
+    assert!(matches!(
+        extract_handshakes(&b"not a capture at all...."[..], &KnownPeers::new()),
+        Err(PcapError::Magic)
+    ));
+    let mut capture = test_capture(&[test_ipv4_packet(&[])]);
+    capture.truncate(capture.len() - 1);
+    assert!(matches!(
+        extract_handshakes(&capture[..], &KnownPeers::new()),
+        Err(PcapError::Truncated)
+    ));
+    let mut capture = test_capture(&[]);
+    capture[20] = 200;
+    assert!(matches!(
+        extract_handshakes(&capture[..], &KnownPeers::new()),
+        Err(PcapError::LinkType(200))
+    ));
+}
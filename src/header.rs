@@ -0,0 +1,436 @@
+//! Request guards and extractors which read keys from request headers, for services which
+//! identify peers by a header such as `X-WireGuard-Pubkey`. [FromHeader] is a Rocket request
+//! guard with the `rocket` feature and an axum extractor with the `axum` feature, and the
+//! filters of the `warp` feature apply the same rules: the header must be present exactly once,
+//! and contain a key in any supported encoding. With the `headers` feature, [XWireGuardPubkey]
+//! and [XWireGuardPresharedKey] are typed headers for the headers crate, which is used by hyper
+//! based clients and servers.
+//!
+//! The header name is given by a type implementing [HeaderName], so that services can define
+//! their own headers:
+//!
+//! ```
+//! use wireguard_keys::header::{FromHeader, HeaderName};
+//! use wireguard_keys::Pubkey;
+//!
+//! struct Node;
+//!
+//! impl HeaderName for Node {
+//!     const NAME: &'static str = "X-Node-Key";
+//! }
+//!
+//! type NodeKey = FromHeader<Pubkey, Node>;
+//! ```
+
+use crate::{ParseError, Pubkey};
+#[cfg(feature = "rocket")]
+use rocket::http::{ContentType, Status};
+#[cfg(feature = "rocket")]
+use rocket::request::{FromRequest, Outcome, Request};
+#[cfg(feature = "rocket")]
+use rocket::response::{Responder, Response};
+use std::fmt;
+#[cfg(feature = "rocket")]
+use std::io::Cursor;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Name of the header which a [FromHeader] guard reads.
+pub trait HeaderName {
+    /// Header name, which is matched case-insensitively.
+    const NAME: &'static str;
+}
+
+/// Header `X-WireGuard-Pubkey`, the default header of [HeaderPubkey].
+#[derive(Copy, Clone, Debug)]
+pub struct WireGuardPubkey;
+
+impl HeaderName for WireGuardPubkey {
+    const NAME: &'static str = "X-WireGuard-Pubkey";
+}
+
+/// Header `X-WireGuard-Preshared-Key`.
+#[derive(Copy, Clone, Debug)]
+pub struct WireGuardPresharedKey;
+
+impl HeaderName for WireGuardPresharedKey {
+    const NAME: &'static str = "X-WireGuard-Preshared-Key";
+}
+
+/// Possible errors that can be generated when reading keys from headers.
+#[derive(Error, Debug)]
+pub enum HeaderError {
+    /// Header is not present
+    #[error("missing header {0}")]
+    Missing(&'static str),
+    /// Header is present more than once
+    #[error("header {0} must only be present once")]
+    Duplicate(&'static str),
+    /// Header value is not visible ASCII
+    #[error("header {0} is not valid text")]
+    Encoding(&'static str),
+    /// Header value is not a valid key
+    #[error("invalid key in header {0}: {1}")]
+    Parse(&'static str, #[source] ParseError),
+}
+
+impl HeaderError {
+    /// Machine-readable error code, for responses with structured bodies.
+    pub fn code(&self) -> &'static str {
+        match self {
+            HeaderError::Missing(_) => "missing_header",
+            HeaderError::Duplicate(_) => "duplicate_header",
+            HeaderError::Encoding(_) => "invalid_header",
+            HeaderError::Parse(_, _) => "invalid_key",
+        }
+    }
+}
+
+/// Parse key from all values of the named header, which must be exactly one.
+pub(crate) fn parse_values<'a, K, I>(name: &'static str, values: I) -> Result<K, HeaderError>
+where
+    I: IntoIterator<Item = &'a str>,
+    K: FromStr<Err = ParseError>,
+{
+    let mut values = values.into_iter();
+    let value = values.next().ok_or(HeaderError::Missing(name))?;
+    if values.next().is_some() {
+        return Err(HeaderError::Duplicate(name));
+    }
+    value
+        .trim()
+        .parse()
+        .map_err(|error| HeaderError::Parse(name, error))
+}
+
+#[cfg(feature = "rocket")]
+impl<'r> Responder<'r, 'static> for HeaderError {
+    fn respond_to(self, _request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let message = self.to_string();
+        Response::build()
+            .status(Status::BadRequest)
+            .header(ContentType::Plain)
+            .sized_body(message.len(), Cursor::new(message))
+            .ok()
+    }
+}
+
+/// Request guard or extractor reading a key of type `K` from the header named by `H`.
+pub struct FromHeader<K, H: HeaderName = WireGuardPubkey>(pub K, PhantomData<H>);
+
+/// Request guard or extractor reading a public key from the `X-WireGuard-Pubkey` header.
+pub type HeaderPubkey = FromHeader<Pubkey, WireGuardPubkey>;
+
+impl<K, H: HeaderName> FromHeader<K, H> {
+    /// Wrap key.
+    pub fn new(key: K) -> Self {
+        FromHeader(key, PhantomData)
+    }
+
+    /// Unwrap key.
+    pub fn into_inner(self) -> K {
+        self.0
+    }
+
+    /// Parse key from all values of the header in a request, which must be exactly one.
+    pub fn from_values<'a, I>(values: I) -> Result<Self, HeaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+        K: FromStr<Err = ParseError>,
+    {
+        parse_values(H::NAME, values).map(Self::new)
+    }
+
+    /// Parse key from the headers of a Rocket request.
+    #[cfg(feature = "rocket")]
+    pub fn from_request_headers(request: &Request<'_>) -> Result<Self, HeaderError>
+    where
+        K: FromStr<Err = ParseError>,
+    {
+        Self::from_values(request.headers().get(H::NAME))
+    }
+}
+
+impl<K: fmt::Debug, H: HeaderName> fmt::Debug for FromHeader<K, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FromHeader").field(&self.0).finish()
+    }
+}
+
+impl<K, H: HeaderName> Deref for FromHeader<K, H> {
+    type Target = K;
+    fn deref(&self) -> &K {
+        &self.0
+    }
+}
+
+impl<K, H: HeaderName> DerefMut for FromHeader<K, H> {
+    fn deref_mut(&mut self) -> &mut K {
+        &mut self.0
+    }
+}
+
+/// When the header is missing, contains more than one value or cannot be parsed, the guard fails
+/// with status 400. Taking a `Result` responds with a message which says what is wrong with the
+/// header:
+///
+/// ```
+/// use rocket::get;
+/// use wireguard_keys::header::{HeaderError, HeaderPubkey};
+///
+/// #[get("/peer")]
+/// fn peer(pubkey: Result<HeaderPubkey, HeaderError>) -> Result<String, HeaderError> {
+///     Ok(pubkey?.to_string())
+/// }
+/// ```
+#[cfg(feature = "rocket")]
+#[rocket::async_trait]
+impl<'r, K, H> FromRequest<'r> for FromHeader<K, H>
+where
+    K: FromStr<Err = ParseError> + Send,
+    H: HeaderName + Send,
+{
+    type Error = HeaderError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match Self::from_request_headers(request) {
+            Ok(key) => Outcome::Success(key),
+            Err(error) => Outcome::Error((Status::BadRequest, error)),
+        }
+    }
+}
+
+/// Implement [headers::Header] for a typed header containing a key.
+#[cfg(feature = "headers")]
+macro_rules! impl_typed_header {
+    ($header:ident, $name:expr, $sensitive:expr) => {
+        impl headers::Header for $header {
+            fn name() -> &'static headers::HeaderName {
+                static NAME: headers::HeaderName = headers::HeaderName::from_static($name);
+                &NAME
+            }
+
+            fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
+            where
+                I: Iterator<Item = &'i headers::HeaderValue>,
+            {
+                let values = values
+                    .map(|value| value.to_str())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| headers::Error::invalid())?;
+                parse_values($name, values)
+                    .map($header)
+                    .map_err(|_| headers::Error::invalid())
+            }
+
+            fn encode<E: Extend<headers::HeaderValue>>(&self, values: &mut E) {
+                let mut value = headers::HeaderValue::from_str(&self.0.to_string())
+                    .expect("encoded keys are valid header values");
+                value.set_sensitive($sensitive);
+                values.extend(std::iter::once(value));
+            }
+        }
+    };
+}
+
+/// Typed header `X-WireGuard-Pubkey`, for use with the [headers](https://docs.rs/headers)
+/// crate, for example through `HeaderMapExt::typed_get`.
+#[cfg(feature = "headers")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct XWireGuardPubkey(pub Pubkey);
+
+#[cfg(feature = "headers")]
+impl_typed_header!(XWireGuardPubkey, "x-wireguard-pubkey", false);
+
+/// Typed header `X-WireGuard-Preshared-Key`, for use with the [headers](https://docs.rs/headers)
+/// crate. Encoded values are marked as sensitive, so that HTTP implementations do not compress
+/// or log them.
+#[cfg(feature = "headers")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct XWireGuardPresharedKey(pub crate::Secret);
+
+#[cfg(feature = "headers")]
+impl_typed_header!(XWireGuardPresharedKey, "x-wireguard-preshared-key", true);
+
+#[cfg(all(
+    test,
+    feature = "rocket",
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+#[rocket::get("/peer")]
+fn test_route_peer(pubkey: HeaderPubkey) -> String {
+    pubkey.to_string()
+}
+
+#[cfg(all(
+    test,
+    feature = "rocket",
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+#[rocket::get("/secret")]
+fn test_route_secret(
+    secret: Result<FromHeader<crate::Secret, TestSecretHeader>, HeaderError>,
+) -> Result<String, HeaderError> {
+    Ok(secret?.to_string())
+}
+
+#[cfg(all(
+    test,
+    feature = "rocket",
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+struct TestSecretHeader;
+
+#[cfg(all(
+    test,
+    feature = "rocket",
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+impl HeaderName for TestSecretHeader {
+    const NAME: &'static str = "X-Test-Secret";
+}
+
+#[cfg(all(
+    feature = "rocket",
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+#[test]
+fn test_header_guard() {
+    use crate::Secret;
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+
+    let rocket = rocket::build().mount("/", rocket::routes![test_route_peer, test_route_secret]);
+    let client = Client::untracked(rocket).unwrap();
+    let pubkey = Pubkey::new([1; 32]);
+    let response = client
+        .get("/peer")
+        .header(Header::new("x-wireguard-pubkey", pubkey.to_string()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().unwrap(), pubkey.to_string());
+
+    assert_eq!(client.get("/peer").dispatch().status(), Status::BadRequest);
+    let response = client
+        .get("/peer")
+        .header(Header::new(WireGuardPubkey::NAME, "abc"))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let secret = Secret::new([2; 32]);
+    let response = client
+        .get("/secret")
+        .header(Header::new(TestSecretHeader::NAME, secret.to_string()))
+        .dispatch();
+    assert_eq!(response.into_string().unwrap(), secret.to_string());
+    let response = client.get("/secret").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(
+        response.into_string().unwrap(),
+        "missing header X-Test-Secret"
+    );
+    let response = client
+        .get("/secret")
+        .header(Header::new(TestSecretHeader::NAME, secret.to_string()))
+        .header(Header::new(TestSecretHeader::NAME, secret.to_string()))
+        .dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        "header X-Test-Secret must only be present once"
+    );
+    let response = client
+        .get("/secret")
+        .header(Header::new(TestSecretHeader::NAME, "abc"))
+        .dispatch();
+    assert!(response
+        .into_string()
+        .unwrap()
+        .starts_with("invalid key in header X-Test-Secret: "));
+}
+
+#[test]
+fn test_header_values() {
+    use crate::Secret;
+    type Guard = FromHeader<Secret>;
+    assert!(matches!(
+        Guard::from_values([]),
+        Err(HeaderError::Missing("X-WireGuard-Pubkey"))
+    ));
+    assert!(matches!(
+        Guard::from_values(["a", "b"]),
+        Err(HeaderError::Duplicate(_))
+    ));
+    assert!(matches!(
+        Guard::from_values(["abc"]),
+        Err(HeaderError::Parse(_, _))
+    ));
+}
+
+#[cfg(all(
+    feature = "headers",
+    any(
+        feature = "hex",
+        feature = "base64",
+        feature = "base32",
+        feature = "base58",
+        feature = "proquint"
+    )
+))]
+#[test]
+fn test_typed_header() {
+    use crate::Secret;
+    use headers::{HeaderMap, HeaderMapExt, HeaderValue};
+
+    let pubkey = Pubkey::new([1; 32]);
+    let secret = Secret::new([2; 32]);
+    let mut map = HeaderMap::new();
+    map.typed_insert(XWireGuardPubkey(pubkey));
+    map.typed_insert(XWireGuardPresharedKey(secret));
+    assert_eq!(map["x-wireguard-pubkey"], pubkey.to_string());
+    assert!(!map["x-wireguard-pubkey"].is_sensitive());
+    assert!(map["x-wireguard-preshared-key"].is_sensitive());
+    assert_eq!(map.typed_get(), Some(XWireGuardPubkey(pubkey)));
+    assert_eq!(map.typed_get(), Some(XWireGuardPresharedKey(secret)));
+
+    map.insert("x-wireguard-pubkey", HeaderValue::from_static("abc"));
+    assert!(map.typed_try_get::<XWireGuardPubkey>().is_err());
+    map.append(
+        "x-wireguard-preshared-key",
+        secret.to_string().parse().unwrap(),
+    );
+    assert!(map.typed_try_get::<XWireGuardPresharedKey>().is_err());
+    map.remove("x-wireguard-pubkey");
+    assert!(matches!(map.typed_try_get::<XWireGuardPubkey>(), Ok(None)));
+}
@@ -0,0 +1,390 @@
+//! Post-quantum hybrid derivation of preshared keys. WireGuard itself only uses X25519, but its
+//! preshared key slot can be used to mix in a secret from a post-quantum key exchange, so that
+//! recorded traffic stays confidential even if X25519 is broken later on.
+//!
+//! The initiator encapsulates a secret to the ML-KEM-768 public key ([KemPubkey]) of the
+//! responder and sends the resulting [HybridCiphertext] over any channel. Both sides then derive
+//! the same preshared key from the ML-KEM shared secret and the X25519 shared secret of their
+//! static WireGuard keys:
+//!
+//! ```text
+//! psk = BLAKE2s-256(label || x25519 || ml-kem || ciphertext || initiator || responder)
+//! ```
+//!
+//! where `initiator` and `responder` are the WireGuard public keys of the two sides. The
+//! preshared key is only as strong as the stronger of the two exchanges.
+//!
+//! The ML-KEM key types [KemPubkey] and [KemPrivkey] support the same encodings, parsing and
+//! serde representation as the WireGuard key types, so they can be distributed the same way.
+
+#[cfg(feature = "base64")]
+use crate::url_component_decode;
+#[cfg(test)]
+use crate::CREDENTIALS_UNAVAILABLE;
+#[cfg(feature = "serde")]
+use crate::{deserialize_bytes, read_bytes_seq, serialize_bytes};
+use crate::{
+    env_error, read_systemd_credential, DisplayAs, Encoding, LoadError, ParseError, Privkey,
+    Pubkey, Secret, CREDENTIALS_DIRECTORY,
+};
+#[cfg(feature = "hex")]
+use crate::{fingerprint_decode, fingerprint_encode, strip_hex_prefix};
+#[cfg(feature = "proquint")]
+use crate::{proquint_decode, proquint_encode};
+#[cfg(feature = "base32")]
+use crate::{zbase32_decode, zbase32_encode};
+use blake2::{Blake2s256, Digest};
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{EncodedSizeUser, KemCore, MlKem768};
+use paste::paste;
+use rand_core::OsRng;
+#[cfg(feature = "rocket")]
+use rocket::request::FromParam;
+#[cfg(feature = "serde")]
+use serde::{
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use x25519_dalek_fiat::{PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+type Ciphertext = ml_kem::Ciphertext<MlKem768>;
+
+/// Length (in bytes) of an ML-KEM-768 public (encapsulation) key.
+pub const KEM_PUBKEY_LEN: usize = 1184;
+
+/// Length (in bytes) of an ML-KEM-768 private key, which is stored as the seed it is expanded
+/// from.
+pub const KEM_PRIVKEY_LEN: usize = 64;
+
+/// Length (in bytes) of a hybrid ciphertext, which is an ML-KEM-768 ciphertext.
+pub const HYBRID_CIPHERTEXT_LEN: usize = 1088;
+
+/// Label used to domain-separate the preshared key derivation.
+const LABEL_PSK: &[u8] = b"wireguard-keys hybrid psk v1";
+
+/// Possible errors that can be generated when deriving hybrid preshared keys.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum HybridError {
+    /// Illegal length
+    #[error("length mismatch")]
+    Length,
+    /// X25519 exchange resulted in an all-zero secret, peer public key has low order
+    #[error("public key has low order")]
+    LowOrder,
+}
+
+/// ML-KEM-768 public key, which the initiator needs to encapsulate a secret to the responder.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Zeroize)]
+pub struct KemPubkey([u8; KEM_PUBKEY_LEN]);
+
+impl_new!(KemPubkey, KEM_PUBKEY_LEN);
+impl_display!(KemPubkey);
+impl_deref!(KemPubkey, KEM_PUBKEY_LEN);
+#[cfg(feature = "hex")]
+impl_hex!(KemPubkey);
+#[cfg(feature = "base64")]
+impl_base64!(KemPubkey);
+#[cfg(feature = "base32")]
+impl_base32!(KemPubkey);
+#[cfg(feature = "base58")]
+impl_base58!(KemPubkey);
+#[cfg(feature = "proquint")]
+impl_proquint!(KemPubkey);
+impl_parse!(KemPubkey);
+impl_encoding!(KemPubkey);
+#[cfg(feature = "serde")]
+impl_serde!(@impl KemPubkey, "ML-KEM-768 public key");
+#[cfg(feature = "rocket")]
+impl_rocket!(KemPubkey);
+impl_env!(KemPubkey);
+#[cfg(feature = "base64")]
+impl_io!(KemPubkey);
+
+impl KemPubkey {
+    #[cfg(test)]
+    fn generate() -> KemPubkey {
+        KemPrivkey::generate().pubkey()
+    }
+}
+
+impl TryFrom<&[u8]> for KemPubkey {
+    type Error = ParseError;
+    fn try_from(key: &[u8]) -> Result<Self, Self::Error> {
+        key.try_into()
+            .map(KemPubkey)
+            .map_err(|_| ParseError::Length)
+    }
+}
+
+/// ML-KEM-768 private key, stored as the 64-byte seed it is expanded from.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Zeroize)]
+pub struct KemPrivkey([u8; KEM_PRIVKEY_LEN]);
+
+impl_new!(KemPrivkey, KEM_PRIVKEY_LEN);
+impl_display!(KemPrivkey);
+impl_deref!(KemPrivkey, KEM_PRIVKEY_LEN);
+#[cfg(feature = "hex")]
+impl_hex!(KemPrivkey);
+#[cfg(feature = "base64")]
+impl_base64!(KemPrivkey);
+#[cfg(feature = "base32")]
+impl_base32!(KemPrivkey);
+#[cfg(feature = "base58")]
+impl_base58!(KemPrivkey);
+#[cfg(feature = "proquint")]
+impl_proquint!(KemPrivkey);
+impl_parse!(KemPrivkey);
+impl_encoding!(KemPrivkey);
+#[cfg(feature = "serde")]
+impl_serde!(@impl KemPrivkey, "ML-KEM-768 private key");
+#[cfg(feature = "rocket")]
+impl_rocket!(KemPrivkey);
+impl_env!(KemPrivkey);
+#[cfg(feature = "base64")]
+impl_io!(KemPrivkey);
+impl_systemd!(KemPrivkey);
+
+impl KemPrivkey {
+    /// Generate new private key using the kernel randomness generator.
+    /// When the `fast-rng` feature is enabled, a per-thread generator seeded from it is used.
+    pub fn generate() -> Self {
+        let mut seed = [0; KEM_PRIVKEY_LEN];
+        crate::rng::fill_bytes(&mut seed);
+        KemPrivkey(seed)
+    }
+
+    fn expand(&self) -> (<MlKem768 as KemCore>::DecapsulationKey, EncapsulationKey) {
+        let d = self.0[..32].try_into().expect("seed has valid length");
+        let z = self.0[32..].try_into().expect("seed has valid length");
+        MlKem768::generate_deterministic(&d, &z)
+    }
+
+    /// Generate the corresponding public key for this private key.
+    pub fn pubkey(&self) -> KemPubkey {
+        let (_, encapsulation_key) = self.expand();
+        KemPubkey::try_from(&encapsulation_key.as_bytes()[..]).expect("valid length")
+    }
+}
+
+impl TryFrom<&[u8]> for KemPrivkey {
+    type Error = ParseError;
+    fn try_from(key: &[u8]) -> Result<Self, Self::Error> {
+        key.try_into()
+            .map(KemPrivkey)
+            .map_err(|_| ParseError::Length)
+    }
+}
+
+/// ML-KEM-768 ciphertext sent from the initiator to the responder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridCiphertext(Box<[u8; HYBRID_CIPHERTEXT_LEN]>);
+
+impl HybridCiphertext {
+    /// Parse ciphertext from its encoding.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, HybridError> {
+        let data: [u8; HYBRID_CIPHERTEXT_LEN] = data.try_into().map_err(|_| HybridError::Length)?;
+        Ok(HybridCiphertext(Box::new(data)))
+    }
+
+    /// Encoding of this ciphertext.
+    pub fn as_bytes(&self) -> &[u8; HYBRID_CIPHERTEXT_LEN] {
+        &self.0
+    }
+}
+
+/// Compute X25519 shared secret, rejecting low-order public keys.
+fn diffie_hellman(privkey: &Privkey, pubkey: &Pubkey) -> Result<[u8; 32], HybridError> {
+    let secret = StaticSecret::from(**privkey);
+    let shared = secret.diffie_hellman(&PublicKey::from(**pubkey)).to_bytes();
+    if shared == [0; 32] {
+        return Err(HybridError::LowOrder);
+    }
+    Ok(shared)
+}
+
+/// Combine both shared secrets and the transcript into the preshared key.
+fn combine(
+    x25519: &[u8; 32],
+    kem: &[u8],
+    ciphertext: &HybridCiphertext,
+    initiator: &Pubkey,
+    responder: &Pubkey,
+) -> Secret {
+    let mut hash = Blake2s256::new();
+    hash.update(LABEL_PSK);
+    hash.update(x25519);
+    hash.update(kem);
+    hash.update(&ciphertext.0[..]);
+    hash.update(&initiator[..]);
+    hash.update(&responder[..]);
+    Secret::new(hash.finalize().into())
+}
+
+/// Derive a preshared key as the initiator, given its own private key and the WireGuard and
+/// ML-KEM public keys of the responder. The returned ciphertext must be sent to the responder.
+pub fn encapsulate(
+    privkey: &Privkey,
+    responder: &Pubkey,
+    responder_kem: &KemPubkey,
+) -> Result<(Secret, HybridCiphertext), HybridError> {
+    let mut x25519 = diffie_hellman(privkey, responder)?;
+    let encoded = (&responder_kem.0[..]).try_into().expect("valid length");
+    let encapsulation_key = EncapsulationKey::from_bytes(encoded);
+    let (ciphertext, mut kem) = encapsulation_key
+        .encapsulate(&mut OsRng)
+        .expect("encapsulation is infallible");
+    let ciphertext = HybridCiphertext::from_bytes(&ciphertext).expect("valid length");
+    let psk = combine(&x25519, &kem, &ciphertext, &privkey.pubkey(), responder);
+    x25519.zeroize();
+    kem.zeroize();
+    Ok((psk, ciphertext))
+}
+
+/// Derive a preshared key as the responder, given its own private key and ML-KEM secret key,
+/// the WireGuard public key of the initiator and the ciphertext it sent.
+pub fn decapsulate(
+    privkey: &Privkey,
+    kem_key: &KemPrivkey,
+    initiator: &Pubkey,
+    ciphertext: &HybridCiphertext,
+) -> Result<Secret, HybridError> {
+    let mut x25519 = diffie_hellman(privkey, initiator)?;
+    let (decapsulation_key, _) = kem_key.expand();
+    let encoded: Ciphertext = (&ciphertext.0[..]).try_into().expect("valid length");
+    let mut kem = decapsulation_key
+        .decapsulate(&encoded)
+        .expect("decapsulation is infallible");
+    let psk = combine(&x25519, &kem, ciphertext, initiator, &privkey.pubkey());
+    x25519.zeroize();
+    kem.zeroize();
+    Ok(psk)
+}
+
+#[cfg(test)]
+fn decode_hex(data: &str) -> Vec<u8> {
+    (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&data[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Test vector generated independently with the Python `cryptography` package: initiator and
+/// responder private keys are all `0x11` and `0x22` bytes, the ML-KEM seed is `0x00..0x3f`.
+#[cfg(test)]
+const TEST_CIPHERTEXT: &str = "\
+    94b2b9869ec0791b0d3b44bdba0fa3efbf7d865ed46bec51c3516cb3af41ff9883b20851a5a30160385c6e85\
+    970bca4d9d3eaf9ab733a1f89ed424de11ec4051cd30531f4f23a6bc7ff1761d99240477dfca1eaf0919e987\
+    1234ed240daf706bd3b8a4099b7e21b3acd10f2d03d8fb29b18c179d39fd9f6113c90f6f324e609dbbeae4c3\
+    d6d1e7904e9fd0f6eccf16c7de2fa6c0d2a360d75285acbb36111a326f8b5dee5ab0d4e88376c4ab6e5c417c\
+    af85cede743acca8ab82facb8a996b35045e682f03864fe1550c90d9dbc6f1b05aa0d3ad51e2a325d0583fb9\
+    25247963cb4fb07c8d4adca16ea1957c7f9247404b50013e443c88d9a7104b3880db7c83a1ab3d7fb14cb455\
+    8b7edb519bf0971319c055de7d94009f0ce41d7d5b2014e1cb81e80b9819a68f49afb0997578469b157321bc\
+    25a7f1fcacfcc07dbd09da09e44ca7ec69213d4a2c73cca484284554cec3cea95ee9b019a81e7259718acc9d\
+    f70590aca2f829c62022a9456348afb5d8c8b29a70d977ce87d01b4c1a261143d3c9a2e6418484ef8084dfc9\
+    cda3bb2a565b84f9da2a5c21b82a8ed436a5f8b7b3bd452d4cecbe8e4f9862e2dd765c37b2b5dc03be935bf0\
+    4115b91d92188f3081f3de2a8a1b627626ab8c702dffa7c6a7985b27c6f03fc2f7e5265a89ccbdd4fcae6dc3\
+    677b6ffc19936938591df0001583ba85fb203f98854ad458ced17589feaecc27832ba1c066fd3e9056965758\
+    ce8d44b731c254c306fdb08e9a9222486c335ba8534f8fc8f276827c586a0bd9a6ca6ec75eda87c6579e4cb4\
+    a8be3f33254b2a62447da2a41da2efa7faa137674d126ace1b22b0481d3026e0e86fc994870b43b4b9aad129\
+    53abdddc82dd348b1df65ff90ac95512187c78e9f885667b496842e624be0f8a6a0a6621f0ce3450d4fa448f\
+    4d174f97af8297db322e5a90026624963b07270740810459ac3c977df7ae17dccba2df3674786f81e57eefc3\
+    99c907f42b805b94326576fb965a07114d6b6638c8825b6ffda1e68c9136f51cb103613dcbe6e2ffe67cac16\
+    54adeec5b85269fd84c6354104b3c763fe499224ad0a5b076c37005821d2d868bcb7fa898e1bd2534443492a\
+    be4549817aaa0ab2311f3d8bdd3bf4d72e06b796896632abeff52db9278f7d45b4e854d26542afaf2b093384\
+    bc9afe3f5e2865ea54f5d415066db135d81bf6cd32861b0e1d1e9993578f6c1294616d12ce8cb07a4618cba0\
+    004d2432738e91761b80e72463c94e6d6eebb56469b44847b2c8a062639de7e255357850bcee4a4364827f54\
+    b0ae72e3a3241b1e4f4b081c3a0aacf952ab0a1fc50388e87c572d612d17bee0a7a5b9358f7e83a3b010e495\
+    7403928675b5a5b156db6f536bb00b9f4926acc75af265c0ab5c7f6df2bb54dd3d0e585eca76e02c2a6994e6\
+    ec32f6b05277e7e4ef70d4737b85573eceebc4c99a1efe1690da1ebfc1d9a6700b98ed8d6356a6a06e00ce3f\
+    c67a364169b2065ce363f2eab3225f63f643df7233109bd4eddf085049896b40";
+
+#[test]
+fn test_hybrid_vector() {
+    let initiator = Privkey::new([0x11; 32]);
+    let responder = Privkey::new([0x22; 32]);
+    let mut seed = [0; KEM_PRIVKEY_LEN];
+    for (index, byte) in seed.iter_mut().enumerate() {
+        *byte = index as u8;
+    }
+    let kem_key = KemPrivkey::new(seed);
+    let ciphertext = HybridCiphertext::from_bytes(&decode_hex(TEST_CIPHERTEXT)).unwrap();
+    let psk = decapsulate(&responder, &kem_key, &initiator.pubkey(), &ciphertext).unwrap();
+    assert_eq!(
+        psk[..],
+        decode_hex("3993c1bc84cf96ba7d6c40d993c4bcca5123ea055e4158af996f9f23c5e97177")[..]
+    );
+}
+
+#[test]
+fn test_hybrid_roundtrip() {
+    let initiator = Privkey::generate();
+    let responder = Privkey::generate();
+    let kem_key = KemPrivkey::generate();
+    let kem_pubkey = kem_key.pubkey();
+    let (psk, ciphertext) = encapsulate(&initiator, &responder.pubkey(), &kem_pubkey).unwrap();
+    let received = HybridCiphertext::from_bytes(ciphertext.as_bytes()).unwrap();
+    assert_eq!(
+        decapsulate(&responder, &kem_key, &initiator.pubkey(), &received).unwrap(),
+        psk
+    );
+    // a different ML-KEM key yields a different preshared key
+    let other = KemPrivkey::generate();
+    assert_ne!(
+        decapsulate(&responder, &other, &initiator.pubkey(), &received).unwrap(),
+        psk
+    );
+}
+
+#[test]
+fn test_hybrid_invalid() {
+    assert_eq!(
+        HybridCiphertext::from_bytes(&[0; 32]),
+        Err(HybridError::Length)
+    );
+    assert!(matches!(
+        KemPubkey::try_from(&[0; 32][..]),
+        Err(ParseError::Length)
+    ));
+    let kem_pubkey = KemPrivkey::generate().pubkey();
+    assert_eq!(
+        encapsulate(&Privkey::generate(), &Pubkey::new([0; 32]), &kem_pubkey).unwrap_err(),
+        HybridError::LowOrder
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_kem_serde() {
+    let privkey = KemPrivkey::generate();
+    let pubkey = privkey.pubkey();
+    let encoded = serde_json::to_string(&pubkey).unwrap();
+    assert_eq!(encoded, format!("\"{}\"", pubkey));
+    assert_eq!(serde_json::from_str::<KemPubkey>(&encoded).unwrap(), pubkey);
+    #[cfg(not(feature = "serde-bytes"))]
+    {
+        use serde_test::{assert_tokens, Configure, Token};
+        let mut tokens = vec![Token::Tuple {
+            len: KEM_PRIVKEY_LEN,
+        }];
+        tokens.extend(privkey.iter().map(|byte| Token::U8(*byte)));
+        tokens.push(Token::TupleEnd);
+        assert_tokens(&privkey.compact(), &tokens);
+    }
+    // serde_test only takes static byte tokens, so use msgpack, which encodes byte strings
+    // as bin 8, and deserialize from the local buffer instead
+    #[cfg(feature = "serde-bytes")]
+    {
+        let encoded = rmp_serde::to_vec(&privkey).unwrap();
+        assert_eq!(encoded[..2], [0xc4, KEM_PRIVKEY_LEN as u8]);
+        assert_eq!(encoded[2..], privkey.0);
+        assert_eq!(
+            rmp_serde::from_slice::<KemPrivkey>(&encoded).unwrap(),
+            privkey
+        );
+    }
+}
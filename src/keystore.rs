@@ -0,0 +1,904 @@
+//! Encrypted keystore, which allows for keeping many named keys in a single password-protected
+//! file. This is useful for applications which need to store an interface private key along
+//! with many peer preshared keys.
+//!
+//! The file format is versioned and consists of a fixed-size header followed by the encrypted
+//! entries:
+//!
+//! | Field      | Size     | Description                                        |
+//! |------------|----------|----------------------------------------------------|
+//! | magic      | 4        | `WGKS`                                             |
+//! | version    | 1        | format version, currently `1`                      |
+//! | m_cost     | 4        | Argon2id memory cost in KiB (little-endian)        |
+//! | t_cost     | 4        | Argon2id iterations (little-endian)                |
+//! | p_cost     | 4        | Argon2id parallelism (little-endian)               |
+//! | salt       | 16       | random salt for key derivation                     |
+//! | nonce      | 24       | random XChaCha20-Poly1305 nonce                    |
+//! | ciphertext | variable | encrypted entries, authenticated with the header   |
+//!
+//! Every decrypted entry is encoded as a one-byte kind, a one-byte name length, the name
+//! and the 32 raw key bytes.
+//!
+//! Daemons which only occasionally need their keys can use a [SealedKeystore], which keeps the
+//! keystore encrypted and only decrypts it on demand, caching the decrypted keys in locked
+//! memory for a limited time to avoid paying for key derivation on every access.
+
+use crate::{Privkey, Pubkey, Secret};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand_core::{OsRng, RngCore};
+use std::alloc::Layout;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use zeroize::Zeroize;
+
+/// Magic bytes at the start of every keystore file.
+const MAGIC: &[u8; 4] = b"WGKS";
+
+/// Current version of the keystore format.
+const VERSION: u8 = 1;
+
+/// Length (in bytes) of the key derivation salt.
+const SALT_LEN: usize = 16;
+
+/// Length (in bytes) of the XChaCha20-Poly1305 nonce.
+const NONCE_LEN: usize = 24;
+
+/// Length (in bytes) of the keystore header.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 12 + SALT_LEN + NONCE_LEN;
+
+/// Maximum memory cost (in KiB) accepted when opening a keystore, to prevent malicious files
+/// from exhausting memory.
+const MAX_M_COST: u32 = 1 << 20;
+
+/// Maximum number of iterations accepted when opening a keystore, to prevent malicious files
+/// from taking forever to open.
+const MAX_T_COST: u32 = 64;
+
+/// Maximum parallelism accepted when opening a keystore, to prevent malicious files from
+/// exhausting threads.
+const MAX_P_COST: u32 = 64;
+
+/// Length (in bytes) of every key stored in the keystore.
+const KEY_LEN: usize = 32;
+
+/// Possible errors that can be generated when working with keystores.
+#[derive(Error, Debug)]
+pub enum KeystoreError {
+    /// Error reading or writing keystore file
+    #[error("error accessing keystore file")]
+    Io(#[from] std::io::Error),
+    /// File is not a keystore or is truncated
+    #[error("invalid keystore format")]
+    Format,
+    /// Keystore was written by an unsupported version
+    #[error("unsupported keystore version {0}")]
+    Version(u8),
+    /// Key derivation parameters are invalid
+    #[error("invalid key derivation parameters")]
+    Kdf,
+    /// Wrong password or corrupted keystore
+    #[error("cannot decrypt keystore, wrong password or corrupted file")]
+    Decrypt,
+    /// Encrypting the keystore failed
+    #[error("cannot encrypt keystore")]
+    Encrypt,
+    /// Entry name is empty or longer than 255 bytes
+    #[error("invalid entry name")]
+    Name,
+    /// Memory for the decrypted keys cannot be locked
+    #[error("cannot lock memory for decrypted keys")]
+    Lock(#[source] std::io::Error),
+}
+
+/// Argon2id parameters used to derive the encryption key from the password.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory cost in KiB.
+    pub m_cost: u32,
+    /// Number of iterations.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Derive encryption key from password and salt.
+    fn derive(&self, password: &[u8], salt: &[u8]) -> Result<[u8; 32], KeystoreError> {
+        if self.m_cost > MAX_M_COST || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err(KeystoreError::Kdf);
+        }
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|_| KeystoreError::Kdf)?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let mut key = [0; 32];
+        argon2
+            .hash_password_into(password, salt, &mut key)
+            .map_err(|_| KeystoreError::Kdf)?;
+        Ok(key)
+    }
+}
+
+/// Single key stored in a [Keystore].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Zeroize)]
+pub enum Entry {
+    /// WireGuard private key.
+    Privkey(Privkey),
+    /// WireGuard public key.
+    Pubkey(Pubkey),
+    /// WireGuard preshared key.
+    Secret(Secret),
+}
+
+impl Entry {
+    fn kind(&self) -> u8 {
+        match self {
+            Entry::Privkey(_) => 0,
+            Entry::Pubkey(_) => 1,
+            Entry::Secret(_) => 2,
+        }
+    }
+
+    fn bytes(&self) -> &[u8; KEY_LEN] {
+        match self {
+            Entry::Privkey(key) => key,
+            Entry::Pubkey(key) => key,
+            Entry::Secret(key) => key,
+        }
+    }
+
+    fn from_parts(kind: u8, data: [u8; KEY_LEN]) -> Result<Self, KeystoreError> {
+        match kind {
+            0 => Ok(Entry::Privkey(Privkey::new(data))),
+            1 => Ok(Entry::Pubkey(Pubkey::new(data))),
+            2 => Ok(Entry::Secret(Secret::new(data))),
+            _ => Err(KeystoreError::Format),
+        }
+    }
+}
+
+impl From<Privkey> for Entry {
+    fn from(key: Privkey) -> Self {
+        Entry::Privkey(key)
+    }
+}
+
+impl From<Pubkey> for Entry {
+    fn from(key: Pubkey) -> Self {
+        Entry::Pubkey(key)
+    }
+}
+
+impl From<Secret> for Entry {
+    fn from(key: Secret) -> Self {
+        Entry::Secret(key)
+    }
+}
+
+/// Password-protected collection of named keys.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Keystore {
+    params: KdfParams,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Keystore {
+    /// Create new, empty keystore using the default key derivation parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create new, empty keystore using custom key derivation parameters.
+    pub fn with_params(params: KdfParams) -> Self {
+        Keystore {
+            params,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Key derivation parameters used when encrypting this keystore.
+    pub fn params(&self) -> KdfParams {
+        self.params
+    }
+
+    /// Insert a key, returning the previous entry with the same name, if any.
+    pub fn insert<E: Into<Entry>>(
+        &mut self,
+        name: &str,
+        entry: E,
+    ) -> Result<Option<Entry>, KeystoreError> {
+        if name.is_empty() || name.len() > u8::MAX as usize {
+            return Err(KeystoreError::Name);
+        }
+        Ok(self.entries.insert(name.to_string(), entry.into()))
+    }
+
+    /// Remove a key by name.
+    pub fn remove(&mut self, name: &str) -> Option<Entry> {
+        self.entries.remove(name)
+    }
+
+    /// Look up a key by name.
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.get(name)
+    }
+
+    /// Look up a private key by name.
+    pub fn privkey(&self, name: &str) -> Option<&Privkey> {
+        match self.entries.get(name) {
+            Some(Entry::Privkey(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Look up a public key by name.
+    pub fn pubkey(&self, name: &str) -> Option<&Pubkey> {
+        match self.entries.get(name) {
+            Some(Entry::Pubkey(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Look up a preshared key by name.
+    pub fn secret(&self, name: &str) -> Option<&Secret> {
+        match self.entries.get(name) {
+            Some(Entry::Secret(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Iterate over all entries, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Entry)> {
+        self.entries
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry))
+    }
+
+    /// Number of entries in this keystore.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Determine if this keystore has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Encrypt keystore with the given password.
+    pub fn encrypt(&self, password: &[u8]) -> Result<Vec<u8>, KeystoreError> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        header.extend_from_slice(&self.params.m_cost.to_le_bytes());
+        header.extend_from_slice(&self.params.t_cost.to_le_bytes());
+        header.extend_from_slice(&self.params.p_cost.to_le_bytes());
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&nonce);
+
+        let mut plaintext = Vec::new();
+        for (name, entry) in &self.entries {
+            plaintext.push(entry.kind());
+            plaintext.push(name.len() as u8);
+            plaintext.extend_from_slice(name.as_bytes());
+            plaintext.extend_from_slice(entry.bytes());
+        }
+
+        let mut key = self.params.derive(password, &salt)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+        key.zeroize();
+        let payload = Payload {
+            msg: &plaintext,
+            aad: &header,
+        };
+        let result = cipher.encrypt(XNonce::from_slice(&nonce), payload);
+        plaintext.zeroize();
+
+        let mut data = header;
+        data.extend_from_slice(&result.map_err(|_| KeystoreError::Encrypt)?);
+        Ok(data)
+    }
+
+    /// Decrypt keystore with the given password.
+    pub fn decrypt(data: &[u8], password: &[u8]) -> Result<Self, KeystoreError> {
+        if data.len() < HEADER_LEN || &data[0..MAGIC.len()] != MAGIC {
+            return Err(KeystoreError::Format);
+        }
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        if header[4] != VERSION {
+            return Err(KeystoreError::Version(header[4]));
+        }
+        let param = |offset: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&header[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        let params = KdfParams {
+            m_cost: param(5),
+            t_cost: param(9),
+            p_cost: param(13),
+        };
+        let salt = &header[17..17 + SALT_LEN];
+        let nonce = &header[17 + SALT_LEN..HEADER_LEN];
+
+        let mut key = params.derive(password, salt)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+        key.zeroize();
+        let payload = Payload {
+            msg: ciphertext,
+            aad: header,
+        };
+        let mut plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| KeystoreError::Decrypt)?;
+        let result = Self::decode_entries(&plaintext);
+        plaintext.zeroize();
+
+        Ok(Keystore {
+            params,
+            entries: result?,
+        })
+    }
+
+    fn decode_entries(mut data: &[u8]) -> Result<BTreeMap<String, Entry>, KeystoreError> {
+        let mut entries = BTreeMap::new();
+        while !data.is_empty() {
+            if data.len() < 2 {
+                return Err(KeystoreError::Format);
+            }
+            let kind = data[0];
+            let name_len = data[1] as usize;
+            data = &data[2..];
+            if data.len() < name_len + KEY_LEN {
+                return Err(KeystoreError::Format);
+            }
+            let name = std::str::from_utf8(&data[0..name_len])
+                .map_err(|_| KeystoreError::Format)?
+                .to_string();
+            let mut key = [0; KEY_LEN];
+            key.copy_from_slice(&data[name_len..name_len + KEY_LEN]);
+            let entry = Entry::from_parts(kind, key);
+            key.zeroize();
+            entries.insert(name, entry?);
+            data = &data[name_len + KEY_LEN..];
+        }
+        Ok(entries)
+    }
+
+    /// Open and decrypt keystore file.
+    pub fn open<P: AsRef<Path>>(path: P, password: &[u8]) -> Result<Self, KeystoreError> {
+        let data = std::fs::read(path)?;
+        Self::decrypt(&data, password)
+    }
+
+    /// Encrypt and save keystore to a file. The file is replaced atomically and, on Unix,
+    /// created with permissions that only allow the owner to access it. The data is written to
+    /// a new temporary file with a random name next to it first, so that an existing file or
+    /// symlink is never written to, and files left behind by interrupted saves do not matter.
+    pub fn save<P: AsRef<Path>>(&self, path: P, password: &[u8]) -> Result<(), KeystoreError> {
+        let path = path.as_ref();
+        let data = self.encrypt(password)?;
+        let name = path
+            .file_name()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        let mut temp = std::ffi::OsString::from(".");
+        temp.push(name);
+        temp.push(format!(".{:016x}.tmp", OsRng.next_u64()));
+        let temp = path.with_file_name(temp);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        let result = file
+            .write_all(&data)
+            .and_then(|()| file.sync_all())
+            .and_then(|()| std::fs::rename(&temp, path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        Ok(result?)
+    }
+}
+
+impl Drop for Keystore {
+    fn drop(&mut self) {
+        for entry in self.entries.values_mut() {
+            entry.zeroize();
+        }
+    }
+}
+
+/// Decrypted keys along with the time they expire, or `None` if they never do.
+type Unsealed = Option<(UnsealedKeys, Option<Instant>)>;
+
+/// Encrypted keystore which is decrypted on demand. The decrypted keys are cached for a
+/// time-to-live counted from decryption, after which they are cleared from memory and the
+/// password is needed again. A time-to-live too large to be represented, such as
+/// [Duration::MAX], means the keys never expire.
+///
+/// Expiry is checked whenever the keystore is accessed and when an [UnsealedGuard] is dropped,
+/// no background thread is used, so sealing is lazy: expired keys stay in memory until the next
+/// access. Daemons which want the keys cleared promptly should call
+/// [seal_expired](Self::seal_expired) periodically.
+///
+/// The cached keys are kept in memory which is locked using `mlock` on Unix and `VirtualLock`
+/// on Windows, so that they are never swapped to disk, and zeroized when sealing. Unsealing
+/// fails with [KeystoreError::Lock] if the memory cannot be locked, for example because the
+/// limit of locked memory is reached, and on other platforms.
+#[derive(Debug)]
+pub struct SealedKeystore {
+    data: Vec<u8>,
+    ttl: Duration,
+    unsealed: Mutex<Unsealed>,
+}
+
+/// Access to the keys of an unsealed [SealedKeystore]. Other threads trying to unseal the
+/// keystore wait until it is dropped.
+#[derive(Debug)]
+pub struct UnsealedGuard<'a>(MutexGuard<'a, Unsealed>);
+
+impl SealedKeystore {
+    /// Create from the encrypted keystore data, as produced by [Keystore::encrypt].
+    pub fn new(data: Vec<u8>, ttl: Duration) -> Self {
+        SealedKeystore {
+            data,
+            ttl,
+            unsealed: Mutex::new(None),
+        }
+    }
+
+    /// Read encrypted keystore file, without decrypting it.
+    pub fn open<P: AsRef<Path>>(path: P, ttl: Duration) -> Result<Self, KeystoreError> {
+        Ok(Self::new(std::fs::read(path)?, ttl))
+    }
+
+    /// Time for which decrypted keys are cached.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Unsealed> {
+        let mut unsealed = self.unsealed.lock().unwrap_or_else(PoisonError::into_inner);
+        if matches!(&*unsealed, Some((_, Some(expires))) if *expires <= Instant::now()) {
+            *unsealed = None;
+        }
+        unsealed
+    }
+
+    /// Access the decrypted keys, decrypting the keystore if it is not cached. The password
+    /// is only requested when decryption is needed, and cleared after use.
+    pub fn unseal<F>(&self, password: F) -> Result<UnsealedGuard<'_>, KeystoreError>
+    where
+        F: FnOnce() -> Vec<u8>,
+    {
+        let mut unsealed = self.lock();
+        if unsealed.is_none() {
+            let mut password = password();
+            let result = Keystore::decrypt(&self.data, &password);
+            password.zeroize();
+            let keys = UnsealedKeys::new(&result?)?;
+            *unsealed = Some((keys, Instant::now().checked_add(self.ttl)));
+        }
+        Ok(UnsealedGuard(unsealed))
+    }
+
+    /// Check if decrypted keys are currently cached.
+    pub fn is_unsealed(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Clear the decrypted keys if they have expired.
+    pub fn seal_expired(&self) {
+        drop(self.lock());
+    }
+
+    /// Clear the decrypted keys immediately.
+    pub fn seal(&self) {
+        *self.lock() = None;
+    }
+}
+
+impl UnsealedGuard<'_> {
+    fn keys(&self) -> &UnsealedKeys {
+        &self.0.as_ref().expect("guard holds unsealed keystore").0
+    }
+
+    /// Look up a key by name.
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        let keys = self.keys();
+        let index = keys.names.binary_search_by_key(&name, String::as_str);
+        index.ok().map(|index| &keys.entries.as_slice()[index])
+    }
+
+    /// Look up a private key by name.
+    pub fn privkey(&self, name: &str) -> Option<&Privkey> {
+        match self.get(name) {
+            Some(Entry::Privkey(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Look up a public key by name.
+    pub fn pubkey(&self, name: &str) -> Option<&Pubkey> {
+        match self.get(name) {
+            Some(Entry::Pubkey(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Look up a preshared key by name.
+    pub fn secret(&self, name: &str) -> Option<&Secret> {
+        match self.get(name) {
+            Some(Entry::Secret(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Iterate over all entries, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Entry)> {
+        let keys = self.keys();
+        keys.names
+            .iter()
+            .map(String::as_str)
+            .zip(keys.entries.as_slice())
+    }
+
+    /// Number of entries in the keystore.
+    pub fn len(&self) -> usize {
+        self.keys().names.len()
+    }
+
+    /// Determine if the keystore has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.keys().names.is_empty()
+    }
+}
+
+impl Drop for UnsealedGuard<'_> {
+    fn drop(&mut self) {
+        if matches!(&*self.0, Some((_, Some(expires))) if *expires <= Instant::now()) {
+            *self.0 = None;
+        }
+    }
+}
+
+/// Decrypted keys of a [SealedKeystore], ordered by name. Only the entries are kept in locked
+/// memory, the names are not secret.
+#[derive(Debug)]
+struct UnsealedKeys {
+    names: Vec<String>,
+    entries: LockedEntries,
+}
+
+impl UnsealedKeys {
+    fn new(keystore: &Keystore) -> Result<Self, KeystoreError> {
+        Ok(UnsealedKeys {
+            names: keystore.entries.keys().cloned().collect(),
+            entries: LockedEntries::new(&keystore.entries)?,
+        })
+    }
+}
+
+/// Entries in memory which is locked, so that it is never swapped to disk. The allocation spans
+/// whole pages, since memory is locked and unlocked by page, so that unlocking it never unlocks
+/// memory of other allocations.
+struct LockedEntries {
+    data: NonNull<Entry>,
+    len: usize,
+    layout: Layout,
+}
+
+// the entries are owned like in a `Box<[Entry]>`
+unsafe impl Send for LockedEntries {}
+unsafe impl Sync for LockedEntries {}
+
+impl LockedEntries {
+    /// Copy the entries of a map into newly allocated, locked memory.
+    fn new(entries: &BTreeMap<String, Entry>) -> Result<Self, KeystoreError> {
+        let page_size = page_size();
+        let size = (entries.len() * std::mem::size_of::<Entry>())
+            .max(1)
+            .next_multiple_of(page_size);
+        let layout = Layout::from_size_align(size, page_size)
+            .map_err(|_| KeystoreError::Lock(std::io::ErrorKind::OutOfMemory.into()))?;
+        // SAFETY: the size of the layout is not zero
+        let data = unsafe { std::alloc::alloc_zeroed(layout) };
+        let Some(data) = NonNull::new(data.cast::<Entry>()) else {
+            std::alloc::handle_alloc_error(layout);
+        };
+        // lock before copying, so that the keys are never written to memory which can be swapped
+        if let Err(error) = lock_memory(data.as_ptr().cast(), size) {
+            // SAFETY: the memory was allocated above with the same layout
+            unsafe { std::alloc::dealloc(data.as_ptr().cast(), layout) };
+            return Err(KeystoreError::Lock(error));
+        }
+        for (index, entry) in entries.values().enumerate() {
+            // SAFETY: the allocation has room for every entry of the map
+            unsafe { data.as_ptr().add(index).write(*entry) };
+        }
+        Ok(LockedEntries {
+            data,
+            len: entries.len(),
+            layout,
+        })
+    }
+
+    fn as_slice(&self) -> &[Entry] {
+        // SAFETY: the first `len` entries were initialized in `new`
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr(), self.len) }
+    }
+}
+
+impl std::fmt::Debug for LockedEntries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+impl Drop for LockedEntries {
+    fn drop(&mut self) {
+        let data = self.data.as_ptr().cast::<u8>();
+        // SAFETY: the allocation is zero-initialized bytes or entries, which are plain bytes
+        unsafe { std::slice::from_raw_parts_mut(data, self.layout.size()) }.zeroize();
+        unlock_memory(data, self.layout.size());
+        // SAFETY: the memory was allocated in `new` with the same layout
+        unsafe { std::alloc::dealloc(data, self.layout) };
+    }
+}
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    // SAFETY: sysconf has no preconditions
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[cfg(unix)]
+fn lock_memory(data: *const u8, len: usize) -> std::io::Result<()> {
+    // SAFETY: the range is a single allocation
+    match unsafe { libc::mlock(data.cast(), len) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+#[cfg(unix)]
+fn unlock_memory(data: *const u8, len: usize) {
+    // SAFETY: the range is a single allocation
+    unsafe { libc::munlock(data.cast(), len) };
+}
+
+#[cfg(windows)]
+fn page_size() -> usize {
+    use windows_sys::Win32::System::SystemInformation::{GetSystemInfo, SYSTEM_INFO};
+    // SAFETY: GetSystemInfo fills in the structure, for which all zeros is valid
+    unsafe {
+        let mut info: SYSTEM_INFO = std::mem::zeroed();
+        GetSystemInfo(&mut info);
+        info.dwPageSize as usize
+    }
+}
+
+#[cfg(windows)]
+fn lock_memory(data: *const u8, len: usize) -> std::io::Result<()> {
+    // SAFETY: the range is a single allocation
+    match unsafe { windows_sys::Win32::System::Memory::VirtualLock(data.cast(), len) } {
+        0 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+fn unlock_memory(data: *const u8, len: usize) {
+    // SAFETY: the range is a single allocation
+    unsafe { windows_sys::Win32::System::Memory::VirtualUnlock(data.cast(), len) };
+}
+
+#[cfg(not(any(unix, windows)))]
+fn page_size() -> usize {
+    4096
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_memory(_data: *const u8, _len: usize) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn unlock_memory(_data: *const u8, _len: usize) {}
+
+#[cfg(test)]
+const TEST_PARAMS: KdfParams = KdfParams {
+    m_cost: 64,
+    t_cost: 1,
+    p_cost: 1,
+};
+
+#[test]
+fn test_keystore_roundtrip() {
+    let mut keystore = Keystore::with_params(TEST_PARAMS);
+    let privkey = Privkey::generate();
+    let secret = Secret::generate();
+    keystore.insert("wg0", privkey).unwrap();
+    keystore.insert("wg0.peer", privkey.pubkey()).unwrap();
+    keystore.insert("wg0.psk", secret).unwrap();
+    let data = keystore.encrypt(b"password").unwrap();
+    let decrypted = Keystore::decrypt(&data, b"password").unwrap();
+    assert_eq!(decrypted, keystore);
+    assert_eq!(decrypted.privkey("wg0"), Some(&privkey));
+    assert_eq!(decrypted.pubkey("wg0.peer"), Some(&privkey.pubkey()));
+    assert_eq!(decrypted.secret("wg0.psk"), Some(&secret));
+    assert_eq!(decrypted.secret("wg0"), None);
+}
+
+#[test]
+fn test_keystore_wrong_password() {
+    let mut keystore = Keystore::with_params(TEST_PARAMS);
+    keystore.insert("wg0", Privkey::generate()).unwrap();
+    let data = keystore.encrypt(b"password").unwrap();
+    assert!(matches!(
+        Keystore::decrypt(&data, b"wrong"),
+        Err(KeystoreError::Decrypt)
+    ));
+}
+
+#[test]
+fn test_keystore_tampered_header() {
+    let keystore = Keystore::with_params(TEST_PARAMS);
+    let mut data = keystore.encrypt(b"password").unwrap();
+    data[20] ^= 1;
+    assert!(matches!(
+        Keystore::decrypt(&data, b"password"),
+        Err(KeystoreError::Decrypt)
+    ));
+    data[4] = 2;
+    assert!(matches!(
+        Keystore::decrypt(&data, b"password"),
+        Err(KeystoreError::Version(2))
+    ));
+    assert!(matches!(
+        Keystore::decrypt(b"WGKS", b"password"),
+        Err(KeystoreError::Format)
+    ));
+}
+
+#[test]
+fn test_keystore_kdf_limits() {
+    let defaults = KdfParams::default();
+    for params in [
+        KdfParams {
+            m_cost: MAX_M_COST + 1,
+            ..defaults
+        },
+        KdfParams {
+            t_cost: MAX_T_COST + 1,
+            ..defaults
+        },
+        KdfParams {
+            p_cost: MAX_P_COST + 1,
+            ..defaults
+        },
+    ] {
+        assert!(matches!(
+            Keystore::with_params(params).encrypt(b"password"),
+            Err(KeystoreError::Kdf)
+        ));
+    }
+    // limits are checked before decrypting, without deriving a key
+    let mut data = Keystore::with_params(TEST_PARAMS)
+        .encrypt(b"password")
+        .unwrap();
+    data[9..13].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        Keystore::decrypt(&data, b"password"),
+        Err(KeystoreError::Kdf)
+    ));
+}
+
+#[test]
+fn test_keystore_name() {
+    let mut keystore = Keystore::new();
+    assert!(matches!(
+        keystore.insert("", Secret::generate()),
+        Err(KeystoreError::Name)
+    ));
+    assert!(matches!(
+        keystore.insert(&"a".repeat(256), Secret::generate()),
+        Err(KeystoreError::Name)
+    ));
+}
+
+#[test]
+fn test_keystore_save_open() {
+    let path = std::env::temp_dir().join(format!(
+        "wireguard-keys-test-{}.keystore",
+        std::process::id()
+    ));
+    let mut keystore = Keystore::with_params(TEST_PARAMS);
+    keystore.insert("wg0", Privkey::generate()).unwrap();
+    // temporary files left behind by interrupted saves are ignored
+    let mut temp = path.file_name().unwrap().to_owned();
+    temp.push(".tmp");
+    std::fs::write(path.with_file_name(&temp), b"").unwrap();
+    keystore.save(&path, b"password").unwrap();
+    std::fs::remove_file(path.with_file_name(&temp)).unwrap();
+    assert_eq!(Keystore::open(&path, b"password").unwrap(), keystore);
+    // saving again replaces the file
+    keystore.insert("wg1", Privkey::generate()).unwrap();
+    keystore.save(&path, b"password").unwrap();
+    assert_eq!(Keystore::open(&path, b"password").unwrap(), keystore);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_sealed_keystore() {
+    let mut keystore = Keystore::with_params(TEST_PARAMS);
+    let privkey = Privkey::generate();
+    keystore.insert("wg0", privkey).unwrap();
+    let data = keystore.encrypt(b"password").unwrap();
+
+    let sealed = SealedKeystore::new(data.clone(), Duration::from_secs(3600));
+    assert!(!sealed.is_unsealed());
+    let unsealed = sealed.unseal(|| b"password".to_vec()).unwrap();
+    assert_eq!(unsealed.privkey("wg0"), Some(&privkey));
+    assert_eq!(unsealed.secret("wg0"), None);
+    assert_eq!(unsealed.get("wg1"), None);
+    assert_eq!(
+        unsealed.iter().collect::<Vec<_>>(),
+        keystore.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(unsealed.len(), 1);
+    drop(unsealed);
+    assert!(sealed.is_unsealed());
+    let unsealed = sealed
+        .unseal(|| panic!("password requested while unsealed"))
+        .unwrap();
+    assert_eq!(unsealed.privkey("wg0"), Some(&privkey));
+    drop(unsealed);
+    sealed.seal();
+    assert!(!sealed.is_unsealed());
+    assert!(matches!(
+        sealed.unseal(|| b"wrong".to_vec()),
+        Err(KeystoreError::Decrypt)
+    ));
+
+    let sealed = SealedKeystore::new(data.clone(), Duration::ZERO);
+    let unsealed = sealed.unseal(|| b"password".to_vec()).unwrap();
+    assert_eq!(unsealed.privkey("wg0"), Some(&privkey));
+    drop(unsealed);
+    assert!(!sealed.is_unsealed());
+
+    // a time-to-live which overflows never expires
+    let sealed = SealedKeystore::new(data, Duration::MAX);
+    drop(sealed.unseal(|| b"password".to_vec()).unwrap());
+    sealed.seal_expired();
+    assert!(sealed.is_unsealed());
+}
@@ -0,0 +1,466 @@
+//! Rendering of keys and tunnel configurations as Kubernetes Secret manifests.
+//!
+//! Values in the `data` field of a Secret are base64-encoded by Kubernetes, independently of
+//! their contents. WireGuard keys are usually stored in their textual base64 form, as written
+//! by `wg genkey`, so that the file mounted into a pod can be used by `wg` directly. This means
+//! that keys end up encoded twice in the manifest, which [SecretManifestBuilder::privkey] and
+//! [SecretManifestBuilder::secret] take care of.
+//!
+//! ```
+//! # use wireguard_keys::{Privkey, kubernetes::SecretManifest};
+//! let manifest = SecretManifest::builder()
+//!     .name("wireguard-keys")
+//!     .namespace("vpn")
+//!     .label("app.kubernetes.io/name", "wireguard")
+//!     .privkey("privatekey", &Privkey::generate())
+//!     .build()
+//!     .unwrap();
+//! println!("{}", manifest.to_yaml());
+//! ```
+
+use crate::config::TunnelConfig;
+use crate::{Privkey, Secret};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Maximum length of object names and data keys.
+const MAX_NAME_LEN: usize = 253;
+
+/// Maximum length of namespaces, label names and label values.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Possible errors that can be generated when building Secret manifests.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum KubernetesError {
+    /// Required value is missing
+    #[error("missing {0}")]
+    Missing(&'static str),
+    /// Name is not a valid DNS subdomain
+    #[error("invalid name {0:?}")]
+    Name(String),
+    /// Namespace is not a valid DNS label
+    #[error("invalid namespace {0:?}")]
+    Namespace(String),
+    /// Data key contains invalid characters
+    #[error("invalid data key {0:?}")]
+    Key(String),
+    /// Label or annotation is not valid
+    #[error("invalid label or annotation {0:?}")]
+    Label(String),
+}
+
+/// Data of a Secret, which is cleared on drop and not shown in debug output.
+#[derive(Clone, Default)]
+struct SecretData(BTreeMap<String, Zeroizing<Vec<u8>>>);
+
+impl fmt::Debug for SecretData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// Kubernetes Secret of type `Opaque`, which can be rendered as YAML or JSON manifest.
+#[derive(Clone, Debug)]
+pub struct SecretManifest {
+    name: String,
+    namespace: Option<String>,
+    labels: BTreeMap<String, String>,
+    annotations: BTreeMap<String, String>,
+    data: SecretData,
+}
+
+/// Check for a DNS subdomain, as used for object names.
+fn valid_subdomain(value: &str) -> bool {
+    value.len() <= MAX_NAME_LEN && value.split('.').all(valid_label)
+}
+
+/// Check for a DNS label, as used for namespaces.
+fn valid_label(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_LABEL_LEN
+        && !value.starts_with('-')
+        && !value.ends_with('-')
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-')
+}
+
+/// Check for a label value, which may be empty.
+fn valid_label_value(value: &str) -> bool {
+    value.is_empty()
+        || (value.len() <= MAX_LABEL_LEN
+            && value.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && value.ends_with(|c: char| c.is_ascii_alphanumeric())
+            && value
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.')))
+}
+
+/// Check for a label or annotation key, which is a name with an optional DNS subdomain prefix.
+fn valid_label_key(key: &str) -> bool {
+    let name = match key.split_once('/') {
+        Some((prefix, name)) => {
+            if !valid_subdomain(prefix) {
+                return false;
+            }
+            name
+        }
+        None => key,
+    };
+    !name.is_empty() && valid_label_value(name)
+}
+
+/// Check for a data key, which is used as file name when mounting the Secret.
+fn valid_data_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= MAX_NAME_LEN
+        && key != "."
+        && key != ".."
+        && key
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'))
+}
+
+/// Write string as quoted JSON string, which is also a valid YAML string.
+fn write_quoted(f: &mut String, value: &str) -> fmt::Result {
+    f.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => f.push_str("\\\""),
+            '\\' => f.push_str("\\\\"),
+            '\n' => f.push_str("\\n"),
+            '\r' => f.push_str("\\r"),
+            '\t' => f.push_str("\\t"),
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.push(c),
+        }
+    }
+    f.push('"');
+    Ok(())
+}
+
+impl SecretManifest {
+    /// Create builder for a Secret manifest. Values are validated when calling
+    /// [build](SecretManifestBuilder::build).
+    pub fn builder() -> SecretManifestBuilder {
+        SecretManifestBuilder::default()
+    }
+
+    /// Name of the Secret.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Namespace of the Secret, if set.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Iterate over the data keys of the Secret.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.data.0.keys().map(String::as_str)
+    }
+
+    /// Raw value of a data key, before it is base64-encoded for the manifest.
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.data.0.get(key).map(|value| value.as_slice())
+    }
+
+    fn write_yaml(&self, f: &mut String) -> fmt::Result {
+        writeln!(f, "apiVersion: v1")?;
+        writeln!(f, "kind: Secret")?;
+        writeln!(f, "metadata:")?;
+        write!(f, "  name: ")?;
+        write_quoted(f, &self.name)?;
+        writeln!(f)?;
+        if let Some(namespace) = &self.namespace {
+            write!(f, "  namespace: ")?;
+            write_quoted(f, namespace)?;
+            writeln!(f)?;
+        }
+        for (field, map) in [("labels", &self.labels), ("annotations", &self.annotations)] {
+            if map.is_empty() {
+                continue;
+            }
+            writeln!(f, "  {}:", field)?;
+            for (key, value) in map {
+                write!(f, "    ")?;
+                write_quoted(f, key)?;
+                write!(f, ": ")?;
+                write_quoted(f, value)?;
+                writeln!(f)?;
+            }
+        }
+        writeln!(f, "type: Opaque")?;
+        if self.data.0.is_empty() {
+            return writeln!(f, "data: {{}}");
+        }
+        writeln!(f, "data:")?;
+        for (key, value) in &self.data.0 {
+            write!(f, "  ")?;
+            write_quoted(f, key)?;
+            writeln!(f, ": {}", base64::encode(value.as_slice()))?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, f: &mut String) -> fmt::Result {
+        fn write_map<'a>(
+            f: &mut String,
+            indent: &str,
+            entries: impl Iterator<Item = (&'a str, String)>,
+        ) -> fmt::Result {
+            write!(f, "{{")?;
+            let mut first = true;
+            for (key, value) in entries {
+                write!(f, "{}\n{}  ", if first { "" } else { "," }, indent)?;
+                write_quoted(f, key)?;
+                write!(f, ": ")?;
+                write_quoted(f, &value)?;
+                first = false;
+            }
+            if !first {
+                write!(f, "\n{}", indent)?;
+            }
+            write!(f, "}}")
+        }
+
+        writeln!(f, "{{")?;
+        writeln!(f, "  \"apiVersion\": \"v1\",")?;
+        writeln!(f, "  \"kind\": \"Secret\",")?;
+        writeln!(f, "  \"metadata\": {{")?;
+        write!(f, "    \"name\": ")?;
+        write_quoted(f, &self.name)?;
+        if let Some(namespace) = &self.namespace {
+            write!(f, ",\n    \"namespace\": ")?;
+            write_quoted(f, namespace)?;
+        }
+        for (field, map) in [("labels", &self.labels), ("annotations", &self.annotations)] {
+            if map.is_empty() {
+                continue;
+            }
+            write!(f, ",\n    \"{}\": ", field)?;
+            let entries = map.iter().map(|(key, value)| (key.as_str(), value.clone()));
+            write_map(f, "    ", entries)?;
+        }
+        writeln!(f, "\n  }},")?;
+        writeln!(f, "  \"type\": \"Opaque\",")?;
+        write!(f, "  \"data\": ")?;
+        let entries = self
+            .data
+            .0
+            .iter()
+            .map(|(key, value)| (key.as_str(), base64::encode(value.as_slice())));
+        write_map(f, "  ", entries)?;
+        writeln!(f, "\n}}")
+    }
+
+    /// Render manifest as YAML, which can be applied using `kubectl apply -f`.
+    pub fn to_yaml(&self) -> String {
+        let mut output = String::new();
+        self.write_yaml(&mut output)
+            .expect("writing to string cannot fail");
+        output
+    }
+
+    /// Render manifest as JSON.
+    pub fn to_json(&self) -> String {
+        let mut output = String::new();
+        self.write_json(&mut output)
+            .expect("writing to string cannot fail");
+        output
+    }
+}
+
+/// Builder for [SecretManifest].
+#[derive(Clone, Debug, Default)]
+pub struct SecretManifestBuilder {
+    name: Option<String>,
+    namespace: Option<String>,
+    labels: BTreeMap<String, String>,
+    annotations: BTreeMap<String, String>,
+    data: SecretData,
+}
+
+impl SecretManifestBuilder {
+    /// Set name of the Secret, which is required.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set namespace of the Secret.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Add label to the Secret.
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Add annotation to the Secret.
+    pub fn annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.annotations.insert(key.into(), value.into());
+        self
+    }
+
+    /// Add raw value under the given data key. It is base64-encoded in the manifest.
+    pub fn data(mut self, key: impl Into<String>, value: impl AsRef<[u8]>) -> Self {
+        self.data
+            .0
+            .insert(key.into(), Zeroizing::new(value.as_ref().to_vec()));
+        self
+    }
+
+    /// Add private key in its textual base64 form under the given data key.
+    pub fn privkey(self, key: impl Into<String>, privkey: &Privkey) -> Self {
+        let value = Zeroizing::new(privkey.to_base64());
+        self.data(key, value.as_bytes())
+    }
+
+    /// Add preshared key in its textual base64 form under the given data key.
+    pub fn secret(self, key: impl Into<String>, secret: &Secret) -> Self {
+        let value = Zeroizing::new(secret.to_base64());
+        self.data(key, value.as_bytes())
+    }
+
+    /// Add tunnel configuration in wg-quick format under the given data key, such as
+    /// `wg0.conf`.
+    pub fn config(self, key: impl Into<String>, config: &TunnelConfig) -> Self {
+        let value = Zeroizing::new(config.to_string());
+        self.data(key, value.as_bytes())
+    }
+
+    /// Validate values and build manifest.
+    pub fn build(self) -> Result<SecretManifest, KubernetesError> {
+        let name = self.name.ok_or(KubernetesError::Missing("name"))?;
+        if !valid_subdomain(&name) {
+            return Err(KubernetesError::Name(name));
+        }
+        if let Some(namespace) = &self.namespace {
+            if !valid_label(namespace) {
+                return Err(KubernetesError::Namespace(namespace.clone()));
+            }
+        }
+        for (key, value) in &self.labels {
+            if !valid_label_key(key) || !valid_label_value(value) {
+                return Err(KubernetesError::Label(key.clone()));
+            }
+        }
+        if let Some(key) = self.annotations.keys().find(|key| !valid_label_key(key)) {
+            return Err(KubernetesError::Label(key.clone()));
+        }
+        if let Some(key) = self.data.0.keys().find(|key| !valid_data_key(key)) {
+            return Err(KubernetesError::Key(key.clone()));
+        }
+        Ok(SecretManifest {
+            name,
+            namespace: self.namespace,
+            labels: self.labels,
+            annotations: self.annotations,
+            data: self.data,
+        })
+    }
+}
+
+#[cfg(test)]
+const TEST_PRIVKEY: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+
+#[test]
+fn test_kubernetes_yaml() {
+    let privkey = Privkey::from_base64(TEST_PRIVKEY).unwrap();
+    let manifest = SecretManifest::builder()
+        .name("wireguard-keys")
+        .namespace("vpn")
+        .label("app.kubernetes.io/name", "wireguard")
+        .annotation("description", "key for \"wg0\"")
+        .privkey("privatekey", &privkey)
+        .build()
+        .unwrap();
+    assert_eq!(manifest.get("privatekey"), Some(TEST_PRIVKEY.as_bytes()));
+    assert_eq!(
+        manifest.to_yaml(),
+        "\
+apiVersion: v1
+kind: Secret
+metadata:
+  name: \"wireguard-keys\"
+  namespace: \"vpn\"
+  labels:
+    \"app.kubernetes.io/name\": \"wireguard\"
+  annotations:
+    \"description\": \"key for \\\"wg0\\\"\"
+type: Opaque
+data:
+  \"privatekey\": eUFuejVURitsWFhKdGUxNHRqaTN6bE1OcStoZDJyWVVJZ0pCZ0IzZkJtaz0=
+"
+    );
+    assert_eq!(format!("{:?}", manifest.data), "{\"privatekey\"}");
+}
+
+#[test]
+fn test_kubernetes_json() {
+    let manifest = SecretManifest::builder()
+        .name("wg0")
+        .data("b", "second")
+        .data("a", "first")
+        .build()
+        .unwrap();
+    assert_eq!(manifest.keys().collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(
+        manifest.to_json(),
+        "\
+{
+  \"apiVersion\": \"v1\",
+  \"kind\": \"Secret\",
+  \"metadata\": {
+    \"name\": \"wg0\"
+  },
+  \"type\": \"Opaque\",
+  \"data\": {
+    \"a\": \"Zmlyc3Q=\",
+    \"b\": \"c2Vjb25k\"
+  }
+}
+"
+    );
+}
+
+#[test]
+fn test_kubernetes_invalid() {
+    let builder = SecretManifest::builder();
+    assert_eq!(
+        builder.clone().build().unwrap_err(),
+        KubernetesError::Missing("name")
+    );
+    let builder = builder.name("wg0");
+    assert_eq!(
+        builder.clone().name("WireGuard").build().unwrap_err(),
+        KubernetesError::Name("WireGuard".into())
+    );
+    assert_eq!(
+        builder
+            .clone()
+            .namespace("vpn.example")
+            .build()
+            .unwrap_err(),
+        KubernetesError::Namespace("vpn.example".into())
+    );
+    assert_eq!(
+        builder.clone().label("app", "-").build().unwrap_err(),
+        KubernetesError::Label("app".into())
+    );
+    assert_eq!(
+        builder.clone().annotation("/app", "").build().unwrap_err(),
+        KubernetesError::Label("/app".into())
+    );
+    assert_eq!(
+        builder.clone().data("../key", "").build().unwrap_err(),
+        KubernetesError::Key("../key".into())
+    );
+}
@@ -9,40 +9,548 @@
 //! crate is used for x25519 operations.
 //!
 //! This crate allows for encoding keys in various ways. The crate supports `base64`, which is
-//! typically used by WireGuard, but `hex` and `base32` can be enabled as well. Enabling encodings
-//! also enables parsing from that encoding.
+//! typically used by WireGuard, but `hex`, `base32` and `base58` can be enabled as well. For
+//! comparing keys verbally, the `proquint` feature encodes them as pronounceable words. On hot
+//! paths, keys can be encoded into a buffer on the stack instead of a [String], using
+//! `encode_base64_into` or `encode_hex_into`. Likewise, `parse_bytes` decodes keys without
+//! copying them to the heap. Keys which are known at compile time can be embedded as constants
+//! using the [pubkey!], [privkey!] and [secret!] macros, see the [literal] module.
+//! Enabling encodings also enables parsing from that encoding. The encoding can also be chosen
+//! at runtime using the [Encoding] type, and `parse_detect` reports which encoding a string was
+//! parsed from. Keys are displayed in base64 when it is enabled, `display_as` returns a wrapper
+//! which displays them in another encoding. Independent of the enabled encodings, all key types implement the
+//! [LowerHex](std::fmt::LowerHex), [UpperHex](std::fmt::UpperHex) and [Binary](std::fmt::Binary)
+//! formatting traits, so `format!("{:x}", pubkey)` works as expected.
 //!
 //! The [serde] feature, which is enabled by default, adds [serialize][serde::Serialize] and
 //! [deserialize][serde::Deserialize] support for WireGuard types. How these types are serialized
 //! depends on the format: when serializing into human-readable formats, such as
 //! JSON, the keys are serialized as base64-encoded strings. However, when
-//! serializing to binary formats such as Bincode, keys are serialized as raw bytes.
+//! serializing to binary formats such as Bincode, keys are serialized as raw bytes. Raw bytes
+//! are a tuple of bytes by default, and a byte string with the optional `serde-bytes` feature,
+//! which is more compact in some formats, such as postcard and CBOR.
+//! Individual fields can use another representation with `#[serde(with = "...")]` and one of
+//! the adapter modules [as_hex], [as_base32], [as_base64_urlsafe] or [as_bytes], which do not
+//! depend on the format. Keys in containers can be wrapped in [Raw] to serialize them as raw
+//! bytes. When deserializing from human readable formats, keys written as byte arrays are
+//! accepted as well as encoded strings.
+//!
+//! The optional `rkyv` feature implements the [rkyv] traits for the key types. Archived keys
+//! are identical to the keys themselves, so that keys in memory-mapped archives can be used
+//! without copying them out. Keep in mind that keys in archives are not zeroized on drop.
+//!
+//! The optional `postgres` feature implements `ToSql` and `FromSql` of postgres-types for the
+//! key types, which are stored as `BYTEA` with tokio-postgres and the crates building on it.
+//!
+//! The optional `sea-orm` feature implements `TryGetable` and `ValueType` of sea-orm for the key
+//! types, so that entity models can have key columns, stored as fixed-length binary. Keys can
+//! also be used as primary keys that are not auto-incremented.
+//!
+//! The [bson] module, enabled by the `bson` feature, converts keys to and from BSON binary
+//! values with the generic subtype, for storing keys in MongoDB documents.
+//!
+//! The optional `redis` feature implements `ToRedisArgs` and `FromRedisValue` for the key types,
+//! which are written to Redis as raw bytes. Keys stored as strings in any enabled encoding are
+//! read as well.
+//!
+//! The [clap] module, enabled by the `clap` feature, allows using the key types as typed command
+//! line arguments with `value_parser!`, with errors listing the accepted encodings.
+//!
+//! The optional `arbitrary` feature implements [Arbitrary](arbitrary::Arbitrary) for the key
+//! types, for fuzz targets which take structured input containing keys.
+//!
+//! The optional `testing` feature adds proptest strategies for valid keys, weak keys and
+//! corrupted encodings, and implements quickcheck's `Arbitrary` for the key types, see the
+//! [testing] module.
 //!
 //! The optional `schema` feature adds information to the types allowing to generate JSON schema
-//! from them automatically using schemars.
+//! from them automatically using schemars. Keys are described as strings with a custom format,
+//! such as `wireguard-pubkey`, and with the base64 pattern, length and an example value when
+//! the `base64` feature is enabled.
+//!
+//! The optional `utoipa` feature describes the key types in the same way for OpenAPI documents
+//! generated using utoipa, so that they can be used directly in request and response types.
+//!
+//! With the `poem-openapi` feature, the key types can be used directly in request and response
+//! objects and parameters of poem-openapi services, where they are described the same way.
+//!
+//! The optional `async-graphql` and `juniper` features add GraphQL scalars for public and
+//! preshared keys, which validate input values before they reach resolvers.
+//!
+//! The [axum] module, enabled by the `axum` feature, has extractors for keys in paths and
+//! headers which reject invalid keys with a JSON body.
+//!
+//! The [warp] module, enabled by the `warp` feature, has filters extracting keys from path
+//! segments and headers, and a recovery function responding to invalid keys with a JSON body.
+//!
+//! The `headers` feature adds typed headers for keys in the [header] module, which can be used
+//! with the headers crate to pass keys between services.
+//!
+//! The [tonic] module, enabled by the `tonic` feature, decodes keys in gRPC messages and
+//! metadata into errors which can be returned as [::tonic::Status].
 //!
 //! Enabling the `rocket` feature adds the ability to parse any WireGuard types from a HTTP
-//! request using the [FromParam][rocket::request::FromParam] trait.
+//! request using the [FromParam][rocket::request::FromParam] trait. Keys can also be used in
+//! query parameters, and embedded in URIs built with `uri!`, which writes them as urlsafe base64
+//! so that they survive query string decoding. The [header] module has request guards which
+//! read keys from headers.
+//!
+//! For identifiers which are not key material, such as node IDs, the [Token32] type supports the
+//! same encodings without any of the secrecy semantics of the key types. Short identifiers can
+//! also be derived from public keys using [Pubkey::prefix], or `Pubkey::hashed_prefix` when the
+//! optional `blake2` feature is enabled. With the `base32` feature, public keys can be embedded
+//! in hostnames using `Pubkey::to_dns_label`.
+//!
+//! Every subsystem has its own error type, such as [ParseError] for parsing keys or
+//! [ConfigError](config::ConfigError) for parsing configuration files. The [enum@Error] type wraps
+//! all of them, for applications which want to propagate any error of this crate. Optional
+//! backends which are compiled in but not available on the system, such as a missing hardware
+//! randomness generator, are reported as [CapabilityUnavailable] with a remediation hint.
+//!
+//! The [config] module contains a typed model of wg-quick configuration files, which uses
+//! the key types of this crate for all key fields. Single-peer tunnels can also be shared as
+//! `wireguard://` links, see the [uri] module, or exported to systemd-networkd and
+//! NetworkManager, see the [networkd] and [networkmanager] modules. Addresses and allowed IPs
+//! are represented by the types of the [allowed_ips] module, which can check networks for
+//! containment and overlap, and peer endpoints by the [Endpoint](endpoint::Endpoint) type,
+//! which supports DNS names.
+//!
+//! Keys and tunnel configurations can be rendered as Kubernetes Secret manifests using the
+//! [kubernetes] module.
+//!
+//! The [rotation] module contains primitives for rotating keys while keeping the previous key
+//! accepted for an overlap window.
+//!
+//! The [versioned] module has a wrapper for keeping track of key history, carrying a version,
+//! creation time and comment along with the key.
+//!
+//! The optional `handshake` feature adds parsing of handshake messages captured on the wire and
+//! identification of the peer they were sent to, see the [handshake] module. It is unstable and
+//! additionally requires the `unstable` feature.
+//!
+//! The optional `qr` feature adds rendering of public keys and tunnel configurations as QR
+//! codes, which can be scanned by the WireGuard mobile apps. They are rendered as SVG images or
+//! for terminals, and with the `qr-png` feature as PNG images.
+//!
+//! The optional `keystore` feature adds an encrypted, password-protected file format for
+//! storing many named keys in a single file, which can be decrypted on demand and cached for a
+//! limited time, see the [keystore] module.
+//!
+//! The optional `hybrid` feature adds derivation of preshared keys from a combined X25519 and
+//! ML-KEM-768 exchange, adding post-quantum resistance through the preshared key slot, see the
+//! [hybrid] module. It is unstable and additionally requires the `unstable` feature.
+//!
+//! The optional `fast-rng` feature makes key generation use a per-thread generator which is
+//! seeded from the kernel, instead of reading from the kernel for every key. This is faster
+//! when generating many keys, but keeps generator state in memory.
+//!
+//! The optional `envelope` feature adds fixed-size, padded frames for exchanging key material
+//! over side channels without revealing message sizes, see the [envelope] module.
+//!
+//! The optional `mesh` feature adds conversion of keys and nodes from Tailscale and Headscale,
+//! see the [mesh] module.
+//!
+//! The optional `sops` feature adds reading and writing keys stored in SOPS-encrypted
+//! documents, see the [sops] module.
+//!
+//! The optional `rmp` feature adds encoding of keys as MessagePack extension types, see the
+//! [msgpack] module.
+//!
+//! The optional `cose` feature adds encoding of public and private keys as COSE keys in CBOR,
+//! see the [cose] module.
+//!
+//! The optional `ffi` feature adds a C interface for generating, parsing and encoding keys and
+//! computing shared secrets, with a header generated by cbindgen, see the [ffi] module.
+//!
+//! The optional `wasm` feature adds JavaScript bindings built with wasm-bindgen, for using the
+//! same implementation in browsers, see the [wasm] module.
+//!
+//! On `wasm32-unknown-unknown`, keys are generated using `crypto.getRandomValues` of the
+//! browser or Node.js, also without the `wasm` feature. Functions which read the system clock,
+//! such as [RotatingKey::generate](rotation::RotatingKey::generate), panic on this target, the
+//! variants taking the current time as argument can be used instead. Tests which run on this
+//! target are marked with `wasm_bindgen_test`, and run in a headless browser with:
+//!
+//! ```sh
+//! wasm-pack test --headless --firefox -- --features wasm
+//! ```
+//!
+//! # `no_std`
+//!
+//! The `std` feature is enabled by default. Without it, the crate is `no_std` and with the
+//! `alloc` feature only needs an allocator, for example in firmware. The key types, the `hex`, `base64`, `base58` and `proquint`
+//! encodings and serde support remain available, and keys are generated with a randomness
+//! generator provided by the caller using `generate_with_rng`. Generating keys from the
+//! operating system, loading keys from files and the environment, the [enum@Error] and
+//! [LoadError] types, the configuration model and all other optional features require `std`.
+//!
+//! Without the `alloc` feature, which `std` enables, the crate does not allocate at all, for
+//! firmware without allocator. Keys are encoded into buffers on the stack using
+//! `encode_base64_into` and `encode_hex_into`, or into fixed-capacity strings using
+//! `to_base64_heapless` and `to_hex_heapless` with the optional `heapless` feature. Parsing with
+//! [FromStr] and `parse_bytes` never allocates, and accepts hex and base64 with padding.
+//! Without any encoding feature, keys are displayed as lowercase hex.
+//! [ParseError] implements [core::error::Error], which does not require `std`. The `serde`,
+//! `base58` and `proquint` features enable `alloc`.
+//!
+//! The optional `defmt` feature implements [defmt::Format] for the key types, for logging over
+//! RTT. Public keys and tokens are formatted as the first four bytes in hex, such as
+//! `Pubkey(c532c403...)`, private and preshared keys only as `Privkey(redacted)`.
+//!
+//! Boards which gather entropy from a hardware generator, but cannot use the randomness
+//! generator of an operating system, have two ways of generating keys: implementing
+//! [RngCore] and [CryptoRng] for the generator and passing it to `generate_with_rng`, or
+//! passing raw samples to `Privkey::from_entropy` and `Secret::from_entropy`, which hash input
+//! of any length into a key and require the `blake2` feature. Samples should contain at least
+//! 256 bits of entropy, which is more than 32 bytes for most hardware generators. Applications
+//! which use `std` on a target that getrandom does not support can instead register the
+//! hardware generator as custom getrandom backend, which `generate` then uses, by depending on
+//! getrandom 0.2 with the `custom` feature:
+//!
+//! ```ignore
+//! fn fill_from_trng(buffer: &mut [u8]) -> Result<(), getrandom::Error> {
+//!     for byte in buffer {
+//!         *byte = trng::read_byte().map_err(|_| getrandom::Error::UNSUPPORTED)?;
+//!     }
+//!     Ok(())
+//! }
+//!
+//! getrandom::register_custom_getrandom!(fill_from_trng);
+//! ```
+//!
+//! # Stability
+//!
+//! The key types, their encodings and the configuration model are stable and follow semantic
+//! versioning. Experimental subsystems are only available when the `unstable` feature is
+//! enabled in addition to their own feature, and may change in any release, including patch
+//! releases. Currently these are the `handshake` and `hybrid` features, and enabling either of
+//! them without `unstable` fails to compile.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(all(feature = "handshake", not(feature = "unstable")))]
+compile_error!("the `handshake` feature is unstable and requires the `unstable` feature");
+#[cfg(all(feature = "hybrid", not(feature = "unstable")))]
+compile_error!("the `hybrid` feature is unstable and requires the `unstable` feature");
 
 #[macro_use]
 mod macros;
+#[cfg(feature = "std")]
+pub mod allowed_ips;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "bson")]
+pub mod bson;
+#[cfg(feature = "clap")]
+pub mod clap;
+#[cfg(all(feature = "std", feature = "base64"))]
+pub mod config;
+#[cfg(feature = "cose")]
+pub mod cose;
+#[cfg(feature = "std")]
+pub mod endpoint;
+#[cfg(feature = "envelope")]
+pub mod envelope;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(any(feature = "async-graphql", feature = "juniper"))]
+mod graphql;
+#[cfg(all(feature = "unstable", feature = "handshake"))]
+pub mod handshake;
+#[cfg(any(
+    feature = "rocket",
+    feature = "axum",
+    feature = "warp",
+    feature = "headers",
+    feature = "tonic"
+))]
+pub mod header;
+#[cfg(all(feature = "unstable", feature = "hybrid"))]
+pub mod hybrid;
+#[cfg(feature = "keystore")]
+pub mod keystore;
+#[cfg(all(feature = "std", feature = "base64"))]
+pub mod kubernetes;
+pub mod literal;
+#[cfg(feature = "mesh")]
+pub mod mesh;
+#[cfg(feature = "rmp")]
+pub mod msgpack;
+#[cfg(all(feature = "std", feature = "base64"))]
+pub mod networkd;
+#[cfg(all(feature = "std", feature = "base64"))]
+pub mod networkmanager;
+#[cfg(feature = "qr")]
+mod qr;
+#[cfg(feature = "std")]
+mod rng;
+#[cfg(feature = "std")]
+pub mod rotation;
+#[cfg(feature = "serde")]
+mod serde_as;
+#[cfg(feature = "sops")]
+pub mod sops;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tonic")]
+pub mod tonic;
+#[cfg(all(feature = "std", feature = "base64"))]
+pub mod uri;
+#[cfg(feature = "std")]
+pub mod versioned;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "warp")]
+pub mod warp;
+
+#[cfg(feature = "qr")]
+pub use qr::QrError;
+#[cfg(all(feature = "serde", feature = "base32"))]
+pub use serde_as::as_base32;
+#[cfg(all(feature = "serde", feature = "base64"))]
+pub use serde_as::as_base64_urlsafe;
+#[cfg(all(feature = "serde", feature = "hex"))]
+pub use serde_as::as_hex;
+#[cfg(feature = "serde")]
+pub use serde_as::{as_bytes, Raw};
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+#[allow(unused_imports)]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+use core::str::FromStr;
 use paste::paste;
-use rand_core::{OsRng, RngCore};
+#[cfg(feature = "std")]
+use rand_core::OsRng;
+use rand_core::{CryptoRng, RngCore};
 #[cfg(feature = "rocket")]
 use rocket::request::FromParam;
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 #[cfg(feature = "serde")]
 use serde::{
-    de::{Error, Visitor},
+    de::{Expected, IgnoredAny, SeqAccess, Visitor},
+    ser::SerializeTuple,
     Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::fmt;
-use std::str::FromStr;
 use thiserror::Error;
 use x25519_dalek_fiat::{PublicKey, StaticSecret};
-use zeroize::Zeroize;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Textual encodings of keys, for choosing the encoding at runtime using the `encode` and
+/// `decode` methods of the key types.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// Lowercase hex
+    #[cfg(feature = "hex")]
+    Hex,
+    /// Uppercase hex
+    #[cfg(feature = "hex")]
+    HexUpper,
+    /// Uppercase hex with bytes separated by colons, such as `AB:CD:EF`, as used for SSH key
+    /// fingerprints
+    #[cfg(feature = "hex")]
+    Fingerprint,
+    /// Base64 with padding, as used by WireGuard
+    #[cfg(feature = "base64")]
+    Base64,
+    /// Base64 with the urlsafe alphabet
+    #[cfg(feature = "base64")]
+    Base64Urlsafe,
+    /// Base64 without padding
+    #[cfg(feature = "base64")]
+    Base64Unpadded,
+    /// Uppercase base32 with padding, decoding also accepts lowercase and unpadded base32
+    #[cfg(feature = "base32")]
+    Base32,
+    /// z-base-32, designed for human transcription
+    #[cfg(feature = "base32")]
+    ZBase32,
+    /// Base58 with the Bitcoin alphabet
+    #[cfg(feature = "base58")]
+    Base58,
+    /// Proquints separated by dashes, for comparing keys verbally
+    #[cfg(feature = "proquint")]
+    Proquint,
+}
+
+impl Encoding {
+    /// All enabled encodings, in the order they are tried by `parse_detect`.
+    pub const ALL: &'static [Encoding] = &[
+        #[cfg(feature = "hex")]
+        Encoding::Hex,
+        #[cfg(feature = "hex")]
+        Encoding::HexUpper,
+        #[cfg(feature = "hex")]
+        Encoding::Fingerprint,
+        #[cfg(feature = "base64")]
+        Encoding::Base64,
+        #[cfg(feature = "base64")]
+        Encoding::Base64Urlsafe,
+        #[cfg(feature = "base64")]
+        Encoding::Base64Unpadded,
+        #[cfg(feature = "base32")]
+        Encoding::Base32,
+        #[cfg(feature = "base32")]
+        Encoding::ZBase32,
+        #[cfg(feature = "proquint")]
+        Encoding::Proquint,
+        #[cfg(feature = "base58")]
+        Encoding::Base58,
+    ];
+
+    /// Name of the encoding, such as `base64-urlsafe`, for choosing it in command line
+    /// arguments or configuration files.
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "hex")]
+            Encoding::Hex => "hex",
+            #[cfg(feature = "hex")]
+            Encoding::HexUpper => "hex-upper",
+            #[cfg(feature = "hex")]
+            Encoding::Fingerprint => "fingerprint",
+            #[cfg(feature = "base64")]
+            Encoding::Base64 => "base64",
+            #[cfg(feature = "base64")]
+            Encoding::Base64Urlsafe => "base64-urlsafe",
+            #[cfg(feature = "base64")]
+            Encoding::Base64Unpadded => "base64-unpadded",
+            #[cfg(feature = "base32")]
+            Encoding::Base32 => "base32",
+            #[cfg(feature = "base32")]
+            Encoding::ZBase32 => "zbase32",
+            #[cfg(feature = "base58")]
+            Encoding::Base58 => "base58",
+            #[cfg(feature = "proquint")]
+            Encoding::Proquint => "proquint",
+        }
+    }
+
+    /// Look up enabled encoding by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|encoding| encoding.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[test]
+fn test_encoding_name() {
+    for &encoding in Encoding::ALL {
+        assert_eq!(Encoding::from_name(encoding.name()), Some(encoding));
+        let upper = encoding.name().to_uppercase();
+        assert_eq!(Encoding::from_name(&upper), Some(encoding));
+    }
+    assert_eq!(Encoding::from_name("rot13"), None);
+}
+
+/// Key which is displayed in a chosen encoding, returned by the `display_as` method of the key
+/// types.
+#[cfg(feature = "alloc")]
+#[derive(Copy, Clone, Debug)]
+pub struct DisplayAs<'a, K> {
+    key: &'a K,
+    encoding: Encoding,
+}
+
+/// Run future in tests. Futures without I/O never wait, so polling until ready is enough.
+#[cfg(test)]
+#[allow(dead_code)]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Waker};
+    let mut future = std::pin::pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// Undo the changes which URLs commonly make to base64: percent-encoding of `+`, `/` and `=`,
+/// and `+` decoded as space in query strings. Other percent-encoded sequences are kept, so that
+/// decoding the result fails.
+#[cfg(all(feature = "alloc", feature = "base64"))]
+fn url_component_decode(data: &str) -> String {
+    let mut output = String::with_capacity(data.len());
+    let mut rest = data;
+    while let Some(c) = rest.chars().next() {
+        let escaped = match rest.get(1..3) {
+            Some(hex) if c == '%' && hex.eq_ignore_ascii_case("2B") => Some('+'),
+            Some(hex) if c == '%' && hex.eq_ignore_ascii_case("2F") => Some('/'),
+            Some(hex) if c == '%' && hex.eq_ignore_ascii_case("3D") => Some('='),
+            _ => None,
+        };
+        match escaped {
+            Some(c) => {
+                output.push(c);
+                rest = &rest[3..];
+            }
+            None => {
+                output.push(if c == ' ' { '+' } else { c });
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    output
+}
+
+/// Remove optional `0x` or `0X` prefix from hex data.
+#[cfg(feature = "hex")]
+fn strip_hex_prefix(data: &[u8]) -> &[u8] {
+    match data {
+        [b'0', b'x' | b'X', rest @ ..] => rest,
+        _ => data,
+    }
+}
+
+/// Encode data as uppercase hex bytes separated by colons.
+#[cfg(all(feature = "alloc", feature = "hex"))]
+fn fingerprint_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len() * 3);
+    for byte in data {
+        if !output.is_empty() {
+            output.push(':');
+        }
+        output.push_str(&hex::encode_upper([*byte]));
+    }
+    output
+}
+
+/// Decode hex bytes separated by colons, in any case.
+#[cfg(all(feature = "alloc", feature = "hex"))]
+fn fingerprint_decode(data: &str) -> Result<Vec<u8>, ParseError> {
+    let mut digits = String::with_capacity(data.len());
+    for (index, c) in data.chars().enumerate() {
+        match (index % 3 == 2, c == ':') {
+            (true, true) => {}
+            (false, false) => digits.push(c),
+            _ => return Err(ParseError::Invalid("fingerprint")),
+        }
+    }
+    if data.ends_with(':') {
+        return Err(ParseError::Length);
+    }
+    hex::decode(digits).map_err(|_| ParseError::Invalid("fingerprint"))
+}
+
+#[cfg(feature = "hex")]
+#[test]
+fn test_fingerprint() {
+    assert_eq!(fingerprint_encode(&[0xab, 0x01, 0xff]), "AB:01:FF");
+    assert_eq!(fingerprint_decode("ab:01:Ff").unwrap(), [0xab, 0x01, 0xff]);
+    assert!(fingerprint_decode("ab01:ff").is_err());
+    assert!(fingerprint_decode("ab:01:").is_err());
+    assert!(fingerprint_decode("ab:01:f").is_err());
+}
 
 /// Possible errors that can be generated when parsing WireGuard keys.
 #[derive(Error, Debug)]
@@ -50,18 +558,563 @@ pub enum ParseError {
     /// Error decoding base64
     #[cfg(feature = "base64")]
     #[error("base64 decoding error")]
-    Base64(#[from] base64::DecodeError),
+    Base64(#[cfg_attr(feature = "std", source)] base64::DecodeError),
     /// Error decoding hex
     #[cfg(feature = "hex")]
     #[error("hex decoding errro")]
-    Hex(#[from] hex::FromHexError),
+    Hex(#[cfg_attr(feature = "std", source)] hex::FromHexError),
     /// Error decoding base32
     #[cfg(feature = "base32")]
     #[error("base32 decoding error")]
     Base32Error,
+    /// Error decoding proquints
+    #[cfg(feature = "proquint")]
+    #[error("proquint decoding error")]
+    Proquint,
+    /// Error decoding base58
+    #[cfg(feature = "base58")]
+    #[error("base58 decoding error")]
+    Base58(#[cfg_attr(feature = "std", source)] bs58::decode::Error),
+    /// String is valid in more than one encoding, with different results
+    #[error("ambiguous encoding")]
+    Ambiguous,
     /// Illegal length
     #[error("length mismatch")]
     Length,
+    /// Error decoding the named encoding, without details which could reveal the input
+    #[error("{0} decoding error")]
+    Invalid(&'static str),
+}
+
+#[cfg(feature = "std")]
+impl ParseError {
+    /// Remove the errors of the encoding crates, which contain characters and offsets of the
+    /// input, before reporting errors about keys which may be secret.
+    pub(crate) fn redact(self) -> Self {
+        match self {
+            #[cfg(feature = "base64")]
+            ParseError::Base64(_) => ParseError::Invalid("base64"),
+            #[cfg(feature = "hex")]
+            ParseError::Hex(_) => ParseError::Invalid("hex"),
+            #[cfg(feature = "base58")]
+            ParseError::Base58(_) => ParseError::Invalid("base58"),
+            error => error,
+        }
+    }
+}
+
+// The errors of the encoding crates only implement the error trait with their `std` feature, so
+// they are sources of parse errors with `std` only, and converted without `#[from]`.
+#[cfg(feature = "base64")]
+impl From<base64::DecodeError> for ParseError {
+    fn from(error: base64::DecodeError) -> Self {
+        ParseError::Base64(error)
+    }
+}
+
+#[cfg(feature = "hex")]
+impl From<hex::FromHexError> for ParseError {
+    fn from(error: hex::FromHexError) -> Self {
+        ParseError::Hex(error)
+    }
+}
+
+#[cfg(feature = "base58")]
+impl From<bs58::decode::Error> for ParseError {
+    fn from(error: bs58::decode::Error) -> Self {
+        ParseError::Base58(error)
+    }
+}
+
+/// Optional backend which is compiled in, but not available on this system. Applications
+/// supporting several backends can match on this to fall back to another one, and show the
+/// remediation hint to the user otherwise.
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+#[error("{backend} is not available, {remediation}")]
+#[non_exhaustive]
+pub struct CapabilityUnavailable {
+    /// Name of the backend
+    pub backend: &'static str,
+    /// What the user can do to make the backend available
+    pub remediation: &'static str,
+}
+
+impl CapabilityUnavailable {
+    pub(crate) const fn new(backend: &'static str, remediation: &'static str) -> Self {
+        CapabilityUnavailable {
+            backend,
+            remediation,
+        }
+    }
+}
+
+/// Hardware randomness generator is missing.
+#[cfg(feature = "std")]
+const HWRNG_UNAVAILABLE: CapabilityUnavailable = CapabilityUnavailable::new(
+    "hardware randomness generator",
+    "load the driver for the device or use Privkey::generate",
+);
+
+/// Service was not started with systemd credentials.
+#[cfg(feature = "std")]
+const CREDENTIALS_UNAVAILABLE: CapabilityUnavailable = CapabilityUnavailable::new(
+    "systemd credentials",
+    "start the service with LoadCredential= or SetCredential=",
+);
+
+/// Possible errors that can be generated when loading WireGuard keys.
+#[cfg(feature = "std")]
+#[derive(Error, Debug)]
+pub enum LoadError {
+    /// Illegal credential name
+    #[error("invalid credential name {0:?}")]
+    CredentialName(String),
+    /// Error reading credential
+    #[error("error reading credential {name:?}")]
+    Credential {
+        name: String,
+        #[source]
+        error: std::io::Error,
+    },
+    /// Environment variable is not set
+    #[error("environment variable {0} is not set")]
+    EnvMissing(String),
+    /// Environment variable is not valid unicode
+    #[error("environment variable {0} is not valid unicode")]
+    EnvUnicode(String),
+    /// Environment variable does not contain a valid key
+    #[error("environment variable {name} does not contain a valid key")]
+    EnvInvalid {
+        name: String,
+        #[source]
+        error: ParseError,
+    },
+    /// Error reading key
+    #[error("error reading key")]
+    Io(#[from] std::io::Error),
+    /// Error parsing key, without details about the key
+    #[error("error parsing key")]
+    Parse(#[source] ParseError),
+    /// Hardware randomness generator failed a health check
+    #[error("hardware randomness failed {0} health check")]
+    Entropy(&'static str),
+    /// Backend is not available on this system
+    #[error(transparent)]
+    Unavailable(#[from] CapabilityUnavailable),
+}
+
+// Keys are loaded from secret sources, so details about them are removed from parse errors.
+#[cfg(feature = "std")]
+impl From<ParseError> for LoadError {
+    fn from(error: ParseError) -> Self {
+        LoadError::Parse(error.redact())
+    }
+}
+
+#[cfg(feature = "std")]
+impl LoadError {
+    /// Backend which is not available, if this error was caused by one.
+    pub fn unavailable(&self) -> Option<CapabilityUnavailable> {
+        match self {
+            LoadError::Unavailable(capability) => Some(*capability),
+            _ => None,
+        }
+    }
+}
+
+/// Any error generated by this crate. Every subsystem has its own error type, which can be
+/// matched on directly; this type wraps all of them for applications that use several
+/// subsystems and want to propagate errors with `?`. The wrapped error is available as the
+/// [source](std::error::Error::source) of this error.
+#[cfg(feature = "std")]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error parsing key
+    #[error("error parsing key")]
+    Parse(#[from] ParseError),
+    /// Error loading key
+    #[error("error loading key")]
+    Load(#[from] LoadError),
+    /// Error parsing network
+    #[error("error parsing network")]
+    Cidr(#[from] allowed_ips::CidrError),
+    /// Error parsing endpoint
+    #[error("error parsing endpoint")]
+    Endpoint(#[from] endpoint::EndpointError),
+    /// Error parsing wg-quick configuration
+    #[cfg(feature = "base64")]
+    #[error("error parsing configuration")]
+    Config(#[from] config::ConfigError),
+    /// Error building configuration section
+    #[cfg(feature = "base64")]
+    #[error("error building configuration")]
+    Build(#[from] config::BuildError),
+    /// Error building Kubernetes Secret manifest
+    #[cfg(feature = "base64")]
+    #[error("error building Kubernetes Secret manifest")]
+    Kubernetes(#[from] kubernetes::KubernetesError),
+    /// Error reading or writing keys using sops
+    #[cfg(feature = "sops")]
+    #[error("error reading or writing keys using sops")]
+    Sops(#[from] sops::SopsError),
+    /// Error decoding COSE key
+    #[cfg(feature = "cose")]
+    #[error("error decoding COSE key")]
+    Cose(#[from] cose::CoseError),
+    /// Error converting key from BSON
+    #[cfg(feature = "bson")]
+    #[error("error converting key from BSON")]
+    Bson(#[from] crate::bson::BsonError),
+    /// Error converting keys or nodes from mesh tools
+    #[cfg(feature = "mesh")]
+    #[error("error converting from mesh tool")]
+    Mesh(#[from] mesh::MeshError),
+    /// Error exporting to systemd-networkd
+    #[cfg(feature = "base64")]
+    #[error("error exporting to systemd-networkd")]
+    Networkd(#[from] networkd::NetworkdError),
+    /// Error exporting to NetworkManager
+    #[cfg(feature = "base64")]
+    #[error("error exporting to NetworkManager")]
+    NetworkManager(#[from] networkmanager::NetworkManagerError),
+    /// Error parsing or emitting tunnel URI
+    #[cfg(feature = "base64")]
+    #[error("error handling tunnel URI")]
+    Uri(#[from] uri::UriError),
+    /// Error reading packet capture
+    #[cfg(all(feature = "unstable", feature = "handshake"))]
+    #[error("error reading packet capture")]
+    Pcap(#[from] handshake::pcap::PcapError),
+    /// Error accessing keystore
+    #[cfg(feature = "keystore")]
+    #[error("error accessing keystore")]
+    Keystore(#[from] keystore::KeystoreError),
+    /// Error deriving hybrid preshared key
+    #[cfg(all(feature = "unstable", feature = "hybrid"))]
+    #[error("error deriving hybrid preshared key")]
+    Hybrid(#[from] hybrid::HybridError),
+    /// Error sealing or opening envelope
+    #[cfg(feature = "envelope")]
+    #[error("error handling envelope")]
+    Envelope(#[from] envelope::EnvelopeError),
+    /// Error rendering QR code
+    #[cfg(feature = "qr")]
+    #[error("error rendering QR code")]
+    Qr(#[from] QrError),
+}
+
+#[cfg(feature = "std")]
+impl Error {
+    /// Backend which is not available, if this error was caused by one. Applications can use
+    /// this to fall back to another backend.
+    pub fn unavailable(&self) -> Option<CapabilityUnavailable> {
+        match self {
+            Error::Load(error) => error.unavailable(),
+            #[cfg(feature = "sops")]
+            Error::Sops(sops::SopsError::Unavailable(capability)) => Some(*capability),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_error_source() {
+    fn parse_endpoint(value: &str) -> Result<endpoint::Endpoint, Error> {
+        Ok(value.parse()?)
+    }
+    let error = parse_endpoint("vpn.example.com").unwrap_err();
+    assert!(matches!(
+        error,
+        Error::Endpoint(endpoint::EndpointError::MissingPort)
+    ));
+    let source = std::error::Error::source(&error).unwrap();
+    assert_eq!(source.to_string(), "missing port");
+}
+
+/// Convert error reading an environment variable, without ever exposing its value.
+#[cfg(feature = "std")]
+fn env_error(name: &str, error: std::env::VarError) -> LoadError {
+    match error {
+        std::env::VarError::NotPresent => LoadError::EnvMissing(name.to_string()),
+        std::env::VarError::NotUnicode(_) => LoadError::EnvUnicode(name.to_string()),
+    }
+}
+
+/// Environment variable systemd uses to pass the credentials directory to services.
+#[cfg(feature = "std")]
+const CREDENTIALS_DIRECTORY: &str = "CREDENTIALS_DIRECTORY";
+
+/// Read a systemd credential by name from the credentials directory, if it is set.
+#[cfg(feature = "std")]
+fn read_systemd_credential(
+    directory: Option<&std::ffi::OsStr>,
+    name: &str,
+) -> Result<String, LoadError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(LoadError::CredentialName(name.to_string()));
+    }
+    let directory = directory.ok_or(LoadError::Unavailable(CREDENTIALS_UNAVAILABLE))?;
+    let path = std::path::Path::new(directory).join(name);
+    std::fs::read_to_string(path).map_err(|error| LoadError::Credential {
+        name: name.to_string(),
+        error,
+    })
+}
+
+/// Serialize key bytes as a tuple, the same way serde serializes byte arrays, or as a byte
+/// string with the `serde-bytes` feature.
+#[cfg(feature = "serde")]
+fn serialize_bytes<S: Serializer, const N: usize>(
+    data: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if cfg!(feature = "serde-bytes") {
+        return serializer.serialize_bytes(data);
+    }
+    let mut tuple = serializer.serialize_tuple(N)?;
+    for byte in data {
+        tuple.serialize_element(byte)?;
+    }
+    tuple.end()
+}
+
+/// Regular expression matching the base64 encoding with padding of the given number of bytes,
+/// including the zero bits at the end, for JSON schemas.
+#[cfg(all(
+    any(feature = "schema", feature = "utoipa", feature = "poem-openapi"),
+    feature = "base64"
+))]
+fn base64_pattern(bytes: usize) -> String {
+    let digits = bytes / 3 * 4;
+    match bytes % 3 {
+        0 => format!("^[A-Za-z0-9+/]{{{}}}$", digits),
+        1 => format!("^[A-Za-z0-9+/]{{{}}}[AQgw]==$", digits + 1),
+        _ => format!("^[A-Za-z0-9+/]{{{}}}[AEIMQUYcgkosw048]=$", digits + 2),
+    }
+}
+
+/// Visitor for key bytes, given either as sequence or as byte string.
+#[cfg(feature = "serde")]
+struct BytesVisitor<const N: usize>;
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} bytes", N)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        read_bytes_seq(seq, &self)
+    }
+
+    fn visit_bytes<E>(self, data: &[u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        data.try_into()
+            .map_err(|_| E::invalid_length(data.len(), &self))
+    }
+}
+
+/// Deserialize key bytes from a tuple or byte string, as written by [serialize_bytes].
+#[cfg(feature = "serde")]
+fn deserialize_bytes<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    if cfg!(feature = "serde-bytes") {
+        deserializer.deserialize_bytes(BytesVisitor::<N>)
+    } else {
+        deserializer.deserialize_tuple(N, BytesVisitor::<N>)
+    }
+}
+
+/// Read exactly `N` bytes from a sequence, rejecting sequences which are shorter or longer.
+#[cfg(feature = "serde")]
+fn read_bytes_seq<'de, A: SeqAccess<'de>, const N: usize>(
+    mut seq: A,
+    expected: &dyn Expected,
+) -> Result<[u8; N], A::Error> {
+    let mut data = [0; N];
+    for index in 0..N {
+        match seq.next_element() {
+            Ok(Some(byte)) => data[index] = byte,
+            Ok(None) => {
+                data.zeroize();
+                return Err(serde::de::Error::invalid_length(index, expected));
+            }
+            Err(error) => {
+                data.zeroize();
+                return Err(error);
+            }
+        }
+    }
+    if seq.next_element::<IgnoredAny>()?.is_some() {
+        data.zeroize();
+        return Err(serde::de::Error::invalid_length(N + 1, expected));
+    }
+    Ok(data)
+}
+
+/// Consonants of proquints, each encoding four bits.
+#[cfg(feature = "proquint")]
+const PROQUINT_CONSONANTS: &[u8; 16] = b"bdfghjklmnprstvz";
+
+/// Vowels of proquints, each encoding two bits.
+#[cfg(feature = "proquint")]
+const PROQUINT_VOWELS: &[u8; 4] = b"aiou";
+
+/// Encode data as proquints separated by dashes. Every proquint encodes 16 bits in five
+/// alternating consonants and vowels. Data of odd length is not supported.
+#[cfg(feature = "proquint")]
+fn proquint_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len() * 3);
+    for word in data.chunks_exact(2) {
+        if !output.is_empty() {
+            output.push('-');
+        }
+        let word = u16::from_be_bytes([word[0], word[1]]);
+        output.push(PROQUINT_CONSONANTS[(word >> 12) as usize & 15] as char);
+        output.push(PROQUINT_VOWELS[(word >> 10) as usize & 3] as char);
+        output.push(PROQUINT_CONSONANTS[(word >> 6) as usize & 15] as char);
+        output.push(PROQUINT_VOWELS[(word >> 4) as usize & 3] as char);
+        output.push(PROQUINT_CONSONANTS[word as usize & 15] as char);
+    }
+    output
+}
+
+/// Decode proquints separated by dashes, in any case. Returns `None` if the data is not valid.
+#[cfg(feature = "proquint")]
+fn proquint_decode(data: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len() / 3);
+    for proquint in data.split('-') {
+        let proquint = proquint.as_bytes();
+        if proquint.len() != 5 {
+            return None;
+        }
+        let mut word = 0u16;
+        for (index, c) in proquint.iter().enumerate() {
+            let c = c.to_ascii_lowercase();
+            word = if index % 2 == 0 {
+                let value = PROQUINT_CONSONANTS.iter().position(|&p| p == c)?;
+                (word << 4) | value as u16
+            } else {
+                let value = PROQUINT_VOWELS.iter().position(|&p| p == c)?;
+                (word << 2) | value as u16
+            };
+        }
+        output.extend_from_slice(&word.to_be_bytes());
+    }
+    Some(output)
+}
+
+#[cfg(feature = "proquint")]
+#[test]
+fn test_proquint() {
+    // examples from the proquint specification, encoding IPv4 addresses
+    assert_eq!(proquint_encode(&[127, 0, 0, 1]), "lusab-babad");
+    assert_eq!(proquint_encode(&[63, 84, 220, 193]), "gutih-tugad");
+    assert_eq!(proquint_encode(&[255, 255, 255, 255]), "zuzuz-zuzuz");
+    assert_eq!(proquint_decode("lusab-babad").unwrap(), [127, 0, 0, 1]);
+    assert_eq!(proquint_decode("GUTIH-tugad").unwrap(), [63, 84, 220, 193]);
+    assert_eq!(proquint_decode("lusab"), Some(vec![127, 0]));
+    assert_eq!(proquint_decode(""), None);
+    assert_eq!(proquint_decode("lusab-"), None);
+    assert_eq!(proquint_decode("lusab babad"), None);
+    assert_eq!(proquint_decode("lusac-babad"), None);
+    assert_eq!(proquint_decode("luuab-babad"), None);
+}
+
+/// Alphabet of z-base-32, in the order of the RFC 4648 alphabet it replaces.
+#[cfg(feature = "base32")]
+const ZBASE32_ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+
+/// Alphabet of RFC 4648 base32.
+#[cfg(feature = "base32")]
+const RFC4648_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encode data as z-base-32, which packs bits like unpadded RFC 4648 base32 but uses an
+/// alphabet designed for human transcription.
+#[cfg(feature = "base32")]
+fn zbase32_encode(data: &[u8]) -> String {
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, data)
+        .bytes()
+        .map(|c| {
+            let index = RFC4648_ALPHABET.iter().position(|&r| r == c).unwrap();
+            ZBASE32_ALPHABET[index] as char
+        })
+        .collect()
+}
+
+/// Decode z-base-32, returning `None` on invalid characters.
+#[cfg(feature = "base32")]
+fn zbase32_decode(data: &str) -> Option<Vec<u8>> {
+    let data = data
+        .bytes()
+        .map(|c| {
+            let index = ZBASE32_ALPHABET.iter().position(|&z| z == c)?;
+            Some(RFC4648_ALPHABET[index] as char)
+        })
+        .collect::<Option<String>>()?;
+    base32::decode(base32::Alphabet::RFC4648 { padding: false }, &data)
+}
+
+#[cfg(feature = "base32")]
+#[test]
+fn test_zbase32() {
+    assert_eq!(zbase32_encode(&[0xf0, 0xbf, 0xc7]), "6n9hq");
+    assert_eq!(zbase32_encode(&[0xd4, 0x7a, 0x04]), "4t7ye");
+    assert_eq!(zbase32_decode("6n9hq").unwrap(), [0xf0, 0xbf, 0xc7]);
+    assert_eq!(zbase32_decode("4T7YE"), None);
+    let data: Vec<u8> = (0..32).collect();
+    let encoded = "yyyoryarywdyqnyjbefoadeqbhebnrounoktcfaadrpbs8y7daxo";
+    assert_eq!(zbase32_encode(&data), encoded);
+    assert_eq!(zbase32_decode(encoded).unwrap(), data);
+}
+
+/// Default path of the hardware randomness generator on Linux.
+#[cfg(feature = "std")]
+pub const HWRNG_PATH: &str = "/dev/hwrng";
+
+/// Error opening a device node which exists, but has no driver loaded.
+#[cfg(feature = "std")]
+const ENODEV: i32 = 19;
+
+/// Number of bytes read from the hardware randomness generator for health checks.
+#[cfg(feature = "std")]
+const HWRNG_SAMPLE_LEN: usize = 512;
+
+/// Repetition count cutoff, see NIST SP 800-90B 4.4.1. Assuming a min-entropy of 4 bits per
+/// byte and a false positive rate of 2^-20, no byte may repeat 6 times in a row.
+#[cfg(feature = "std")]
+const HWRNG_REPETITION_CUTOFF: usize = 6;
+
+/// Adaptive proportion cutoff, see NIST SP 800-90B 4.4.2. With the same assumptions, the
+/// first byte of the sample may not occur 62 times or more in a window of 512 bytes.
+#[cfg(feature = "std")]
+const HWRNG_PROPORTION_CUTOFF: usize = 62;
+
+/// Run the repetition count and adaptive proportion health checks on a sample.
+#[cfg(feature = "std")]
+fn check_entropy(sample: &[u8]) -> Result<(), LoadError> {
+    let mut run = 1;
+    for pair in sample.windows(2) {
+        run = if pair[0] == pair[1] { run + 1 } else { 1 };
+        if run >= HWRNG_REPETITION_CUTOFF {
+            return Err(LoadError::Entropy("repetition count"));
+        }
+    }
+    let first = sample[0];
+    if sample.iter().filter(|byte| **byte == first).count() >= HWRNG_PROPORTION_CUTOFF {
+        return Err(LoadError::Entropy("adaptive proportion"));
+    }
+    Ok(())
 }
 
 /// Length (in bytes) of a WireGuard public key (ed25519).
@@ -74,7 +1127,6 @@ pub const PRIVKEY_LEN: usize = 32;
 pub const SECRET_LEN: usize = 32;
 
 /// WireGuard public key.
-#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Zeroize)]
 pub struct Pubkey([u8; PUBKEY_LEN]);
 
@@ -87,17 +1139,265 @@ impl_hex!(Pubkey);
 impl_base64!(Pubkey);
 #[cfg(feature = "base32")]
 impl_base32!(Pubkey);
+#[cfg(feature = "base58")]
+impl_base58!(Pubkey);
+#[cfg(feature = "proquint")]
+impl_proquint!(Pubkey);
 impl_parse!(Pubkey);
+#[cfg(feature = "alloc")]
+impl_encoding!(Pubkey);
 #[cfg(feature = "serde")]
 impl_serde!(Pubkey, "WireGuard public key");
+#[cfg(feature = "schema")]
+impl_schema!(
+    Pubkey,
+    "wireguard-pubkey",
+    "WireGuard public key",
+    "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg="
+);
+#[cfg(feature = "utoipa")]
+impl_utoipa!(
+    Pubkey,
+    "wireguard-pubkey",
+    "WireGuard public key",
+    "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg="
+);
+#[cfg(feature = "poem-openapi")]
+impl_poem_openapi!(
+    Pubkey,
+    "wireguard-pubkey",
+    "WireGuard public key",
+    "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg="
+);
+#[cfg(feature = "rkyv")]
+impl_rkyv!(Pubkey);
+#[cfg(feature = "arbitrary")]
+impl_arbitrary!(Pubkey);
+#[cfg(feature = "defmt")]
+impl_defmt!(Pubkey);
+#[cfg(feature = "postgres")]
+impl_postgres!(Pubkey);
+#[cfg(feature = "redis")]
+impl_redis!(Pubkey);
+#[cfg(feature = "sea-orm")]
+impl_sea_orm!(Pubkey);
 #[cfg(feature = "rocket")]
 impl_rocket!(Pubkey);
+#[cfg(feature = "std")]
+impl_env!(Pubkey);
+#[cfg(all(feature = "std", feature = "base64"))]
+impl_io!(Pubkey);
+
+/// Maximum length of a single DNS label, such as one component of a hostname.
+#[cfg(feature = "base32")]
+pub const DNS_LABEL_MAX_LEN: usize = 63;
+
+/// Domain separation label for hashed key prefixes.
+#[cfg(feature = "blake2")]
+const LABEL_PREFIX: &[u8] = b"wireguard-keys prefix v1";
+
+/// Domain separation label for private keys derived from entropy.
+#[cfg(feature = "blake2")]
+const LABEL_ENTROPY_PRIVKEY: &[u8] = b"wireguard-keys privkey entropy v1";
+
+/// Domain separation label for preshared keys derived from entropy.
+#[cfg(feature = "blake2")]
+const LABEL_ENTROPY_SECRET: &[u8] = b"wireguard-keys psk entropy v1";
+
+/// Hash entropy of any length into 32 bytes, using BLAKE2s with a domain separation label.
+#[cfg(feature = "blake2")]
+fn hash_entropy(label: &[u8], entropy: &[u8]) -> [u8; 32] {
+    use blake2::{Blake2s256, Digest};
+    let mut data = [0; 32];
+    Blake2s256::new()
+        .chain_update(label)
+        .chain_update(entropy)
+        .finalize_into((&mut data).into());
+    data
+}
 
 impl Pubkey {
-    #[cfg(test)]
+    #[cfg(all(test, feature = "std"))]
     fn generate() -> Pubkey {
         Privkey::generate().pubkey()
     }
+
+    /// First `N` bytes of the key. Asking for more bytes than the key has is a compile error:
+    ///
+    /// ```compile_fail
+    /// # use wireguard_keys::Pubkey;
+    /// let prefix: [u8; 33] = Pubkey::new([0; 32]).prefix();
+    /// ```
+    pub fn prefix<const N: usize>(&self) -> [u8; N] {
+        const { assert!(N <= PUBKEY_LEN, "prefix is longer than the key") };
+        let mut prefix = [0; N];
+        prefix.copy_from_slice(&self.0[..N]);
+        prefix
+    }
+
+    /// First `N` bytes of the BLAKE2s hash of the key. Unlike the raw key bytes, every bit of
+    /// the hash is uniformly distributed, so this is better suited for short identifiers, filters
+    /// and hash tables. Requires the `blake2` feature.
+    #[cfg(feature = "blake2")]
+    pub fn hashed_prefix<const N: usize>(&self) -> [u8; N] {
+        use blake2::{Blake2s256, Digest};
+        const { assert!(N <= PUBKEY_LEN, "prefix is longer than the hash") };
+        let hash = Blake2s256::new()
+            .chain_update(LABEL_PREFIX)
+            .chain_update(self.0)
+            .finalize();
+        let mut prefix = [0; N];
+        prefix.copy_from_slice(&hash[..N]);
+        prefix
+    }
+
+    /// Parse key from a DNS label, such as the first component of a hostname. Since DNS names
+    /// are case insensitive, both lowercase and uppercase labels are accepted.
+    #[cfg(feature = "base32")]
+    pub fn from_dns_label(label: &str) -> Result<Self, ParseError> {
+        if label.is_empty() || label.len() > DNS_LABEL_MAX_LEN {
+            return Err(ParseError::Length);
+        }
+        let data = base32::decode(base32::Alphabet::RFC4648 { padding: false }, label)
+            .ok_or(ParseError::Base32Error)?;
+        data.as_slice().try_into()
+    }
+
+    /// Encode key as lowercase base32 without padding, which can be used as a DNS label, for
+    /// example to derive hostnames of peers. The label is 52 characters long.
+    #[cfg(feature = "base32")]
+    pub fn to_dns_label(&self) -> String {
+        let label =
+            base32::encode(base32::Alphabet::RFC4648 { padding: false }, &self.0).to_lowercase();
+        debug_assert!(label.len() <= DNS_LABEL_MAX_LEN);
+        label
+    }
+}
+
+#[cfg(feature = "base32")]
+#[test]
+fn test_pubkey_dns_label() {
+    let pubkey = Pubkey::generate();
+    let label = pubkey.to_dns_label();
+    assert_eq!(label.len(), 52);
+    assert!(label
+        .bytes()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+    assert_eq!(Pubkey::from_dns_label(&label).unwrap(), pubkey);
+    assert_eq!(
+        Pubkey::from_dns_label(&label.to_uppercase()).unwrap(),
+        pubkey
+    );
+    assert!(matches!(
+        Pubkey::from_dns_label(""),
+        Err(ParseError::Length)
+    ));
+    assert!(matches!(
+        Pubkey::from_dns_label(&"a".repeat(DNS_LABEL_MAX_LEN + 1)),
+        Err(ParseError::Length)
+    ));
+    assert!(matches!(
+        Pubkey::from_dns_label(&format!("{}-x", &label[..50])),
+        Err(ParseError::Base32Error)
+    ));
+    assert!(matches!(
+        Pubkey::from_dns_label(&label[..40]),
+        Err(ParseError::Length)
+    ));
+}
+
+#[cfg(feature = "base32")]
+#[test]
+fn test_pubkey_parse_dns_label() {
+    // this label only uses characters which are also valid z-base-32
+    let label = "abcdefghijkmnopqrstuwxyz34567abcdefghijkmnopqrstuwxa";
+    let pubkey = Pubkey::from_dns_label(label).unwrap();
+    assert_eq!(pubkey.to_dns_label(), label);
+    assert_eq!(Pubkey::from_base32(label).unwrap(), pubkey);
+    assert_ne!(Pubkey::from_zbase32(label).unwrap(), pubkey);
+    assert!(matches!(Pubkey::parse(label), Err(ParseError::Ambiguous)));
+    assert_eq!(Pubkey::parse(&label.to_uppercase()).unwrap(), pubkey);
+    let label = pubkey.to_zbase32();
+    assert!(Pubkey::from_base32(&label).is_err());
+    assert_eq!(Pubkey::parse(&label).unwrap(), pubkey);
+}
+
+#[cfg(all(feature = "base58", feature = "base64"))]
+#[test]
+fn test_pubkey_parse_base58() {
+    // keys with a small leading byte have 43 base58 characters, like unpadded base64, which
+    // takes precedence in parse
+    let mut found = false;
+    for byte in 0..=255 {
+        let mut data = [7; PUBKEY_LEN];
+        data[PUBKEY_LEN - 1] = byte;
+        let encoded = Pubkey::new(data).to_base58();
+        if let Ok(base64) = Pubkey::from_base64_unpadded(&encoded) {
+            assert_eq!(Pubkey::parse(&encoded).unwrap(), base64);
+            assert!(matches!(
+                Pubkey::parse_detect(&encoded),
+                Err(ParseError::Ambiguous)
+            ));
+            found = true;
+        } else {
+            assert_eq!(Pubkey::parse(&encoded).unwrap().0, data);
+            assert_eq!(
+                Pubkey::parse_detect(&encoded).unwrap(),
+                (Pubkey::new(data), Encoding::Base58)
+            );
+        }
+        assert_eq!(Pubkey::decode(&encoded, Encoding::Base58).unwrap().0, data);
+    }
+    assert!(found);
+}
+
+#[cfg(all(test, feature = "rocket", feature = "base64"))]
+#[rocket::get("/peers/<pubkey>?<peer>")]
+fn rocket_peer(pubkey: Pubkey, peer: Option<Pubkey>) -> String {
+    format!("{pubkey} {peer:?}")
+}
+
+#[cfg(all(feature = "rocket", feature = "base64"))]
+#[test]
+fn test_pubkey_rocket_uri() {
+    use rocket::uri;
+    let pubkey = Pubkey::new([0xfb; PUBKEY_LEN]);
+    let component = pubkey.to_url_component();
+    assert_eq!(
+        uri!(rocket_peer(pubkey, Some(pubkey))).to_string(),
+        format!("/peers/{component}?peer={component}")
+    );
+    assert_eq!(
+        uri!(rocket_peer(&pubkey, _)).to_string(),
+        format!("/peers/{component}")
+    );
+
+    let rocket = rocket::build().mount("/", rocket::routes![rocket_peer]);
+    let client = rocket::local::blocking::Client::untracked(rocket).unwrap();
+    let response = client.get(uri!(rocket_peer(pubkey, Some(pubkey))));
+    assert_eq!(
+        response.dispatch().into_string().unwrap(),
+        format!("{pubkey} Some({pubkey:?})")
+    );
+}
+
+#[test]
+fn test_pubkey_prefix() {
+    let pubkey = Pubkey::new(std::array::from_fn(|index| index as u8));
+    assert_eq!(pubkey.prefix::<0>(), [0u8; 0]);
+    assert_eq!(pubkey.prefix(), [0, 1, 2, 3]);
+    assert_eq!(pubkey.prefix::<PUBKEY_LEN>(), *pubkey);
+}
+
+#[cfg(all(feature = "std", feature = "blake2"))]
+#[test]
+fn test_pubkey_hashed_prefix() {
+    let pubkey = Pubkey::generate();
+    let short: [u8; 8] = pubkey.hashed_prefix();
+    let long: [u8; PUBKEY_LEN] = pubkey.hashed_prefix();
+    assert_eq!(short, long[..8]);
+    assert_ne!(long, *pubkey);
+    assert_ne!(short, Pubkey::generate().hashed_prefix());
 }
 
 #[test]
@@ -128,7 +1428,6 @@ impl TryFrom<&[u8]> for Pubkey {
 }
 
 /// WireGuard private key.
-#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Zeroize)]
 pub struct Privkey([u8; PRIVKEY_LEN]);
 
@@ -141,16 +1440,123 @@ impl_hex!(Privkey);
 impl_base64!(Privkey);
 #[cfg(feature = "base32")]
 impl_base32!(Privkey);
+#[cfg(feature = "base58")]
+impl_base58!(Privkey);
+#[cfg(feature = "proquint")]
+impl_proquint!(Privkey);
 impl_parse!(Privkey);
+#[cfg(feature = "alloc")]
+impl_encoding!(Privkey);
 #[cfg(feature = "serde")]
 impl_serde!(Privkey, "WireGuard private key");
+#[cfg(feature = "schema")]
+impl_schema!(
+    Privkey,
+    "wireguard-privkey",
+    "WireGuard private key",
+    "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk="
+);
+#[cfg(feature = "utoipa")]
+impl_utoipa!(
+    Privkey,
+    "wireguard-privkey",
+    "WireGuard private key",
+    "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk="
+);
+#[cfg(feature = "poem-openapi")]
+impl_poem_openapi!(
+    Privkey,
+    "wireguard-privkey",
+    "WireGuard private key",
+    "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk="
+);
+#[cfg(feature = "rkyv")]
+impl_rkyv!(Privkey);
+#[cfg(feature = "arbitrary")]
+impl_arbitrary!(Privkey);
+#[cfg(feature = "defmt")]
+impl_defmt!(Privkey, redacted);
+#[cfg(feature = "postgres")]
+impl_postgres!(Privkey);
+#[cfg(feature = "redis")]
+impl_redis!(Privkey);
+#[cfg(feature = "sea-orm")]
+impl_sea_orm!(Privkey);
 #[cfg(feature = "rocket")]
 impl_rocket!(Privkey);
+#[cfg(feature = "std")]
+impl_env!(Privkey);
+#[cfg(all(feature = "std", feature = "base64"))]
+impl_io!(Privkey);
+#[cfg(feature = "std")]
+impl_systemd!(Privkey);
 
 impl Privkey {
     /// Generate new private key using the kernel randomness generator.
+    /// When the `fast-rng` feature is enabled, a per-thread generator seeded from it is used.
+    #[cfg(feature = "std")]
     pub fn generate() -> Self {
-        let private_key = StaticSecret::new(OsRng);
+        let mut data = [0; PRIVKEY_LEN];
+        rng::fill_bytes(&mut data);
+        let private_key = StaticSecret::from(data);
+        data.zeroize();
+        Privkey(private_key.to_bytes())
+    }
+
+    /// Generate new private key using the given randomness generator, such as the hardware
+    /// generator of a microcontroller, for targets without operating system.
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut data = [0; PRIVKEY_LEN];
+        rng.fill_bytes(&mut data);
+        let private_key = StaticSecret::from(data);
+        data.zeroize();
+        Privkey(private_key.to_bytes())
+    }
+
+    /// Generate new private key from a hardware randomness generator, such as [HWRNG_PATH].
+    /// The output of the device is health checked before use and mixed with the kernel
+    /// randomness generator, so the key is never weaker than one from [generate](Self::generate).
+    ///
+    /// The generator is reported as [unavailable](LoadError::Unavailable) when the device has
+    /// no driver, or when [HWRNG_PATH] does not exist. Other paths which do not exist are
+    /// reported as I/O errors.
+    #[cfg(feature = "std")]
+    pub fn from_hwrng<P: AsRef<std::path::Path>>(path: P) -> Result<Self, LoadError> {
+        use std::io::Read;
+        let path = path.as_ref();
+        let mut sample = [0; HWRNG_SAMPLE_LEN];
+        let mut file = std::fs::File::open(path).map_err(|error| {
+            let missing = error.kind() == std::io::ErrorKind::NotFound
+                && path == std::path::Path::new(HWRNG_PATH);
+            if missing || error.raw_os_error() == Some(ENODEV) {
+                LoadError::Unavailable(HWRNG_UNAVAILABLE)
+            } else {
+                LoadError::Io(error)
+            }
+        })?;
+        file.read_exact(&mut sample)?;
+        let result = check_entropy(&sample);
+        let mut data = [0; PRIVKEY_LEN];
+        OsRng.fill_bytes(&mut data);
+        for (byte, hardware) in data.iter_mut().zip(&sample) {
+            *byte ^= hardware;
+        }
+        sample.zeroize();
+        let private_key = StaticSecret::from(data);
+        data.zeroize();
+        result?;
+        Ok(Privkey(private_key.to_bytes()))
+    }
+
+    /// Derive private key from entropy gathered by the caller, such as samples of a hardware
+    /// randomness generator, for targets without operating system. The input can have any
+    /// length and is hashed, but must contain at least 256 bits of entropy, which this can not
+    /// check. Requires the `blake2` feature.
+    #[cfg(feature = "blake2")]
+    pub fn from_entropy(entropy: &[u8]) -> Self {
+        let mut data = hash_entropy(LABEL_ENTROPY_PRIVKEY, entropy);
+        let private_key = StaticSecret::from(data);
+        data.zeroize();
         Privkey(private_key.to_bytes())
     }
 
@@ -170,6 +1576,93 @@ impl Privkey {
         let public_key: PublicKey = (&private_key).into();
         Pubkey(public_key.to_bytes())
     }
+
+    /// Check if the public key belongs to this private key, for catching configurations where
+    /// keys of different pairs were mixed up.
+    pub fn matches(&self, pubkey: &Pubkey) -> bool {
+        self.pubkey() == *pubkey
+    }
+
+    /// Compute the x25519 shared secret of this private key and the public key. This is the raw
+    /// output of the function, which WireGuard never uses as a key directly but hashes in its
+    /// handshake, so it is mostly useful for testing against other x25519 implementations. It
+    /// is all zeroes when the public key is a point of low order, which WireGuard rejects.
+    pub fn diffie_hellman(&self, pubkey: &Pubkey) -> SharedSecret {
+        let private_key = StaticSecret::from(self.0);
+        let shared = private_key.diffie_hellman(&PublicKey::from(pubkey.0));
+        SharedSecret(*shared.as_bytes())
+    }
+}
+
+/// Raw x25519 shared secret, as computed by [Privkey::diffie_hellman]. It is not a WireGuard
+/// key, so unlike [Secret] it has no encodings, no [Display](fmt::Display) and no serde
+/// support, which keeps it from being written to configurations by accident. It is zeroized
+/// when dropped.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+    /// Raw bytes of the shared secret.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Determine if the shared secret is all zeroes, which is the case when the public key is
+    /// a point of low order.
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().fold(0, |acc, byte| acc | byte) == 0
+    }
+}
+
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedSecret(..)")
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_privkey_matches() {
+    let privkey = Privkey::generate();
+    assert!(privkey.matches(&privkey.pubkey()));
+    assert!(!privkey.matches(&Privkey::generate().pubkey()));
+    assert!(!privkey.matches(&Pubkey::new(*privkey)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_privkey_diffie_hellman() {
+    let alice = Privkey::generate();
+    let bob = Privkey::generate();
+    assert_eq!(
+        alice.diffie_hellman(&bob.pubkey()),
+        bob.diffie_hellman(&alice.pubkey())
+    );
+    assert_ne!(
+        alice.diffie_hellman(&bob.pubkey()),
+        alice.diffie_hellman(&alice.pubkey())
+    );
+    assert!(alice.diffie_hellman(&Pubkey::new([0; 32])).is_zero());
+    assert!(!alice.diffie_hellman(&bob.pubkey()).is_zero());
+    assert_eq!(
+        format!("{:?}", alice.diffie_hellman(&bob.pubkey())),
+        "SharedSecret(..)"
+    );
+
+    // test vector from RFC 7748, section 6.1
+    #[cfg(feature = "hex")]
+    {
+        let alice =
+            Privkey::from_hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a")
+                .unwrap();
+        let bob =
+            Pubkey::from_hex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+                .unwrap();
+        let shared =
+            Secret::from_hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742")
+                .unwrap();
+        assert_eq!(alice.diffie_hellman(&bob).as_bytes(), &*shared);
+    }
 }
 
 #[test]
@@ -199,6 +1692,7 @@ impl TryFrom<&[u8]> for Privkey {
     }
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_wireguard_privkey() {
     let key = Privkey::new([0; PRIVKEY_LEN]);
@@ -211,8 +1705,100 @@ fn test_wireguard_privkey() {
     assert_eq!(key.pubkey(), key.pubkey());
 }
 
+#[cfg(feature = "std")]
+#[test]
+fn test_generate_with_rng() {
+    let privkey = Privkey::generate_with_rng(&mut OsRng);
+    assert!(privkey.valid());
+    assert_ne!(privkey, Privkey::generate_with_rng(&mut OsRng));
+    assert_ne!(
+        Secret::generate_with_rng(&mut OsRng),
+        Secret::generate_with_rng(&mut OsRng)
+    );
+    assert_ne!(
+        Token32::generate_with_rng(&mut OsRng),
+        Token32::generate_with_rng(&mut OsRng)
+    );
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg(feature = "std")]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+fn test_generate() {
+    let privkey = Privkey::generate();
+    assert!(privkey.valid());
+    assert_ne!(privkey, Privkey::generate());
+    assert!(privkey.matches(&privkey.pubkey()));
+    assert_ne!(Secret::generate(), Secret::generate());
+    assert_ne!(Token32::generate(), Token32::generate());
+}
+
+#[cfg(feature = "blake2")]
+#[test]
+fn test_from_entropy() {
+    let entropy = [0x5a; 64];
+    let privkey = Privkey::from_entropy(&entropy);
+    assert!(privkey.valid());
+    assert_eq!(Privkey::from_entropy(&entropy), privkey);
+    assert_ne!(Privkey::from_entropy(&entropy[..63]), privkey);
+    assert_ne!(Privkey::from_entropy(&[]), privkey);
+    let secret = Secret::from_entropy(&entropy);
+    assert_eq!(Secret::from_entropy(&entropy), secret);
+    assert_ne!(*secret, *privkey);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_privkey_from_hwrng() {
+    let directory =
+        std::env::temp_dir().join(format!("wireguard-keys-hwrng-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("random");
+    let mut sample = [0; HWRNG_SAMPLE_LEN];
+    OsRng.fill_bytes(&mut sample);
+    // make sure the random sample does not fail by chance
+    for index in 1..sample.len() {
+        if sample[index] == sample[index - 1] {
+            sample[index] ^= 1;
+        }
+    }
+    std::fs::write(&path, sample).unwrap();
+    let key = Privkey::from_hwrng(&path).unwrap();
+    assert!(key.valid());
+    assert_ne!(Privkey::from_hwrng(&path).unwrap(), key);
+
+    let mut stuck = sample;
+    stuck[100..110].fill(0xaa);
+    std::fs::write(&path, stuck).unwrap();
+    assert!(matches!(
+        Privkey::from_hwrng(&path),
+        Err(LoadError::Entropy("repetition count"))
+    ));
+    let mut biased = sample;
+    for byte in biased.iter_mut().step_by(4) {
+        *byte = 0;
+    }
+    std::fs::write(&path, biased).unwrap();
+    assert!(matches!(
+        Privkey::from_hwrng(&path),
+        Err(LoadError::Entropy("adaptive proportion"))
+    ));
+    std::fs::write(&path, &sample[..32]).unwrap();
+    assert!(matches!(Privkey::from_hwrng(&path), Err(LoadError::Io(_))));
+    std::fs::remove_file(&path).unwrap();
+    // only the default device is reported as unavailable when missing
+    let error = Privkey::from_hwrng(&path).unwrap_err();
+    assert!(matches!(error, LoadError::Io(_)));
+    assert_eq!(error.unavailable(), None);
+    let error = Error::from(LoadError::Unavailable(HWRNG_UNAVAILABLE));
+    assert_eq!(error.unavailable(), Some(HWRNG_UNAVAILABLE));
+    std::fs::remove_dir(&directory).unwrap();
+}
+
 /// WireGuard preshared key.
-#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Zeroize)]
 pub struct Secret([u8; SECRET_LEN]);
 
@@ -225,19 +1811,81 @@ impl_hex!(Secret);
 impl_base64!(Secret);
 #[cfg(feature = "base32")]
 impl_base32!(Secret);
+#[cfg(feature = "base58")]
+impl_base58!(Secret);
+#[cfg(feature = "proquint")]
+impl_proquint!(Secret);
 impl_parse!(Secret);
+#[cfg(feature = "alloc")]
+impl_encoding!(Secret);
 #[cfg(feature = "serde")]
 impl_serde!(Secret, "WireGuard preshared key");
+#[cfg(feature = "schema")]
+impl_schema!(
+    Secret,
+    "wireguard-psk",
+    "WireGuard preshared key",
+    "FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE="
+);
+#[cfg(feature = "utoipa")]
+impl_utoipa!(
+    Secret,
+    "wireguard-psk",
+    "WireGuard preshared key",
+    "FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE="
+);
+#[cfg(feature = "poem-openapi")]
+impl_poem_openapi!(
+    Secret,
+    "wireguard-psk",
+    "WireGuard preshared key",
+    "FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE="
+);
+#[cfg(feature = "rkyv")]
+impl_rkyv!(Secret);
+#[cfg(feature = "arbitrary")]
+impl_arbitrary!(Secret);
+#[cfg(feature = "defmt")]
+impl_defmt!(Secret, redacted);
+#[cfg(feature = "postgres")]
+impl_postgres!(Secret);
+#[cfg(feature = "redis")]
+impl_redis!(Secret);
+#[cfg(feature = "sea-orm")]
+impl_sea_orm!(Secret);
 #[cfg(feature = "rocket")]
 impl_rocket!(Secret);
+#[cfg(feature = "std")]
+impl_env!(Secret);
+#[cfg(all(feature = "std", feature = "base64"))]
+impl_io!(Secret);
+#[cfg(feature = "std")]
+impl_systemd!(Secret);
 
 impl Secret {
     /// Generate new random preshared key using the system randomness generator.
+    /// When the `fast-rng` feature is enabled, a per-thread generator seeded from it is used.
+    #[cfg(feature = "std")]
     pub fn generate() -> Self {
         let mut data = [0; SECRET_LEN];
-        OsRng.fill_bytes(&mut data);
+        rng::fill_bytes(&mut data);
         Secret(data)
     }
+
+    /// Generate new random preshared key using the given randomness generator.
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut data = [0; SECRET_LEN];
+        rng.fill_bytes(&mut data);
+        Secret(data)
+    }
+
+    /// Derive preshared key from entropy gathered by the caller, in the same way as
+    /// [Privkey::from_entropy]. The same entropy gives a different preshared key than private
+    /// key. Requires the `blake2` feature.
+    #[cfg(feature = "blake2")]
+    pub fn from_entropy(entropy: &[u8]) -> Self {
+        Secret(hash_entropy(LABEL_ENTROPY_SECRET, entropy))
+    }
 }
 
 #[test]
@@ -266,3 +1914,109 @@ impl TryFrom<&[u8]> for Secret {
         }
     }
 }
+
+/// Length (in bytes) of a [Token32].
+pub const TOKEN_LEN: usize = 32;
+
+/// Random 32-byte identifier, such as a node ID or correlation ID. Supports the same encodings
+/// as the key types, but is explicitly not key material: it is not cleared from memory and it
+/// is printed in full by [Debug]. Use [Secret] for anything that needs to be kept secret.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Token32([u8; TOKEN_LEN]);
+
+impl_new!(Token32, TOKEN_LEN);
+impl_display!(Token32);
+impl_deref!(Token32, TOKEN_LEN);
+#[cfg(feature = "hex")]
+impl_hex!(Token32);
+#[cfg(feature = "base64")]
+impl_base64!(Token32);
+#[cfg(feature = "base32")]
+impl_base32!(Token32);
+#[cfg(feature = "base58")]
+impl_base58!(Token32);
+#[cfg(feature = "proquint")]
+impl_proquint!(Token32);
+impl_parse!(Token32);
+#[cfg(feature = "alloc")]
+impl_encoding!(Token32);
+#[cfg(feature = "serde")]
+impl_serde!(Token32, "32-byte token");
+#[cfg(feature = "schema")]
+impl_schema!(
+    Token32,
+    "token32",
+    "32-byte token",
+    "YWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXoxMjM0NTY="
+);
+#[cfg(feature = "utoipa")]
+impl_utoipa!(
+    Token32,
+    "token32",
+    "32-byte token",
+    "YWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXoxMjM0NTY="
+);
+#[cfg(feature = "poem-openapi")]
+impl_poem_openapi!(
+    Token32,
+    "token32",
+    "32-byte token",
+    "YWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXoxMjM0NTY="
+);
+#[cfg(feature = "rkyv")]
+impl_rkyv!(Token32);
+#[cfg(feature = "defmt")]
+impl_defmt!(Token32);
+#[cfg(feature = "postgres")]
+impl_postgres!(Token32);
+#[cfg(feature = "redis")]
+impl_redis!(Token32);
+#[cfg(feature = "sea-orm")]
+impl_sea_orm!(Token32);
+#[cfg(feature = "rocket")]
+impl_rocket!(Token32);
+#[cfg(feature = "std")]
+impl_env!(Token32);
+#[cfg(all(feature = "std", feature = "base64"))]
+impl_io!(Token32);
+
+impl Token32 {
+    /// Generate new random token using the system randomness generator.
+    /// When the `fast-rng` feature is enabled, a per-thread generator seeded from it is used.
+    #[cfg(feature = "std")]
+    pub fn generate() -> Self {
+        let mut data = [0; TOKEN_LEN];
+        rng::fill_bytes(&mut data);
+        Token32(data)
+    }
+
+    /// Generate new random token using the given randomness generator. Tokens are not key
+    /// material, so any generator is accepted.
+    pub fn generate_with_rng<R: RngCore>(rng: &mut R) -> Self {
+        let mut data = [0; TOKEN_LEN];
+        rng.fill_bytes(&mut data);
+        Token32(data)
+    }
+}
+
+impl TryFrom<&[u8]> for Token32 {
+    type Error = ParseError;
+    fn try_from(token: &[u8]) -> Result<Self, Self::Error> {
+        token
+            .try_into()
+            .map(Token32)
+            .map_err(|_| ParseError::Length)
+    }
+}
+
+#[test]
+fn test_token_from_slice() {
+    assert!(matches!(
+        Token32::try_from(&[0; 3][..]),
+        Err(ParseError::Length)
+    ));
+    assert_eq!(
+        Token32::try_from(&[7; TOKEN_LEN][..]).unwrap(),
+        Token32::new([7; TOKEN_LEN])
+    );
+}
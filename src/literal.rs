@@ -0,0 +1,180 @@
+//! Decoding of keys at compile time. The [pubkey!](crate::pubkey!),
+//! [privkey!](crate::privkey!) and [secret!](crate::secret!) macros accept a key as hex or
+//! base64 string literal and embed it as constant, so that invalid keys are compile errors:
+//!
+//! ```
+//! use wireguard_keys::{pubkey, Pubkey};
+//! const SERVER: Pubkey = pubkey!("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=");
+//! ```
+//!
+//! ```compile_fail
+//! # use wireguard_keys::pubkey;
+//! let server = pubkey!("not a key");
+//! ```
+//!
+//! Keep in mind that private keys and preshared keys embedded this way are stored in plain text
+//! in the binary.
+
+/// Value of a hex digit, in either case.
+const fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Value of a base64 digit, in either the standard or the urlsafe alphabet.
+const fn base64_digit(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+/// Decode `N` bytes from hex, in either case. Returns `None` if the data is not valid.
+pub const fn decode_hex<const N: usize>(data: &str) -> Option<[u8; N]> {
+    let data = data.as_bytes();
+    if data.len() != N * 2 {
+        return None;
+    }
+    let mut output = [0; N];
+    let mut index = 0;
+    while index < N {
+        let (Some(high), Some(low)) = (hex_digit(data[2 * index]), hex_digit(data[2 * index + 1]))
+        else {
+            return None;
+        };
+        output[index] = (high << 4) | low;
+        index += 1;
+    }
+    Some(output)
+}
+
+/// Decode `N` bytes from base64 with padding, in either the standard or the urlsafe alphabet.
+/// Returns `None` if the data is not valid.
+pub const fn decode_base64<const N: usize>(data: &str) -> Option<[u8; N]> {
+    let data = data.as_bytes();
+    if data.len() != N.div_ceil(3) * 4 {
+        return None;
+    }
+    let digits = (N * 4).div_ceil(3);
+    let mut output = [0; N];
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    let mut written = 0;
+    let mut index = 0;
+    while index < digits {
+        let Some(digit) = base64_digit(data[index]) else {
+            return None;
+        };
+        bits = (bits << 6) | digit as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            output[written] = (bits >> count) as u8;
+            written += 1;
+        }
+        index += 1;
+    }
+    // unused bits of the last digit must be zero, so that every key has a single encoding
+    if bits & ((1 << count) - 1) != 0 {
+        return None;
+    }
+    while index < data.len() {
+        if data[index] != b'=' {
+            return None;
+        }
+        index += 1;
+    }
+    Some(output)
+}
+
+/// Decode `N` bytes from hex or base64, panicking if the data is valid in neither. Used by
+/// the key macros, where the panic turns into a compile error.
+#[doc(hidden)]
+pub const fn decode_literal<const N: usize>(data: &str) -> [u8; N] {
+    if let Some(output) = decode_hex(data) {
+        return output;
+    }
+    match decode_base64(data) {
+        Some(output) => output,
+        None => panic!("key literal is neither valid hex nor base64"),
+    }
+}
+
+/// Create [Pubkey](crate::Pubkey) constant from a hex or base64 string literal, which is
+/// validated at compile time.
+#[macro_export]
+macro_rules! pubkey {
+    ($key:expr) => {{
+        const KEY: $crate::Pubkey = $crate::Pubkey::new($crate::literal::decode_literal($key));
+        KEY
+    }};
+}
+
+/// Create [Privkey](crate::Privkey) constant from a hex or base64 string literal, which is
+/// validated at compile time.
+#[macro_export]
+macro_rules! privkey {
+    ($key:expr) => {{
+        const KEY: $crate::Privkey = $crate::Privkey::new($crate::literal::decode_literal($key));
+        KEY
+    }};
+}
+
+/// Create [Secret](crate::Secret) constant from a hex or base64 string literal, which is
+/// validated at compile time.
+#[macro_export]
+macro_rules! secret {
+    ($key:expr) => {{
+        const KEY: $crate::Secret = $crate::Secret::new($crate::literal::decode_literal($key));
+        KEY
+    }};
+}
+
+#[test]
+fn test_decode_hex() {
+    assert_eq!(decode_hex("00ff7fA0"), Some([0x00, 0xff, 0x7f, 0xa0]));
+    assert_eq!(decode_hex::<4>("00ff7f"), None);
+    assert_eq!(decode_hex::<4>("00ff7fg0"), None);
+    assert_eq!(decode_hex::<0>(""), Some([]));
+}
+
+#[test]
+fn test_decode_base64() {
+    assert_eq!(decode_base64("Zm9vYg=="), Some(*b"foob"));
+    assert_eq!(decode_base64("Zm9vYmE="), Some(*b"fooba"));
+    assert_eq!(decode_base64("Zm9vYmFy"), Some(*b"foobar"));
+    assert_eq!(decode_base64("-_8="), Some([0xfb, 0xff]));
+    assert_eq!(decode_base64("+/8="), Some([0xfb, 0xff]));
+    assert_eq!(decode_base64::<4>("Zm9vYh=="), None);
+    assert_eq!(decode_base64::<4>("Zm9vYg="), None);
+    assert_eq!(decode_base64::<4>("Zm9vYgA="), None);
+    assert_eq!(decode_base64::<4>("Zm9v Yg="), None);
+}
+
+#[test]
+fn test_key_macros() {
+    use crate::{Privkey, Pubkey, Secret};
+    const PRIVKEY: Privkey = privkey!("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=");
+    const PUBKEY: Pubkey = pubkey!("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=");
+    assert_eq!(
+        *PRIVKEY,
+        decode_base64::<32>("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=").unwrap()
+    );
+    assert_eq!(
+        pubkey!("c53201039adba14be71f886da1d8dbe9eebded08cb111b75340078999aa9f038"),
+        PUBKEY
+    );
+    let secret: Secret =
+        secret!("0000000000000000000000000000000000000000000000000000000000000001");
+    let mut expected = [0; 32];
+    expected[31] = 1;
+    assert_eq!(*secret, expected);
+}
@@ -1,6 +1,6 @@
 #[cfg(feature = "serde")]
 macro_rules! impl_serde {
-    ($type:ty, $mesg:literal) => {
+    (@impl $type:ty, $mesg:literal) => {
         impl Serialize for $type {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where
@@ -10,7 +10,7 @@ macro_rules! impl_serde {
                     let encoded: String = self.to_string();
                     (&encoded).serialize(serializer)
                 } else {
-                    self.0.serialize(serializer)
+                    serialize_bytes(&self.0, serializer)
                 }
             }
         }
@@ -32,24 +32,45 @@ macro_rules! impl_serde {
 
                         fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
                         where
-                            E: Error,
+                            E: serde::de::Error,
+                        {
+                            <$type>::from_str(s).map_err(serde::de::Error::custom)
+                        }
+
+                        fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+                        where
+                            A: SeqAccess<'de>,
+                        {
+                            Ok(<$type>::new(read_bytes_seq(seq, &self)?))
+                        }
+
+                        fn visit_bytes<E>(self, data: &[u8]) -> Result<Self::Value, E>
+                        where
+                            E: serde::de::Error,
                         {
-                            <$type>::from_str(s).map_err(Error::custom)
+                            <$type>::try_from(data)
+                                .map_err(|_| E::invalid_length(data.len(), &self))
                         }
                     }
 
-                    deserializer.deserialize_str(KeyVisitor)
+                    // self-describing formats may also contain keys as byte arrays
+                    deserializer.deserialize_any(KeyVisitor)
                 } else {
-                    let data: [u8; 32] = <[u8; 32]>::deserialize(deserializer)?;
-                    Ok(<$type>::new(data))
+                    Ok(<$type>::new(deserialize_bytes(deserializer)?))
                 }
             }
         }
+    };
+    ($type:ty, $mesg:literal) => {
+        impl_serde!(@impl $type, $mesg);
 
         paste! {
             #[test]
             fn [<test_ $type:lower _serde>]() {
-                use serde_test::{assert_tokens, Configure, Token};
+                use serde_test::{
+                    assert_de_tokens, assert_de_tokens_error, assert_tokens, Configure, Readable,
+                    Token,
+                };
                 let example = "yG+Xc4BmcF/j5ChWkOloirX6nWxjWqN3p2nihDtGVW4=";
                 let key = <$type>::from_str(example).unwrap();
                 assert_tokens(&key.readable(), &[Token::Str(example)]);
@@ -58,7 +79,23 @@ macro_rules! impl_serde {
                     tokens.push(Token::U8(*byte));
                 }
                 tokens.push(Token::TupleEnd);
+                #[cfg(not(feature = "serde-bytes"))]
                 assert_tokens(&key.compact(), &tokens);
+                // serde_test only takes static byte tokens, so use msgpack, which encodes byte
+                // strings as bin 8, and deserialize from the local buffer instead
+                #[cfg(feature = "serde-bytes")]
+                {
+                    let encoded = rmp_serde::to_vec(&key).unwrap();
+                    assert_eq!(encoded[..2], [0xc4, 32]);
+                    assert_eq!(encoded[2..], key.0);
+                    assert_eq!(rmp_serde::from_slice::<$type>(&encoded).unwrap(), key);
+                }
+                assert_de_tokens(&key.readable(), &tokens);
+                assert_de_tokens(&<$type>::new([7; 32]).readable(), &[Token::Bytes(&[7; 32])]);
+                assert_de_tokens_error::<Readable<$type>>(
+                    &[Token::Seq { len: Some(2) }, Token::U8(1), Token::U8(2), Token::SeqEnd],
+                    &format!("invalid length 2, expected {}", $mesg),
+                );
             }
         }
     };
@@ -68,16 +105,75 @@ macro_rules! impl_serde {
 macro_rules! impl_hex {
     ($type:ty) => {
         impl $type {
-            /// Parse key from hex.
+            /// Parse key from hex. Both uppercase and lowercase digits are accepted, also mixed,
+            /// with an optional `0x` prefix.
+            #[cfg(feature = "alloc")]
             pub fn from_hex(data: &str) -> Result<Self, ParseError> {
-                let data = hex::decode(data)?;
+                let data = hex::decode(strip_hex_prefix(data.as_bytes()))?;
                 Ok(data.as_slice().try_into()?)
             }
 
             /// Encode key as hex.
+            #[cfg(feature = "alloc")]
             pub fn to_hex(&self) -> String {
                 hex::encode(self.0)
             }
+
+            /// Encode key as uppercase hex.
+            #[cfg(feature = "alloc")]
+            pub fn to_hex_upper(&self) -> String {
+                hex::encode_upper(self.0)
+            }
+
+            /// Length of the key encoded as hex.
+            pub const HEX_LEN: usize =
+                core::mem::size_of::<<$type as core::ops::Deref>::Target>() * 2;
+
+            /// Encode key as hex into the buffer, without allocating.
+            pub fn encode_hex_into<'a>(&self, buffer: &'a mut [u8; Self::HEX_LEN]) -> &'a str {
+                hex::encode_to_slice(&self.0, buffer).expect("buffer has the length of the hex");
+                core::str::from_utf8(buffer).expect("hex is valid UTF-8")
+            }
+
+            /// Encode key as hex into a fixed-capacity string, without allocating.
+            #[cfg(feature = "heapless")]
+            pub fn to_hex_heapless(&self) -> heapless::String<{ Self::HEX_LEN }> {
+                let mut buffer = [0; Self::HEX_LEN];
+                let mut output = heapless::String::new();
+                output
+                    .push_str(self.encode_hex_into(&mut buffer))
+                    .expect("string has the length of the hex");
+                buffer.zeroize();
+                output
+            }
+
+            /// Parse key from hex bytes with an optional `0x` prefix, without allocating.
+            pub fn from_hex_bytes(data: &[u8]) -> Result<Self, ParseError> {
+                let mut buffer = [0; Self::HEX_LEN / 2];
+                let result = hex::decode_to_slice(strip_hex_prefix(data), &mut buffer)
+                    .map_err(ParseError::from)
+                    .and_then(|_| Self::try_from(&buffer[..]));
+                buffer.zeroize();
+                result
+            }
+        }
+
+        paste! {
+            #[cfg(feature = "std")]
+            #[test]
+            fn [<test_ $type:lower _encode_hex_into>]() {
+                let value = <$type>::generate();
+                let mut buffer = [0; <$type>::HEX_LEN];
+                assert_eq!(value.encode_hex_into(&mut buffer), value.to_hex());
+                #[cfg(feature = "heapless")]
+                assert_eq!(value.to_hex_heapless().as_str(), value.to_hex());
+                assert_eq!(<$type>::from_hex_bytes(&buffer).unwrap(), value);
+                assert_eq!(
+                    <$type>::from_hex_bytes(value.to_hex_upper().as_bytes()).unwrap(),
+                    value
+                );
+                assert!(<$type>::from_hex_bytes(&buffer[1..]).is_err());
+            }
         }
     };
 }
@@ -89,7 +185,7 @@ macro_rules! impl_base32 {
             /// Base32 alphabet to use.
             const BASE32_ALPHABET: base32::Alphabet = base32::Alphabet::RFC4648 { padding: true };
 
-            /// Parse key from base32.
+            /// Parse key from base32, in any case and with or without padding.
             pub fn from_base32(data: &str) -> Result<Self, ParseError> {
                 let data =
                     base32::decode(Self::BASE32_ALPHABET, data).ok_or(ParseError::Base32Error)?;
@@ -100,6 +196,54 @@ macro_rules! impl_base32 {
             pub fn to_base32(&self) -> String {
                 base32::encode(Self::BASE32_ALPHABET, &self.0)
             }
+
+            /// Parse key from z-base-32.
+            pub fn from_zbase32(data: &str) -> Result<Self, ParseError> {
+                let data = zbase32_decode(data).ok_or(ParseError::Base32Error)?;
+                Ok(data.as_slice().try_into()?)
+            }
+
+            /// Encode key as z-base-32, which is designed for human transcription.
+            pub fn to_zbase32(&self) -> String {
+                zbase32_encode(&self.0)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "base58")]
+macro_rules! impl_base58 {
+    ($type:ty) => {
+        impl $type {
+            /// Parse key from base58, using the Bitcoin alphabet.
+            pub fn from_base58(data: &str) -> Result<Self, ParseError> {
+                let data = bs58::decode(data).into_vec()?;
+                Ok(data.as_slice().try_into()?)
+            }
+
+            /// Encode key as base58, using the Bitcoin alphabet.
+            pub fn to_base58(&self) -> String {
+                bs58::encode(&self.0).into_string()
+            }
+        }
+    };
+}
+
+#[cfg(feature = "proquint")]
+macro_rules! impl_proquint {
+    ($type:ty) => {
+        impl $type {
+            /// Parse key from proquints, in any case.
+            pub fn from_proquint(data: &str) -> Result<Self, ParseError> {
+                let data = proquint_decode(data).ok_or(ParseError::Proquint)?;
+                data.as_slice().try_into()
+            }
+
+            /// Encode key as proquints, such as `lusab-babad`, which are pronounceable and
+            /// suited for comparing keys verbally.
+            pub fn to_proquint(&self) -> String {
+                proquint_encode(&self.0)
+            }
         }
     };
 }
@@ -109,63 +253,429 @@ macro_rules! impl_base64 {
     ($type:ty) => {
         impl $type {
             /// Parse key from base64.
+            #[cfg(feature = "alloc")]
             pub fn from_base64(data: &str) -> Result<Self, ParseError> {
                 let data = base64::decode(data)?;
                 Ok(data.as_slice().try_into()?)
             }
 
             /// Parse key from base64 with urlsafe encoding.
+            #[cfg(feature = "alloc")]
             pub fn from_base64_urlsafe(data: &str) -> Result<Self, ParseError> {
                 let data = base64::decode_config(data, base64::URL_SAFE)?;
                 Ok(data.as_slice().try_into()?)
             }
 
+            /// Parse key from base64 without trailing padding, accepting both the standard and
+            /// the urlsafe alphabet.
+            #[cfg(feature = "alloc")]
+            pub fn from_base64_unpadded(data: &str) -> Result<Self, ParseError> {
+                let data = base64::decode_config(data, base64::STANDARD_NO_PAD)
+                    .or_else(|_| base64::decode_config(data, base64::URL_SAFE_NO_PAD))?;
+                Ok(data.as_slice().try_into()?)
+            }
+
             /// Encode key as base64.
+            #[cfg(feature = "alloc")]
             pub fn to_base64(&self) -> String {
                 base64::encode(&self.0)
             }
 
             /// Encode key as base64 with urlsafe encoding.
+            #[cfg(feature = "alloc")]
             pub fn to_base64_urlsafe(&self) -> String {
                 base64::encode_config(&self.0, base64::URL_SAFE)
             }
+
+            /// Encode key as base64 without trailing padding.
+            #[cfg(feature = "alloc")]
+            pub fn to_base64_unpadded(&self) -> String {
+                base64::encode_config(&self.0, base64::STANDARD_NO_PAD)
+            }
+
+            /// Length of the key encoded as base64, including padding.
+            pub const BASE64_LEN: usize =
+                core::mem::size_of::<<$type as core::ops::Deref>::Target>().div_ceil(3) * 4;
+
+            /// Encode key as base64 into the buffer, without allocating.
+            pub fn encode_base64_into<'a>(
+                &self,
+                buffer: &'a mut [u8; Self::BASE64_LEN],
+            ) -> &'a str {
+                let len = base64::encode_config_slice(&self.0, base64::STANDARD, buffer);
+                core::str::from_utf8(&buffer[..len]).expect("base64 is valid UTF-8")
+            }
+
+            /// Encode key as base64 into a fixed-capacity string, without allocating.
+            #[cfg(feature = "heapless")]
+            pub fn to_base64_heapless(&self) -> heapless::String<{ Self::BASE64_LEN }> {
+                let mut buffer = [0; Self::BASE64_LEN];
+                let mut output = heapless::String::new();
+                output
+                    .push_str(self.encode_base64_into(&mut buffer))
+                    .expect("string has the length of the base64");
+                buffer.zeroize();
+                output
+            }
+
+            /// Encode key as URL component, which can be placed in the path or the query of a URL
+            /// without escaping. This is urlsafe base64 without padding.
+            #[cfg(feature = "alloc")]
+            pub fn to_url_component(&self) -> String {
+                base64::encode_config(&self.0, base64::URL_SAFE_NO_PAD)
+            }
+
+            /// Parse key from URL component. Besides urlsafe base64, this accepts standard
+            /// base64 which was percent-encoded, or which was not and had `+` turned into spaces
+            /// by query string decoding.
+            #[cfg(feature = "alloc")]
+            pub fn from_url_component(data: &str) -> Result<Self, ParseError> {
+                let mut data = url_component_decode(data);
+                let result = Self::from_base64_unpadded(data.trim_end_matches('='));
+                data.zeroize();
+                result
+            }
+
+            /// Parse key from base64 bytes, without allocating.
+            pub fn from_base64_bytes(data: &[u8]) -> Result<Self, ParseError> {
+                Self::decode_base64_bytes(data, base64::STANDARD)
+            }
+
+            /// Decode base64 with padding into a buffer on the stack, which is cleared afterwards.
+            fn decode_base64_bytes(
+                data: &[u8],
+                config: base64::Config,
+            ) -> Result<Self, ParseError> {
+                if data.len() != Self::BASE64_LEN {
+                    return Err(ParseError::Length);
+                }
+                let mut buffer = [0; Self::BASE64_LEN / 4 * 3];
+                let result = base64::decode_config_slice(data, config, &mut buffer)
+                    .map_err(ParseError::from)
+                    .and_then(|len| Self::try_from(&buffer[..len]));
+                buffer.zeroize();
+                result
+            }
+        }
+
+        paste! {
+            #[cfg(feature = "std")]
+            #[test]
+            fn [<test_ $type:lower _url_component>]() {
+                let value = <$type>::generate();
+                let component = value.to_url_component();
+                assert!(component
+                    .bytes()
+                    .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+                assert_eq!(<$type>::from_url_component(&component).unwrap(), value);
+                let base64 = value.to_base64();
+                assert_eq!(<$type>::from_url_component(&base64).unwrap(), value);
+                let percent = base64
+                    .replace('+', "%2B")
+                    .replace('/', "%2f")
+                    .replace('=', "%3D");
+                assert_eq!(<$type>::from_url_component(&percent).unwrap(), value);
+                let spaces = base64.replace('+', " ");
+                assert_eq!(<$type>::from_url_component(&spaces).unwrap(), value);
+                let padded = value.to_base64_urlsafe();
+                assert_eq!(<$type>::from_url_component(&padded).unwrap(), value);
+                assert!(<$type>::from_url_component(&format!("{}%2", component)).is_err());
+            }
+
+            #[cfg(feature = "std")]
+            #[test]
+            fn [<test_ $type:lower _encode_base64_into>]() {
+                let value = <$type>::generate();
+                let mut buffer = [0; <$type>::BASE64_LEN];
+                assert_eq!(value.encode_base64_into(&mut buffer), value.to_base64());
+                #[cfg(feature = "heapless")]
+                assert_eq!(value.to_base64_heapless().as_str(), value.to_base64());
+                assert_eq!(<$type>::from_base64_bytes(&buffer).unwrap(), value);
+                assert!(matches!(
+                    <$type>::from_base64_bytes(&buffer[4..]),
+                    Err(ParseError::Length)
+                ));
+                buffer[0] = b'!';
+                assert!(matches!(
+                    <$type>::from_base64_bytes(&buffer),
+                    Err(ParseError::Base64(_))
+                ));
+            }
         }
     };
 }
 
-macro_rules! impl_parse {
+macro_rules! impl_encoding {
     ($type:ty) => {
         impl $type {
-            /// Try parsing from string.
-            pub fn parse(data: &str) -> Result<Self, ParseError> {
-                let ret = match data.len() {
+            /// Encode key using the given encoding.
+            pub fn encode(&self, encoding: Encoding) -> String {
+                match encoding {
                     #[cfg(feature = "hex")]
-                    64 => Self::from_hex(data)?,
+                    Encoding::Hex => self.to_hex(),
+                    #[cfg(feature = "hex")]
+                    Encoding::HexUpper => self.to_hex_upper(),
+                    #[cfg(feature = "hex")]
+                    Encoding::Fingerprint => fingerprint_encode(&self.0),
+                    #[cfg(feature = "base64")]
+                    Encoding::Base64 => self.to_base64(),
+                    #[cfg(feature = "base64")]
+                    Encoding::Base64Urlsafe => self.to_base64_urlsafe(),
+                    #[cfg(feature = "base64")]
+                    Encoding::Base64Unpadded => self.to_base64_unpadded(),
+                    #[cfg(feature = "base32")]
+                    Encoding::Base32 => self.to_base32(),
+                    #[cfg(feature = "base32")]
+                    Encoding::ZBase32 => self.to_zbase32(),
+                    #[cfg(feature = "base58")]
+                    Encoding::Base58 => self.to_base58(),
+                    #[cfg(feature = "proquint")]
+                    Encoding::Proquint => self.to_proquint(),
+                }
+            }
+
+            /// Decode key using the given encoding.
+            #[cfg_attr(
+                not(any(
+                    feature = "hex",
+                    feature = "base64",
+                    feature = "base32",
+                    feature = "base58",
+                    feature = "proquint"
+                )),
+                allow(unused_variables)
+            )]
+            pub fn decode(data: &str, encoding: Encoding) -> Result<Self, ParseError> {
+                match encoding {
+                    #[cfg(feature = "hex")]
+                    Encoding::Hex | Encoding::HexUpper => Self::from_hex(data),
+                    #[cfg(feature = "hex")]
+                    Encoding::Fingerprint => Ok(fingerprint_decode(data)?.as_slice().try_into()?),
+                    #[cfg(feature = "base64")]
+                    Encoding::Base64 => Self::from_base64(data),
+                    #[cfg(feature = "base64")]
+                    Encoding::Base64Urlsafe => Self::from_base64_urlsafe(data),
                     #[cfg(feature = "base64")]
-                    44 => Self::from_base64(data).or_else(|_| Self::from_base64_urlsafe(data))?,
+                    Encoding::Base64Unpadded => Self::from_base64_unpadded(data),
                     #[cfg(feature = "base32")]
-                    56 => Self::from_base32(data)?,
-                    _ => Err(ParseError::Length)?,
+                    Encoding::Base32 => Self::from_base32(data),
+                    #[cfg(feature = "base32")]
+                    Encoding::ZBase32 => Self::from_zbase32(data),
+                    #[cfg(feature = "base58")]
+                    Encoding::Base58 => Self::from_base58(data),
+                    #[cfg(feature = "proquint")]
+                    Encoding::Proquint => Self::from_proquint(data),
+                }
+            }
+
+            /// Wrap key so that its [Display](core::fmt::Display) implementation uses the given
+            /// encoding, such as `format!("{}", key.display_as(Encoding::Hex))`.
+            pub fn display_as(&self, encoding: Encoding) -> DisplayAs<'_, Self> {
+                DisplayAs {
+                    key: self,
+                    encoding,
+                }
+            }
+
+            /// Try parsing from string in every enabled encoding, returning the key and the
+            /// encoding it was found in. Strings which decode to different keys in different
+            /// encodings are rejected as ambiguous. Since decoding is not case sensitive for
+            /// every encoding, the detected encoding is the first one from [Encoding::ALL].
+            pub fn parse_detect(data: &str) -> Result<(Self, Encoding), ParseError> {
+                let mut detected: Option<(Self, Encoding)> = None;
+                for &encoding in Encoding::ALL {
+                    if let Ok(key) = Self::decode(data, encoding) {
+                        match &detected {
+                            None => detected = Some((key, encoding)),
+                            Some((other, _)) if *other != key => return Err(ParseError::Ambiguous),
+                            Some(_) => {}
+                        }
+                    }
+                }
+                match detected {
+                    Some(detected) => Ok(detected),
+                    // report why the encoding matching the length failed
+                    None => Err(Self::parse(data).err().unwrap_or(ParseError::Length)),
+                }
+            }
+        }
+
+        impl core::fmt::Display for DisplayAs<'_, $type> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+                f.write_str(&self.key.encode(self.encoding))
+            }
+        }
+
+        paste! {
+            #[cfg(any(
+                feature = "hex",
+                feature = "base64",
+                feature = "base32",
+                feature = "base58",
+                feature = "proquint"
+            ))]
+            #[cfg(feature = "std")]
+            #[test]
+            fn [<test_ $type:lower _encoding>]() {
+                let value = <$type>::generate();
+                for &encoding in Encoding::ALL {
+                    let encoded = value.encode(encoding);
+                    assert_eq!(<$type>::decode(&encoded, encoding).unwrap(), value);
+                    assert_eq!(format!("{}", value.display_as(encoding)), encoded);
+                    match <$type>::parse_detect(&encoded) {
+                        Ok((key, detected)) => {
+                            assert_eq!(key, value);
+                            assert_eq!(<$type>::decode(&encoded, detected).unwrap(), value);
+                        }
+                        Err(ParseError::Ambiguous) => {}
+                        Err(error) => panic!("error detecting {:?}: {}", encoding, error),
+                    }
+                }
+                // compare the bytes, since not every key type is `Copy`
+                #[allow(unused)]
+                let detect = |data: &str| {
+                    <$type>::parse_detect(data).map(|(key, encoding)| (key.0, encoding))
                 };
-                Ok(ret)
+                #[cfg(feature = "hex")]
+                {
+                    let fingerprint = value.encode(Encoding::Fingerprint);
+                    assert_eq!(fingerprint, fingerprint.to_uppercase());
+                    assert_eq!(fingerprint.split(':').count(), value.0.len());
+                    assert_eq!(<$type>::parse(&fingerprint).unwrap(), value);
+                    assert_eq!(
+                        detect(&fingerprint).unwrap(),
+                        (value.0, Encoding::Fingerprint)
+                    );
+                    assert_eq!(
+                        detect(&value.to_hex_upper()).unwrap(),
+                        (value.0, Encoding::Hex)
+                    );
+                }
+                #[cfg(feature = "base64")]
+                assert_eq!(
+                    detect(&value.to_base64()).unwrap(),
+                    (value.0, Encoding::Base64)
+                );
+                assert!(<$type>::parse_detect("").is_err());
+            }
+        }
+    };
+}
+
+macro_rules! impl_parse {
+    ($type:ty) => {
+        impl $type {
+            /// Try parsing from string, detecting the encoding from its length and separators.
+            /// Lowercase strings which are valid z-base-32 and unpadded base32 with different
+            /// results are rejected as ambiguous.
+            /// Base58 has no fixed length, so it is only tried for strings which fail to decode in
+            /// the encoding matching their length. Short base58 strings can be valid unpadded
+            /// base64 as well, these are always read as base64.
+            #[cfg(feature = "alloc")]
+            pub fn parse(data: &str) -> Result<Self, ParseError> {
+                #[allow(unused)]
+                const LEN: usize = core::mem::size_of::<<$type as core::ops::Deref>::Target>();
+                #[allow(unused_mut)]
+                let mut result = Err(ParseError::Length);
+                #[cfg(feature = "hex")]
+                if data.len() == LEN * 2 || data.len() == LEN * 2 + 2 {
+                    result = Self::from_hex(data);
+                }
+                #[cfg(feature = "hex")]
+                if data.len() == LEN * 3 - 1 && data.as_bytes()[2] == b':' {
+                    result = Self::decode(data, Encoding::Fingerprint);
+                }
+                #[cfg(feature = "base64")]
+                if data.len() == LEN.div_ceil(3) * 4 {
+                    result = Self::from_base64(data).or_else(|_| Self::from_base64_urlsafe(data));
+                }
+                #[cfg(feature = "base64")]
+                if data.len() == (LEN * 4).div_ceil(3) {
+                    result = Self::from_base64_unpadded(data);
+                }
+                #[cfg(feature = "base32")]
+                if data.len() == LEN.div_ceil(5) * 8 {
+                    result = Self::from_base32(data);
+                }
+                #[cfg(feature = "base32")]
+                if data.len() == (LEN * 8).div_ceil(5) {
+                    // z-base-32 and unpadded base32 have the same length, and lowercase strings
+                    // using only the characters common to both alphabets are valid in both
+                    result = match (Self::from_zbase32(data), Self::from_base32(data)) {
+                        (Ok(zbase32), Ok(base32)) if zbase32 != base32 => {
+                            Err(ParseError::Ambiguous)
+                        }
+                        (Ok(key), _) | (_, Ok(key)) => Ok(key),
+                        (Err(error), _) => Err(error),
+                    };
+                }
+                #[cfg(feature = "proquint")]
+                if data.len() == LEN / 2 * 6 - 1 && data.as_bytes()[5] == b'-' {
+                    result = Self::from_proquint(data);
+                }
+                // base58 never contains `+`, `/` or `=`, so no valid base64 is read as base58, and
+                // it needs at most 1.37 characters per byte, longer strings are not decoded since
+                // decoding takes quadratic time
+                #[cfg(feature = "base58")]
+                if result.is_err() && data.len() <= (LEN * 137).div_ceil(100) {
+                    result = result.or_else(|error| Self::from_base58(data).map_err(|_| error));
+                }
+                result
+            }
+
+            /// Try parsing from string like [Self::parse], but ignoring surrounding whitespace and
+            /// line breaks inside the key, as found in keys pasted from terminals or read from
+            /// files such as the output of `wg genkey`.
+            #[cfg(feature = "alloc")]
+            pub fn parse_lenient(data: &str) -> Result<Self, ParseError> {
+                let data = data.trim();
+                if !data.contains(['\r', '\n']) {
+                    return Self::parse(data);
+                }
+                let mut joined: String = data.lines().map(str::trim).collect();
+                let result = Self::parse(&joined);
+                joined.zeroize();
+                result
+            }
+
+            /// Try parsing from bytes without allocating, detecting the encoding from the length.
+            /// Only hex and base64 with padding are supported, since the other encodings need
+            /// temporary allocations.
+            #[cfg_attr(not(any(feature = "hex", feature = "base64")), allow(unused_variables))]
+            pub fn parse_bytes(data: &[u8]) -> Result<Self, ParseError> {
+                #[cfg(feature = "hex")]
+                if data.len() == Self::HEX_LEN || data.len() == Self::HEX_LEN + 2 {
+                    return Self::from_hex_bytes(data);
+                }
+                #[cfg(feature = "base64")]
+                if data.len() == Self::BASE64_LEN {
+                    return Self::from_base64_bytes(data)
+                        .or_else(|_| Self::decode_base64_bytes(data, base64::URL_SAFE));
+                }
+                Err(ParseError::Length)
             }
         }
 
         impl TryFrom<&str> for $type {
             type Error = ParseError;
             fn try_from(value: &str) -> Result<Self, Self::Error> {
-                <$type>::parse(value)
+                value.parse()
             }
         }
 
         impl FromStr for $type {
             type Err = ParseError;
             fn from_str(value: &str) -> Result<Self, Self::Err> {
-                <$type>::parse(value)
+                #[cfg(feature = "alloc")]
+                return <$type>::parse(value);
+                // without an allocator, only the encodings supported by parse_bytes are parsed
+                #[cfg(not(feature = "alloc"))]
+                return <$type>::parse_bytes(value.as_bytes());
             }
         }
 
         paste! {
+            #[cfg(feature = "std")]
             #[test]
             fn [<test_ $type:lower _parse>]() {
                 let value = <$type>::generate();
@@ -173,6 +683,26 @@ macro_rules! impl_parse {
                 {
                     let value_hex = value.to_hex();
                     assert_eq!(<$type>::parse(&value_hex).unwrap(), value);
+                    let value_hex_upper = value.to_hex_upper();
+                    assert_eq!(value_hex_upper, value_hex.to_uppercase());
+                    assert_eq!(<$type>::parse(&value_hex_upper).unwrap(), value);
+                    let value_hex_mixed: String = value_hex
+                        .chars()
+                        .enumerate()
+                        .map(|(i, c)| if i % 2 == 0 { c.to_ascii_uppercase() } else { c })
+                        .collect();
+                    assert_eq!(<$type>::parse(&value_hex_mixed).unwrap(), value);
+                    for prefix in ["0x", "0X"] {
+                        let value_hex_prefixed = format!("{}{}", prefix, value_hex);
+                        assert_eq!(<$type>::parse(&value_hex_prefixed).unwrap(), value);
+                        assert_eq!(
+                            <$type>::parse_bytes(value_hex_prefixed.as_bytes()).unwrap(),
+                            value
+                        );
+                    }
+                    assert!(<$type>::from_hex(&format!("0x0x{}", value_hex)).is_err());
+                    assert!(<$type>::from_hex(&format!("x{}", value_hex)).is_err());
+                    assert_eq!(<$type>::parse_bytes(value_hex.as_bytes()).unwrap(), value);
                 }
                 #[cfg(feature = "base64")]
                 {
@@ -180,11 +710,69 @@ macro_rules! impl_parse {
                     assert_eq!(<$type>::parse(&value_base64).unwrap(), value);
                     let value_base64_url = value.to_base64_urlsafe();
                     assert_eq!(<$type>::parse(&value_base64_url).unwrap(), value);
+                    let value_base64_unpadded = value.to_base64_unpadded();
+                    let value_base64_url_unpadded = value_base64_url.trim_end_matches('=');
+                    for unpadded in [value_base64_unpadded.as_str(), value_base64_url_unpadded] {
+                        assert_eq!(<$type>::parse(unpadded).unwrap(), value);
+                    }
+                    assert_eq!(<$type>::parse_bytes(value_base64.as_bytes()).unwrap(), value);
+                    assert!(<$type>::parse(&format!("{}\n", value_base64)).is_err());
+                    assert_eq!(
+                        <$type>::parse_lenient(&format!(" {}\n", value_base64)).unwrap(),
+                        value
+                    );
+                    let (start, end) = value_base64.split_at(value_base64.len() / 2);
+                    assert_eq!(
+                        <$type>::parse_lenient(&format!("{}\r\n  {}\n", start, end)).unwrap(),
+                        value
+                    );
+                    assert!(<$type>::parse_lenient(&format!("{} {}", start, end)).is_err());
+                    assert_eq!(<$type>::parse_bytes(value_base64_url.as_bytes()).unwrap(), value);
+                    assert!(matches!(
+                        <$type>::parse_bytes(value_base64_unpadded.as_bytes()),
+                        Err(ParseError::Length)
+                    ));
                 }
                 #[cfg(feature = "base32")]
                 {
                     let value_base32 = value.to_base32();
                     assert_eq!(<$type>::parse(&value_base32).unwrap(), value);
+                    let value_base32_lower = value_base32.to_lowercase();
+                    assert_eq!(<$type>::parse(&value_base32_lower).unwrap(), value);
+                    // z-base-32 and unpadded base32 have the same length, uppercase is never
+                    // z-base-32
+                    let value_base32_unpadded = value_base32.trim_end_matches('=');
+                    assert_eq!(<$type>::parse(value_base32_unpadded).unwrap(), value);
+                    let value_zbase32 = value.to_zbase32();
+                    for data in [value_base32_lower.trim_end_matches('='), &value_zbase32] {
+                        match <$type>::parse(data) {
+                            Ok(parsed) => assert_eq!(parsed, value),
+                            Err(ParseError::Ambiguous) => {}
+                            Err(error) => panic!("error parsing base32: {}", error),
+                        }
+                    }
+                    assert_eq!(<$type>::from_base32(value_base32_unpadded).unwrap(), value);
+                }
+                #[cfg(feature = "proquint")]
+                {
+                    let value_proquint = value.to_proquint();
+                    assert_eq!(<$type>::parse(&value_proquint).unwrap(), value);
+                    assert_eq!(<$type>::parse(&value_proquint.to_uppercase()).unwrap(), value);
+                }
+                #[cfg(feature = "base58")]
+                {
+                    let value_base58 = value.to_base58();
+                    assert_eq!(<$type>::from_base58(&value_base58).unwrap(), value);
+                    assert_eq!(
+                        <$type>::decode(&value_base58, Encoding::Base58).unwrap(),
+                        value
+                    );
+                    // short base58 strings can be valid unpadded base64 as well
+                    #[cfg(feature = "base64")]
+                    let expected = <$type>::from_base64_unpadded(&value_base58).unwrap_or(value);
+                    #[cfg(not(feature = "base64"))]
+                    let expected = value;
+                    assert_eq!(<$type>::parse(&value_base58).unwrap(), expected);
                 }
             }
 
@@ -203,6 +791,551 @@ macro_rules! impl_parse {
     };
 }
 
+#[cfg(feature = "schema")]
+macro_rules! impl_schema {
+    ($type:ty, $format:literal, $mesg:literal, $example:literal) => {
+        impl JsonSchema for $type {
+            fn schema_name() -> String {
+                stringify!($type).into()
+            }
+
+            fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+                use schemars::schema::{InstanceType, Metadata, SchemaObject};
+                // keys are written as base64 in human readable formats when it is enabled
+                #[cfg(feature = "base64")]
+                let (string, examples) = (
+                    Some(Box::new(schemars::schema::StringValidation {
+                        min_length: Some(<$type>::BASE64_LEN as u32),
+                        max_length: Some(<$type>::BASE64_LEN as u32),
+                        pattern: Some(base64_pattern(std::mem::size_of::<
+                            <$type as std::ops::Deref>::Target,
+                        >())),
+                    })),
+                    vec![$example.into()],
+                );
+                #[cfg(not(feature = "base64"))]
+                let (string, examples) = (None, Vec::new());
+                let schema = SchemaObject {
+                    instance_type: Some(InstanceType::String.into()),
+                    format: Some($format.into()),
+                    string,
+                    metadata: Some(Box::new(Metadata {
+                        description: Some($mesg.into()),
+                        examples,
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                };
+                schema.into()
+            }
+        }
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _schema>]() {
+                let schema = schemars::schema_for!($type);
+                let schema = serde_json::to_value(&schema).unwrap();
+                assert_eq!(schema["type"], "string");
+                assert_eq!(schema["format"], $format);
+                assert_eq!(schema["description"], $mesg);
+                #[cfg(feature = "base64")]
+                {
+                    assert_eq!(schema["minLength"], 44);
+                    assert_eq!(schema["maxLength"], 44);
+                    assert_eq!(schema["examples"][0], $example);
+                    assert!(<$type>::from_base64($example).is_ok());
+                    let pattern = schema["pattern"].as_str().unwrap();
+                    assert_eq!(pattern, "^[A-Za-z0-9+/]{42}[AEIMQUYcgkosw048]=$");
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "utoipa")]
+macro_rules! impl_utoipa {
+    ($type:ty, $format:literal, $mesg:literal, $example:literal) => {
+        impl utoipa::PartialSchema for $type {
+            fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+                use utoipa::openapi::schema::{ObjectBuilder, SchemaFormat, Type};
+                let schema = ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .format(Some(SchemaFormat::Custom($format.into())))
+                    .description(Some($mesg));
+                // keys are written as base64 in human readable formats when it is enabled
+                #[cfg(feature = "base64")]
+                let schema = schema
+                    .min_length(Some(<$type>::BASE64_LEN))
+                    .max_length(Some(<$type>::BASE64_LEN))
+                    .pattern(Some(base64_pattern(std::mem::size_of::<
+                        <$type as std::ops::Deref>::Target,
+                    >())))
+                    .examples([$example]);
+                schema.into()
+            }
+        }
+
+        impl utoipa::ToSchema for $type {}
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _utoipa>]() {
+                use utoipa::{PartialSchema, ToSchema};
+                assert_eq!(<$type>::name(), stringify!($type));
+                let schema = serde_json::to_value(<$type>::schema()).unwrap();
+                assert_eq!(schema["type"], "string");
+                assert_eq!(schema["format"], $format);
+                assert_eq!(schema["description"], $mesg);
+                #[cfg(feature = "base64")]
+                {
+                    assert_eq!(schema["minLength"], 44);
+                    assert_eq!(schema["maxLength"], 44);
+                    assert_eq!(schema["examples"][0], $example);
+                    let pattern = schema["pattern"].as_str().unwrap();
+                    assert_eq!(pattern, "^[A-Za-z0-9+/]{42}[AEIMQUYcgkosw048]=$");
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "poem-openapi")]
+macro_rules! impl_poem_openapi {
+    ($type:ty, $format:literal, $mesg:literal, $example:literal) => {
+        impl poem_openapi::types::Type for $type {
+            const IS_REQUIRED: bool = true;
+
+            type RawValueType = Self;
+
+            type RawElementValueType = Self;
+
+            fn name() -> std::borrow::Cow<'static, str> {
+                concat!("string_", $format).into()
+            }
+
+            fn schema_ref() -> poem_openapi::registry::MetaSchemaRef {
+                use poem_openapi::registry::{MetaSchema, MetaSchemaRef};
+                let schema = MetaSchema {
+                    description: Some($mesg),
+                    ..MetaSchema::new_with_format("string", $format)
+                };
+                // keys are written as base64 in human readable formats when it is enabled
+                #[cfg(feature = "base64")]
+                let schema = MetaSchema {
+                    min_length: Some(<$type>::BASE64_LEN),
+                    max_length: Some(<$type>::BASE64_LEN),
+                    pattern: Some(base64_pattern(std::mem::size_of::<
+                        <$type as std::ops::Deref>::Target,
+                    >())),
+                    example: Some($example.into()),
+                    ..schema
+                };
+                MetaSchemaRef::Inline(Box::new(schema))
+            }
+
+            fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+                Some(self)
+            }
+
+            fn raw_element_iter<'a>(
+                &'a self,
+            ) -> Box<dyn Iterator<Item = &'a Self::RawElementValueType> + 'a> {
+                Box::new(self.as_raw_value().into_iter())
+            }
+        }
+
+        impl poem_openapi::types::ParseFromJSON for $type {
+            fn parse_from_json(
+                value: Option<serde_json::Value>,
+            ) -> poem_openapi::types::ParseResult<Self> {
+                use poem_openapi::types::ParseError;
+                match value.unwrap_or_default() {
+                    serde_json::Value::String(mut value) => {
+                        let result = <$type>::from_str(&value).map_err(ParseError::custom);
+                        value.zeroize();
+                        result
+                    }
+                    value => Err(ParseError::expected_type(value)),
+                }
+            }
+        }
+
+        impl poem_openapi::types::ParseFromParameter for $type {
+            fn parse_from_parameter(value: &str) -> poem_openapi::types::ParseResult<Self> {
+                <$type>::from_str(value).map_err(poem_openapi::types::ParseError::custom)
+            }
+        }
+
+        impl poem_openapi::types::ToJSON for $type {
+            fn to_json(&self) -> Option<serde_json::Value> {
+                Some(serde_json::Value::String(self.to_string()))
+            }
+        }
+
+        paste! {
+            #[cfg(any(
+                feature = "hex",
+                feature = "base64",
+                feature = "base32",
+                feature = "base58",
+                feature = "proquint"
+            ))]
+            #[test]
+            fn [<test_ $type:lower _poem_openapi>]() {
+                use poem_openapi::registry::MetaSchemaRef;
+                use poem_openapi::types::{ParseFromJSON, ParseFromParameter, ToJSON, Type};
+                let MetaSchemaRef::Inline(schema) = <$type>::schema_ref() else {
+                    panic!("schema is not inline");
+                };
+                assert_eq!(schema.ty, "string");
+                assert_eq!(schema.format, Some($format));
+                assert_eq!(schema.description, Some($mesg));
+                #[cfg(feature = "base64")]
+                {
+                    assert_eq!(schema.min_length, Some(44));
+                    assert_eq!(schema.max_length, Some(44));
+                    assert_eq!(schema.example, Some($example.into()));
+                }
+
+                let value = <$type>::generate();
+                let json = value.to_json();
+                assert_eq!(json, Some(serde_json::Value::String(value.to_string())));
+                assert_eq!(<$type>::parse_from_json(json).unwrap(), value);
+                assert_eq!(<$type>::parse_from_parameter(&value.to_string()).unwrap(), value);
+                assert!(<$type>::parse_from_json(Some(serde_json::json!(1))).is_err());
+                assert!(<$type>::parse_from_json(Some("abc".into())).is_err());
+                assert!(<$type>::parse_from_json(None).is_err());
+                assert!(<$type>::parse_from_parameter("abc").is_err());
+            }
+        }
+    };
+}
+
+#[cfg(feature = "rkyv")]
+macro_rules! impl_rkyv {
+    ($type:ty) => {
+        // keys are plain byte arrays, so the archived form is the key itself and can be used
+        // directly from the archive, without copying.
+        impl rkyv::Archive for $type {
+            type Archived = $type;
+            type Resolver = ();
+
+            #[inline]
+            unsafe fn resolve(&self, _pos: usize, _resolver: (), out: *mut Self::Archived) {
+                out.write(<$type>::new(self.0));
+            }
+        }
+
+        impl<S: rkyv::Fallible + ?Sized> rkyv::Serialize<S> for $type {
+            #[inline]
+            fn serialize(&self, _serializer: &mut S) -> Result<(), S::Error> {
+                Ok(())
+            }
+        }
+
+        impl<D: rkyv::Fallible + ?Sized> rkyv::Deserialize<$type, D> for $type {
+            #[inline]
+            fn deserialize(&self, _deserializer: &mut D) -> Result<$type, D::Error> {
+                Ok(<$type>::new(self.0))
+            }
+        }
+
+        // every byte pattern is a valid key.
+        impl<C: ?Sized> rkyv::bytecheck::CheckBytes<C> for $type {
+            type Error = std::convert::Infallible;
+
+            #[inline]
+            unsafe fn check_bytes<'a>(
+                value: *const Self,
+                _context: &mut C,
+            ) -> Result<&'a Self, Self::Error> {
+                Ok(&*value)
+            }
+        }
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _rkyv>]() {
+                use rkyv::Deserialize;
+                let value = <$type>::generate();
+                let bytes = rkyv::to_bytes::<_, 64>(&vec![value, value]).unwrap();
+                let archived = rkyv::check_archived_root::<Vec<$type>>(&bytes).unwrap();
+                assert_eq!(archived.as_slice(), &[value, value]);
+                let deserialized: Vec<$type> =
+                    archived.deserialize(&mut rkyv::Infallible).unwrap();
+                assert_eq!(deserialized, [value, value]);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "arbitrary")]
+macro_rules! impl_arbitrary {
+    ($type:ty) => {
+        impl<'a> arbitrary::Arbitrary<'a> for $type {
+            fn arbitrary(data: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+                Ok(<$type>::new(data.arbitrary()?))
+            }
+
+            fn size_hint(depth: usize) -> (usize, Option<usize>) {
+                <<$type as std::ops::Deref>::Target as arbitrary::Arbitrary>::size_hint(depth)
+            }
+        }
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _arbitrary>]() {
+                use arbitrary::{Arbitrary, Unstructured};
+                let data: Vec<u8> = (0..64).collect();
+                let mut data = Unstructured::new(&data);
+                let first = <$type>::arbitrary(&mut data).unwrap();
+                let second = <$type>::arbitrary(&mut data).unwrap();
+                assert_eq!(first[..], (0..32).collect::<Vec<u8>>()[..]);
+                assert_eq!(second[..], (32..64).collect::<Vec<u8>>()[..]);
+                assert_eq!(<$type>::size_hint(0), (32, Some(32)));
+            }
+        }
+    };
+}
+
+#[cfg(feature = "defmt")]
+macro_rules! impl_defmt {
+    ($type:ty, redacted) => {
+        impl defmt::Format for $type {
+            /// Format only the type name, since logs are not kept secret.
+            fn format(&self, f: defmt::Formatter<'_>) {
+                defmt::write!(f, "{=str}(redacted)", stringify!($type));
+            }
+        }
+    };
+    ($type:ty) => {
+        impl defmt::Format for $type {
+            /// Format the first four bytes in hex, which is enough to tell keys apart in logs.
+            fn format(&self, f: defmt::Formatter<'_>) {
+                defmt::write!(
+                    f,
+                    "{=str}({=u8:02x}{=u8:02x}{=u8:02x}{=u8:02x}...)",
+                    stringify!($type),
+                    self.0[0],
+                    self.0[1],
+                    self.0[2],
+                    self.0[3]
+                );
+            }
+        }
+    };
+}
+
+#[cfg(feature = "postgres")]
+macro_rules! impl_postgres {
+    ($type:ty) => {
+        impl postgres_types::ToSql for $type {
+            fn to_sql(
+                &self,
+                ty: &postgres_types::Type,
+                out: &mut bytes::BytesMut,
+            ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+                <&[u8] as postgres_types::ToSql>::to_sql(&&self.0[..], ty, out)
+            }
+
+            fn accepts(ty: &postgres_types::Type) -> bool {
+                <&[u8] as postgres_types::ToSql>::accepts(ty)
+            }
+
+            postgres_types::to_sql_checked!();
+        }
+
+        impl<'a> postgres_types::FromSql<'a> for $type {
+            fn from_sql(
+                _ty: &postgres_types::Type,
+                raw: &'a [u8],
+            ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+                Ok(<$type>::try_from(raw)?)
+            }
+
+            fn accepts(ty: &postgres_types::Type) -> bool {
+                <&[u8] as postgres_types::FromSql>::accepts(ty)
+            }
+        }
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _postgres>]() {
+                use postgres_types::{FromSql, ToSql, Type};
+                let value = <$type>::new([7; 32]);
+                let mut buffer = bytes::BytesMut::new();
+                value.to_sql_checked(&Type::BYTEA, &mut buffer).unwrap();
+                assert_eq!(&buffer[..], &[7; 32]);
+                assert_eq!(<$type>::from_sql(&Type::BYTEA, &buffer).unwrap(), value);
+                assert!(<$type>::from_sql(&Type::BYTEA, &buffer[1..]).is_err());
+                assert!(value.to_sql_checked(&Type::TEXT, &mut buffer).is_err());
+                assert!(!<$type as FromSql>::accepts(&Type::TEXT));
+            }
+        }
+    };
+}
+
+#[cfg(feature = "redis")]
+macro_rules! impl_redis {
+    ($type:ty) => {
+        impl redis::ToRedisArgs for $type {
+            fn write_redis_args<W: ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+                out.write_arg(&self.0);
+            }
+        }
+
+        impl redis::FromRedisValue for $type {
+            fn from_redis_value(value: &redis::Value) -> redis::RedisResult<Self> {
+                const LEN: usize = std::mem::size_of::<<$type as std::ops::Deref>::Target>();
+                let result = match value {
+                    redis::Value::BulkString(data) if data.len() == LEN => {
+                        <$type>::try_from(&data[..])
+                    }
+                    redis::Value::BulkString(data) => std::str::from_utf8(data)
+                        .map_err(|_| ParseError::Length)
+                        .and_then(<$type>::parse),
+                    redis::Value::SimpleString(data) => <$type>::parse(data),
+                    _ => {
+                        return Err(redis::RedisError::from((
+                            redis::ErrorKind::TypeError,
+                            "Response was of incompatible type",
+                            format!("{:?} (response was {:?})", stringify!($type), value),
+                        )))
+                    }
+                };
+                result.map_err(|error| {
+                    redis::RedisError::from((
+                        redis::ErrorKind::TypeError,
+                        "Response is not a valid key",
+                        error.to_string(),
+                    ))
+                })
+            }
+        }
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _redis>]() {
+                use redis::{FromRedisValue, ToRedisArgs, Value};
+                let value = <$type>::new([7; 32]);
+                let args = value.to_redis_args();
+                assert_eq!(args, vec![vec![7; 32]]);
+                let data = Value::BulkString(args[0].clone());
+                assert_eq!(<$type>::from_redis_value(&data).unwrap(), value);
+                assert!(<$type>::from_redis_value(&Value::BulkString(vec![7; 31])).is_err());
+                assert!(<$type>::from_redis_value(&Value::Int(7)).is_err());
+                assert!(<$type>::from_redis_value(&Value::Nil).is_err());
+                assert!(Option::<$type>::from_redis_value(&Value::Nil).unwrap().is_none());
+                #[cfg(feature = "base64")]
+                {
+                    let encoded = Value::BulkString(value.to_base64().into_bytes());
+                    assert_eq!(<$type>::from_redis_value(&encoded).unwrap(), value);
+                    let encoded = Value::SimpleString(value.to_base64());
+                    assert_eq!(<$type>::from_redis_value(&encoded).unwrap(), value);
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "sea-orm")]
+macro_rules! impl_sea_orm {
+    ($type:ty) => {
+        impl From<$type> for sea_orm::Value {
+            fn from(key: $type) -> Self {
+                sea_orm::Value::Bytes(Some(Box::new(key.to_vec())))
+            }
+        }
+
+        impl sea_orm::sea_query::Nullable for $type {
+            fn null() -> sea_orm::Value {
+                sea_orm::Value::Bytes(None)
+            }
+        }
+
+        impl sea_orm::sea_query::ValueType for $type {
+            fn try_from(
+                value: sea_orm::Value,
+            ) -> Result<Self, sea_orm::sea_query::ValueTypeErr> {
+                match value {
+                    sea_orm::Value::Bytes(Some(mut data)) => {
+                        let result = <$type as TryFrom<&[u8]>>::try_from(&data[..]);
+                        zeroize::Zeroize::zeroize(&mut *data);
+                        result.map_err(|_| sea_orm::sea_query::ValueTypeErr)
+                    }
+                    _ => Err(sea_orm::sea_query::ValueTypeErr),
+                }
+            }
+
+            fn type_name() -> String {
+                stringify!($type).to_string()
+            }
+
+            fn array_type() -> sea_orm::sea_query::ArrayType {
+                sea_orm::sea_query::ArrayType::Bytes
+            }
+
+            fn column_type() -> sea_orm::sea_query::ColumnType {
+                const LEN: usize = std::mem::size_of::<<$type as std::ops::Deref>::Target>();
+                sea_orm::sea_query::ColumnType::Binary(LEN as u32)
+            }
+        }
+
+        impl sea_orm::TryGetable for $type {
+            fn try_get_by<I: sea_orm::ColIdx>(
+                res: &sea_orm::QueryResult,
+                index: I,
+            ) -> Result<Self, sea_orm::TryGetError> {
+                let mut data = <Vec<u8> as sea_orm::TryGetable>::try_get_by(res, index)?;
+                let result = <$type as TryFrom<&[u8]>>::try_from(&data[..]);
+                zeroize::Zeroize::zeroize(&mut data);
+                result.map_err(|error| {
+                    sea_orm::TryGetError::DbErr(sea_orm::DbErr::Type(format!(
+                        "{}: {}",
+                        stringify!($type),
+                        error
+                    )))
+                })
+            }
+        }
+
+        impl sea_orm::TryFromU64 for $type {
+            fn try_from_u64(_: u64) -> Result<Self, sea_orm::DbErr> {
+                Err(sea_orm::DbErr::ConvertFromU64(stringify!($type)))
+            }
+        }
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _sea_orm>]() {
+                use sea_orm::sea_query::{ColumnType, Nullable, ValueType};
+                use sea_orm::{ConnectionTrait, DbBackend, MockDatabase, Statement, Value};
+                let value = <$type>::new([7; 32]);
+                let column: Value = value.into();
+                assert_eq!(column, Value::Bytes(Some(Box::new(vec![7; 32]))));
+                assert_eq!(<$type as ValueType>::try_from(column.clone()).unwrap(), value);
+                let short = Value::Bytes(Some(Box::new(vec![7; 31])));
+                assert!(<$type as ValueType>::try_from(short.clone()).is_err());
+                assert!(<$type as ValueType>::try_from(<$type>::null()).is_err());
+                let null = <Option<$type> as ValueType>::try_from(<$type>::null());
+                assert!(null.unwrap().is_none());
+                assert!(matches!(<$type>::column_type(), ColumnType::Binary(_)));
+
+                let db = MockDatabase::new(DbBackend::Postgres)
+                    .append_query_results([[
+                        std::collections::BTreeMap::from([("key", column)]),
+                        std::collections::BTreeMap::from([("key", short)]),
+                    ]])
+                    .into_connection();
+                let statement = Statement::from_string(DbBackend::Postgres, "SELECT key");
+                let rows = crate::block_on(db.query_all(statement)).unwrap();
+                assert_eq!(rows[0].try_get::<$type>("", "key").unwrap(), value);
+                assert!(rows[1].try_get::<$type>("", "key").is_err());
+            }
+        }
+    };
+}
+
 #[cfg(feature = "rocket")]
 macro_rules! impl_rocket {
     ($type:ty) => {
@@ -213,6 +1346,42 @@ macro_rules! impl_rocket {
                 <$type>::parse(param)
             }
         }
+
+        impl<'v> rocket::form::FromFormField<'v> for $type {
+            fn from_value(field: rocket::form::ValueField<'v>) -> rocket::form::Result<'v, Self> {
+                <$type>::parse(field.value)
+                    .map_err(|error| rocket::form::Error::validation(error.to_string()).into())
+            }
+        }
+
+        /// Written as urlsafe base64 without padding if the `base64` feature is enabled, so
+        /// that no escaping is needed. Otherwise, the default encoding is percent-encoded.
+        impl<P: rocket::http::uri::fmt::Part> rocket::http::uri::fmt::UriDisplay<P> for $type {
+            fn fmt(&self, f: &mut rocket::http::uri::fmt::Formatter<'_, P>) -> fmt::Result {
+                #[cfg(feature = "base64")]
+                return f.write_value(self.to_url_component().as_str());
+                #[cfg(not(feature = "base64"))]
+                return f.write_value(self.to_string().as_str());
+            }
+        }
+
+        rocket::http::impl_from_uri_param_identity!($type);
+
+        paste! {
+            #[cfg(feature = "base64")]
+            #[test]
+            fn [<test_ $type:lower _uri_display>]() {
+                use rocket::http::uri::fmt::{Path, Query, UriDisplay};
+                const LEN: usize = std::mem::size_of::<<$type as std::ops::Deref>::Target>();
+                let value = <$type>::new([0xfb; LEN]);
+                let path = (&value as &dyn UriDisplay<Path>).to_string();
+                let query = (&value as &dyn UriDisplay<Query>).to_string();
+                assert_eq!(path, value.to_url_component());
+                assert_eq!(query, value.to_url_component());
+                assert!(path.contains('-') && path.contains('_'));
+                assert_eq!(<$type>::from_param(&path).unwrap(), value);
+            }
+        }
     };
 }
 
@@ -221,7 +1390,7 @@ macro_rules! impl_new {
         impl $type {
             /// Create new from existing data. Warning: this function does not check
             /// if the passed data is valid.
-            pub fn new(data: [u8; $len]) -> Self {
+            pub const fn new(data: [u8; $len]) -> Self {
                 Self(data)
             }
         }
@@ -230,7 +1399,7 @@ macro_rules! impl_new {
 
 macro_rules! impl_deref {
     ($type:ty, $len:expr) => {
-        impl std::ops::Deref for $type {
+        impl core::ops::Deref for $type {
             type Target = [u8; $len];
             fn deref(&self) -> &Self::Target {
                 &self.0
@@ -241,25 +1410,79 @@ macro_rules! impl_deref {
 
 macro_rules! impl_display {
     ($type:ty) => {
-        impl std::fmt::Display for $type {
+        impl core::fmt::Display for $type {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
                 #[cfg(feature = "base64")]
-                return write!(f, "{}", self.to_base64());
+                return f.write_str(self.encode_base64_into(&mut [0; Self::BASE64_LEN]));
                 #[cfg(all(not(feature = "base64"), feature = "hex"))]
-                return write!(f, "{}", self.to_hex());
+                return f.write_str(self.encode_hex_into(&mut [0; Self::HEX_LEN]));
                 #[cfg(all(not(feature = "base64"), not(feature = "hex"), feature = "base32"))]
                 return write!(f, "{}", self.to_base32());
                 #[cfg(all(
                     not(feature = "base64"),
                     not(feature = "hex"),
-                    not(feature = "base32")
+                    not(feature = "base32"),
+                    feature = "base58"
+                ))]
+                return write!(f, "{}", self.to_base58());
+                #[cfg(all(
+                    not(feature = "base64"),
+                    not(feature = "hex"),
+                    not(feature = "base32"),
+                    not(feature = "base58"),
+                    feature = "proquint"
                 ))]
-                return unimplemented!();
+                return write!(f, "{}", self.to_proquint());
+                #[cfg(all(
+                    not(feature = "base64"),
+                    not(feature = "hex"),
+                    not(feature = "base32"),
+                    not(feature = "base58"),
+                    not(feature = "proquint")
+                ))]
+                return self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte));
+            }
+        }
+
+        impl core::fmt::LowerHex for $type {
+            /// Format key as lowercase hex, with `0x` prefix in the alternate form (`{:#x}`).
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+                if f.alternate() {
+                    f.write_str("0x")?;
+                }
+                self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+        }
+
+        impl core::fmt::UpperHex for $type {
+            /// Format key as uppercase hex, with `0x` prefix in the alternate form (`{:#X}`).
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+                if f.alternate() {
+                    f.write_str("0x")?;
+                }
+                self.0.iter().try_for_each(|byte| write!(f, "{:02X}", byte))
+            }
+        }
+
+        impl core::fmt::Binary for $type {
+            /// Format key as binary, with `0b` prefix in the alternate form (`{:#b}`).
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+                if f.alternate() {
+                    f.write_str("0b")?;
+                }
+                self.0.iter().try_for_each(|byte| write!(f, "{:08b}", byte))
             }
         }
 
         paste! {
-            #[cfg(any(feature = "base64", feature = "hex", feature = "base32"))]
+            #[cfg(any(
+                feature = "base64",
+                feature = "hex",
+                feature = "base32",
+                feature = "base58",
+                feature = "proquint"
+            ))]
+            #[cfg(feature = "std")]
             #[test]
             fn [<test_ $type:lower _display>]() {
                 let value = <$type>::generate();
@@ -267,6 +1490,207 @@ macro_rules! impl_display {
                 let parsed = <$type>::parse(&display).unwrap();
                 assert_eq!(value, parsed);
             }
+
+            #[cfg(not(any(
+                feature = "base64",
+                feature = "hex",
+                feature = "base32",
+                feature = "base58",
+                feature = "proquint"
+            )))]
+            #[test]
+            fn [<test_ $type:lower _display_hex>]() {
+                let value = <$type>::new(core::array::from_fn(|index| index as u8));
+                let display = value.to_string();
+                assert!(display.starts_with("000102"));
+                assert_eq!(display, format!("{:x}", value));
+            }
+
+            #[cfg(feature = "std")]
+            #[test]
+            fn [<test_ $type:lower _format_hex>]() {
+                let value = <$type>::generate();
+                let lower = format!("{:x}", value);
+                assert_eq!(lower.len(), value.0.len() * 2);
+                assert_eq!(&lower[..2], &format!("{:02x}", value.0[0]));
+                assert_eq!(format!("{:X}", value), lower.to_uppercase());
+                assert_eq!(format!("{:#x}", value), format!("0x{}", lower));
+                assert_eq!(format!("{:#X}", value), format!("0x{}", lower.to_uppercase()));
+                #[cfg(feature = "hex")]
+                assert_eq!(lower, value.to_hex());
+                let binary = format!("{:b}", value);
+                assert_eq!(binary.len(), value.0.len() * 8);
+                assert_eq!(&binary[..8], &format!("{:08b}", value.0[0]));
+                assert!(format!("{:#b}", value).starts_with("0b"));
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+macro_rules! impl_systemd {
+    ($type:ty) => {
+        impl $type {
+            /// Load key from a systemd credential, as passed to the service using
+            /// `LoadCredential=` or `SetCredential=`. Surrounding whitespace is ignored.
+            pub fn from_systemd_credential(name: &str) -> Result<Self, LoadError> {
+                let directory = std::env::var_os(CREDENTIALS_DIRECTORY);
+                Self::from_credentials_directory(directory.as_deref(), name)
+            }
+
+            fn from_credentials_directory(
+                directory: Option<&std::ffi::OsStr>,
+                name: &str,
+            ) -> Result<Self, LoadError> {
+                let mut data = read_systemd_credential(directory, name)?;
+                let result = <$type>::parse(data.trim());
+                data.zeroize();
+                Ok(result?)
+            }
+        }
+
+        paste! {
+            #[cfg(any(
+                feature = "base64",
+                feature = "hex",
+                feature = "base32",
+                feature = "base58",
+                feature = "proquint"
+            ))]
+            #[test]
+            fn [<test_ $type:lower _systemd_credential>]() {
+                // the environment is not modified, since tests run in parallel
+                let directory = std::env::temp_dir()
+                    .join(format!("wireguard-keys-credentials-{}", std::process::id()));
+                std::fs::create_dir_all(&directory).unwrap();
+                let load = |name: &str| {
+                    <$type>::from_credentials_directory(Some(directory.as_os_str()), name)
+                };
+                let name = stringify!([<$type:lower>]);
+                let value = <$type>::generate();
+                std::fs::write(directory.join(name), format!("{}\n", value)).unwrap();
+                assert_eq!(load(name).unwrap(), value);
+                assert!(matches!(load("missing"), Err(LoadError::Credential { .. })));
+                assert!(matches!(load("../escape"), Err(LoadError::CredentialName(_))));
+                assert!(matches!(
+                    <$type>::from_credentials_directory(None, name),
+                    Err(LoadError::Unavailable(CREDENTIALS_UNAVAILABLE))
+                ));
+                std::fs::remove_file(directory.join(name)).unwrap();
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+macro_rules! impl_env {
+    ($type:ty) => {
+        impl $type {
+            /// Load key from an environment variable. Surrounding whitespace is ignored and
+            /// the encoding is detected automatically. Errors never contain the value of the
+            /// variable.
+            pub fn from_env(name: &str) -> Result<Self, LoadError> {
+                Self::from_env_value(name, std::env::var(name))
+            }
+
+            fn from_env_value(
+                name: &str,
+                value: Result<String, std::env::VarError>,
+            ) -> Result<Self, LoadError> {
+                let mut data = value.map_err(|error| env_error(name, error))?;
+                let result = <$type>::parse(data.trim());
+                data.zeroize();
+                result.map_err(|error| LoadError::EnvInvalid {
+                    name: name.to_string(),
+                    error: error.redact(),
+                })
+            }
+        }
+
+        paste! {
+            #[cfg(any(
+                feature = "base64",
+                feature = "hex",
+                feature = "base32",
+                feature = "base58",
+                feature = "proquint"
+            ))]
+            #[test]
+            fn [<test_ $type:lower _env>]() {
+                // the environment is not modified, since tests run in parallel
+                let name = stringify!([<WIREGUARD_KEYS_TEST_ $type:upper>]);
+                let load = |value: &str| <$type>::from_env_value(name, Ok(value.to_string()));
+                let value = <$type>::generate();
+                assert_eq!(load(&format!(" {}\n", value)).unwrap(), value);
+                let error = load("not-a-key").unwrap_err();
+                assert!(matches!(error, LoadError::EnvInvalid { .. }));
+                assert!(!error.to_string().contains("not-a-key"));
+                // errors of the encoding crates would contain the invalid character
+                let mut invalid = value.to_string();
+                invalid.replace_range(..1, "!");
+                let error = load(&invalid).unwrap_err();
+                let mut source: Option<&dyn std::error::Error> = Some(&error);
+                while let Some(error) = source {
+                    assert!(!error.to_string().contains('!'));
+                    let debug = format!("{:?}", error);
+                    assert!(!debug.contains('!') && !debug.contains("33"));
+                    source = error.source();
+                }
+                assert!(matches!(
+                    <$type>::from_env(name),
+                    Err(LoadError::EnvMissing(_))
+                ));
+                assert!(matches!(
+                    <$type>::from_env_value(name, Err(std::env::VarError::NotUnicode("".into()))),
+                    Err(LoadError::EnvUnicode(_))
+                ));
+            }
+        }
+    };
+}
+
+#[cfg(all(feature = "std", feature = "base64"))]
+macro_rules! impl_io {
+    ($type:ty) => {
+        impl $type {
+            /// Read key from a reader, such as the output of `wg genkey`. Surrounding
+            /// whitespace, including the trailing newline, is ignored.
+            pub fn read_from<R: std::io::Read>(mut reader: R) -> Result<Self, LoadError> {
+                let mut data = String::new();
+                let result = match reader.read_to_string(&mut data) {
+                    Ok(_) => <$type>::parse(data.trim()).map_err(LoadError::from),
+                    Err(error) => Err(error.into()),
+                };
+                data.zeroize();
+                result
+            }
+
+            /// Write key to a writer as a single base64 line with trailing newline, the
+            /// same way `wg` does.
+            pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+                let mut data = self.to_base64();
+                data.push('\n');
+                let result = writer.write_all(data.as_bytes());
+                data.zeroize();
+                result
+            }
+        }
+
+        paste! {
+            #[test]
+            fn [<test_ $type:lower _io>]() {
+                let value = <$type>::generate();
+                let mut output = Vec::new();
+                value.write_to(&mut output).unwrap();
+                assert_eq!(output, format!("{}\n", value.to_base64()).as_bytes());
+                assert_eq!(<$type>::read_from(&output[..]).unwrap(), value);
+                let input = format!("  {}\r\n", value.to_base64());
+                assert_eq!(<$type>::read_from(input.as_bytes()).unwrap(), value);
+                assert!(matches!(
+                    <$type>::read_from(&b"\n"[..]),
+                    Err(LoadError::Parse(ParseError::Length))
+                ));
+            }
         }
     };
 }
@@ -0,0 +1,172 @@
+//! Conversion of keys and nodes from mesh VPN tools built on WireGuard, for migrating their
+//! nodes onto plain WireGuard.
+//!
+//! Tailscale and Headscale use WireGuard keys as node keys, but write them as hex with a type
+//! prefix, such as `nodekey:` for public and `privkey:` for private node keys. These are
+//! supported by [Pubkey::from_tailscale] and [Privkey::from_tailscale]. Nodes listed by
+//! `headscale nodes list --output json` can be deserialized as [HeadscaleNode] and converted
+//! into peer sections.
+//!
+//! Only formats which are documented are supported. The state files of `tailscaled` and the
+//! API of Netmaker are internal to those tools and change between releases.
+
+use crate::allowed_ips::Cidr;
+use crate::config::Peer;
+use crate::{ParseError, Privkey, Pubkey};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use thiserror::Error;
+use zeroize::Zeroize;
+
+/// Prefix of public node keys.
+pub const NODE_KEY_PREFIX: &str = "nodekey:";
+
+/// Prefix of private node keys.
+pub const NODE_PRIVATE_PREFIX: &str = "privkey:";
+
+/// Possible errors that can be generated when converting keys and nodes from mesh tools.
+#[derive(Error, Debug)]
+pub enum MeshError {
+    /// Key does not have the expected type prefix
+    #[error("missing {0:?} prefix")]
+    Prefix(&'static str),
+    /// Key is not valid
+    #[error("error parsing key")]
+    Key(#[from] ParseError),
+    /// Node address is not a valid IP address
+    #[error("invalid address {0:?}")]
+    Address(String),
+}
+
+impl Pubkey {
+    /// Parse public node key in the Tailscale format, such as `nodekey:` followed by hex.
+    pub fn from_tailscale(value: &str) -> Result<Self, MeshError> {
+        let hex = value
+            .trim()
+            .strip_prefix(NODE_KEY_PREFIX)
+            .ok_or(MeshError::Prefix(NODE_KEY_PREFIX))?;
+        Ok(Pubkey::from_hex(hex)?)
+    }
+
+    /// Encode key as public node key in the Tailscale format.
+    pub fn to_tailscale(&self) -> String {
+        format!("{}{}", NODE_KEY_PREFIX, self.to_hex())
+    }
+}
+
+impl Privkey {
+    /// Parse private node key in the Tailscale format, such as `privkey:` followed by hex.
+    pub fn from_tailscale(value: &str) -> Result<Self, MeshError> {
+        let hex = value
+            .trim()
+            .strip_prefix(NODE_PRIVATE_PREFIX)
+            .ok_or(MeshError::Prefix(NODE_PRIVATE_PREFIX))?;
+        Ok(Privkey::from_hex(hex)?)
+    }
+
+    /// Encode key as private node key in the Tailscale format.
+    pub fn to_tailscale(&self) -> String {
+        let mut hex = self.to_hex();
+        let encoded = format!("{}{}", NODE_PRIVATE_PREFIX, hex);
+        hex.zeroize();
+        encoded
+    }
+}
+
+/// Node as listed by `headscale nodes list --output json`. Fields which are not needed for
+/// WireGuard are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadscaleNode {
+    /// Hostname reported by the node.
+    #[serde(default)]
+    pub name: String,
+    /// Name given to the node by the administrator, used in MagicDNS.
+    #[serde(default, alias = "givenName")]
+    pub given_name: String,
+    /// Public node key, with `nodekey:` prefix.
+    #[serde(alias = "nodeKey")]
+    pub node_key: String,
+    /// Addresses assigned to the node.
+    #[serde(default, alias = "ipAddresses")]
+    pub ip_addresses: Vec<String>,
+}
+
+impl HeadscaleNode {
+    /// Public key of the node.
+    pub fn pubkey(&self) -> Result<Pubkey, MeshError> {
+        Pubkey::from_tailscale(&self.node_key)
+    }
+
+    /// Convert node into a peer section, which allows the addresses of the node.
+    pub fn to_peer(&self) -> Result<Peer, MeshError> {
+        let mut peer = Peer::new(self.pubkey()?);
+        let name = if self.given_name.is_empty() {
+            &self.name
+        } else {
+            &self.given_name
+        };
+        peer.name = Some(name.clone()).filter(|name| !name.is_empty());
+        for address in &self.ip_addresses {
+            let address: IpAddr = address
+                .parse()
+                .map_err(|_| MeshError::Address(address.clone()))?;
+            peer.allowed_ips.push(Cidr::from(address));
+        }
+        Ok(peer)
+    }
+}
+
+#[test]
+fn test_tailscale_keys() {
+    let privkey = Privkey::generate();
+    let encoded = privkey.to_tailscale();
+    assert!(encoded.starts_with(NODE_PRIVATE_PREFIX));
+    assert_eq!(Privkey::from_tailscale(&encoded).unwrap(), privkey);
+    let pubkey = privkey.pubkey();
+    assert_eq!(
+        pubkey.to_tailscale(),
+        format!("nodekey:{}", pubkey.to_hex())
+    );
+    assert_eq!(
+        Pubkey::from_tailscale(&pubkey.to_tailscale()).unwrap(),
+        pubkey
+    );
+    assert!(matches!(
+        Pubkey::from_tailscale(&pubkey.to_hex()),
+        Err(MeshError::Prefix(NODE_KEY_PREFIX))
+    ));
+    assert!(matches!(
+        Pubkey::from_tailscale("nodekey:abc"),
+        Err(MeshError::Key(_))
+    ));
+}
+
+#[test]
+fn test_headscale_node() {
+    let json = r#"[{
+        "id": "1",
+        "machine_key": "mkey:9b1b0fd0b6ba1d5ad2d16dd1d29bb4d4d5b9c13c3a7a79bd9fb2a1d6e2b4a911",
+        "node_key": "nodekey:c5320103...",
+        "ip_addresses": ["100.64.0.1", "fd7a:115c:a1e0::1"],
+        "name": "laptop-3f2a",
+        "given_name": "laptop",
+        "online": true
+    }]"#;
+    let pubkey = Pubkey::generate();
+    let json = json.replace("nodekey:c5320103...", &pubkey.to_tailscale());
+    let nodes: Vec<HeadscaleNode> = serde_json::from_str(&json).unwrap();
+    let peer = nodes[0].to_peer().unwrap();
+    assert_eq!(peer.public_key, pubkey);
+    assert_eq!(peer.name.as_deref(), Some("laptop"));
+    assert_eq!(
+        peer.allowed_ips.to_string(),
+        "100.64.0.1/32, fd7a:115c:a1e0::1/128"
+    );
+
+    let node: HeadscaleNode = serde_json::from_str(&format!(
+        r#"{{"nodeKey": "{}", "ipAddresses": ["100.64.0.2/10"]}}"#,
+        pubkey.to_tailscale()
+    ))
+    .unwrap();
+    assert!(matches!(node.to_peer(), Err(MeshError::Address(_))));
+}
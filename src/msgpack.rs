@@ -0,0 +1,221 @@
+//! Encoding of keys as [MessagePack](https://msgpack.org) extension types, for use with
+//! [rmp-serde](https://docs.rs/rmp-serde). Every key type has its own extension type, so that
+//! keys are written as a fixed 32-byte payload with a type tag, which is about a third of the
+//! size of the base64 string and can be told apart from other binary data.
+//!
+//! Individual fields can use this module with `#[serde(with = "wireguard_keys::msgpack")]`,
+//! keys in containers can be wrapped in [Ext]:
+//!
+//! ```
+//! # use wireguard_keys::{msgpack::Ext, Pubkey};
+//! let peers = vec![Ext(Pubkey::new([1; 32])), Ext(Pubkey::new([2; 32]))];
+//! let encoded = rmp_serde::to_vec(&peers).unwrap();
+//! let decoded: Vec<Ext<Pubkey>> = rmp_serde::from_slice(&encoded).unwrap();
+//! assert_eq!(decoded, peers);
+//! ```
+//!
+//! The extension types only work with rmp-serde, other formats see a newtype struct containing
+//! the tag and the payload.
+
+use crate::{BytesVisitor, ParseError, Privkey, Pubkey, Secret};
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+/// Name of the newtype struct which rmp-serde encodes as extension type.
+const EXT_STRUCT_NAME: &str = "_ExtStruct";
+
+/// Extension type of [Pubkey].
+pub const PUBKEY_EXT_TYPE: i8 = 0x57;
+
+/// Extension type of [Privkey].
+pub const PRIVKEY_EXT_TYPE: i8 = 0x58;
+
+/// Extension type of [Secret].
+pub const SECRET_EXT_TYPE: i8 = 0x59;
+
+/// Key types which have a MessagePack extension type.
+pub trait ExtType {
+    /// Extension type of this key type.
+    const EXT_TYPE: i8;
+}
+
+impl ExtType for Pubkey {
+    const EXT_TYPE: i8 = PUBKEY_EXT_TYPE;
+}
+
+impl ExtType for Privkey {
+    const EXT_TYPE: i8 = PRIVKEY_EXT_TYPE;
+}
+
+impl ExtType for Secret {
+    const EXT_TYPE: i8 = SECRET_EXT_TYPE;
+}
+
+/// Payload of the extension type, which has to be written as byte string.
+struct Payload<'a, const N: usize>(&'a [u8; N]);
+
+impl<const N: usize> Serialize for Payload<'_, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Payload of the extension type, as read by rmp-serde.
+struct PayloadBytes<const N: usize>([u8; N]);
+
+impl<'de, const N: usize> Deserialize<'de> for PayloadBytes<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_bytes(BytesVisitor::<N>)
+            .map(PayloadBytes)
+    }
+}
+
+/// Serialize key as extension type.
+pub fn serialize<K, S, const N: usize>(key: &K, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: ExtType + Deref<Target = [u8; N]>,
+    S: Serializer,
+{
+    serializer.serialize_newtype_struct(EXT_STRUCT_NAME, &(K::EXT_TYPE, Payload(key)))
+}
+
+/// Deserialize key from extension type, checking that the type tag matches the key type.
+pub fn deserialize<'de, K, D, const N: usize>(deserializer: D) -> Result<K, D::Error>
+where
+    K: ExtType + Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+    D: Deserializer<'de>,
+{
+    struct ExtVisitor<const N: usize>(i8);
+
+    impl<'de, const N: usize> Visitor<'de> for ExtVisitor<N> {
+        type Value = [u8; N];
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "MessagePack extension type {}", self.0)
+        }
+
+        fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_tuple(2, self)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let tag: i8 = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+            if tag != self.0 {
+                return Err(A::Error::custom(format!(
+                    "unexpected extension type {}, expected {}",
+                    tag, self.0
+                )));
+            }
+            let PayloadBytes(data) = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+            Ok(data)
+        }
+    }
+
+    let mut data =
+        deserializer.deserialize_newtype_struct(EXT_STRUCT_NAME, ExtVisitor::<N>(K::EXT_TYPE))?;
+    let result = K::try_from(&data[..]).map_err(D::Error::custom);
+    data.zeroize();
+    result
+}
+
+/// Wrapper which serializes keys as extension type. This is useful for keys nested in generic
+/// containers, such as `Vec<Ext<Pubkey>>`, where `#[serde(with = "...")]` cannot be used.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ext<K>(pub K);
+
+impl<K> Ext<K> {
+    /// Unwrap key.
+    pub fn into_inner(self) -> K {
+        self.0
+    }
+}
+
+impl<K> From<K> for Ext<K> {
+    fn from(key: K) -> Self {
+        Ext(key)
+    }
+}
+
+impl<K> Deref for Ext<K> {
+    type Target = K;
+    fn deref(&self) -> &K {
+        &self.0
+    }
+}
+
+impl<K> DerefMut for Ext<K> {
+    fn deref_mut(&mut self) -> &mut K {
+        &mut self.0
+    }
+}
+
+impl<K, const N: usize> Serialize for Ext<K>
+where
+    K: ExtType + Deref<Target = [u8; N]>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, K, const N: usize> Deserialize<'de> for Ext<K>
+where
+    K: ExtType + Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Ext)
+    }
+}
+
+#[test]
+fn test_msgpack_ext() {
+    let pubkey = Pubkey::new([0xab; 32]);
+    let encoded = rmp_serde::to_vec(&Ext(pubkey)).unwrap();
+    // ext 8 with length and type, followed by the key
+    let mut expected = vec![0xc7, 32, PUBKEY_EXT_TYPE as u8];
+    expected.extend([0xab; 32]);
+    assert_eq!(encoded, expected);
+    assert_eq!(
+        rmp_serde::from_slice::<Ext<Pubkey>>(&encoded).unwrap(),
+        Ext(pubkey)
+    );
+    assert!(rmp_serde::from_slice::<Ext<Secret>>(&encoded).is_err());
+
+    let mut truncated = vec![0xc7, 31, PUBKEY_EXT_TYPE as u8];
+    truncated.extend([0xab; 31]);
+    assert!(rmp_serde::from_slice::<Ext<Pubkey>>(&truncated).is_err());
+    assert!(rmp_serde::from_slice::<Ext<Pubkey>>(&rmp_serde::to_vec(&pubkey).unwrap()).is_err());
+}
+
+#[test]
+fn test_msgpack_fields() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Peer {
+        #[serde(with = "crate::msgpack")]
+        pubkey: Pubkey,
+        #[serde(with = "crate::msgpack")]
+        preshared_key: Secret,
+        privkey: Option<Ext<Privkey>>,
+    }
+    let peer = Peer {
+        pubkey: Pubkey::new([1; 32]),
+        preshared_key: Secret::new([2; 32]),
+        privkey: Some(Ext(Privkey::new([3; 32]))),
+    };
+    let encoded = rmp_serde::to_vec_named(&peer).unwrap();
+    assert_eq!(rmp_serde::from_slice::<Peer>(&encoded).unwrap(), peer);
+}
@@ -0,0 +1,216 @@
+//! Export of tunnel configurations to systemd-networkd. A tunnel is described by two files: a
+//! `.netdev` file which creates the WireGuard interface and contains the keys and peers, and a
+//! `.network` file which assigns addresses and DNS servers to it.
+//!
+//! The `.netdev` file contains the private key of the interface, so it should only be readable
+//! by root and the `systemd-network` group.
+//!
+//! Routes for the allowed IPs of peers are added to the main routing table, the same way
+//! wg-quick does, unless `Table = off` is set. Hook commands and `SaveConfig` have no
+//! equivalent in networkd, so configurations using them cannot be exported.
+
+use crate::config::{valid_interface_name, Interface, Peer, TunnelConfig};
+use std::fmt::{self, Write};
+use std::net::IpAddr;
+use thiserror::Error;
+
+/// Possible errors that can be generated when exporting to systemd-networkd.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NetworkdError {
+    /// Interface name is not valid
+    #[error("invalid interface name {0:?}")]
+    Name(String),
+    /// Configuration uses a setting which networkd does not support
+    #[error("{0} is not supported by systemd-networkd")]
+    Unsupported(&'static str),
+}
+
+/// Contents of the `.netdev` and `.network` files of a tunnel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkdConfig {
+    /// Contents of the `.netdev` file.
+    pub netdev: String,
+    /// Contents of the `.network` file.
+    pub network: String,
+}
+
+fn write_netdev(f: &mut String, name: &str, config: &TunnelConfig) -> fmt::Result {
+    let interface = &config.interface;
+    writeln!(f, "[NetDev]")?;
+    writeln!(f, "Name={}", name)?;
+    writeln!(f, "Kind=wireguard")?;
+    if let Some(mtu) = interface.mtu {
+        writeln!(f, "MTUBytes={}", mtu)?;
+    }
+    writeln!(f)?;
+    writeln!(f, "[WireGuard]")?;
+    writeln!(f, "PrivateKey={}", interface.private_key.to_base64())?;
+    if let Some(port) = interface.listen_port {
+        writeln!(f, "ListenPort={}", port)?;
+    }
+    if let Some(fwmark) = interface.fwmark {
+        writeln!(f, "FirewallMark={}", fwmark)?;
+    }
+    match interface.table.as_deref() {
+        None => writeln!(f, "RouteTable=main")?,
+        Some(table) if table.eq_ignore_ascii_case("auto") => writeln!(f, "RouteTable=main")?,
+        Some(table) if table.eq_ignore_ascii_case("off") => {}
+        Some(table) => writeln!(f, "RouteTable={}", table)?,
+    }
+    for peer in &config.peers {
+        writeln!(f)?;
+        write_peer(f, peer)?;
+    }
+    Ok(())
+}
+
+fn write_peer(f: &mut String, peer: &Peer) -> fmt::Result {
+    if let Some(name) = &peer.name {
+        writeln!(f, "# {}", name.replace(['\r', '\n'], " "))?;
+    }
+    writeln!(f, "[WireGuardPeer]")?;
+    writeln!(f, "PublicKey={}", peer.public_key.to_base64())?;
+    if let Some(preshared_key) = &peer.preshared_key {
+        writeln!(f, "PresharedKey={}", preshared_key.to_base64())?;
+    }
+    for cidr in &peer.allowed_ips {
+        writeln!(f, "AllowedIPs={}", cidr)?;
+    }
+    if let Some(endpoint) = &peer.endpoint {
+        writeln!(f, "Endpoint={}", endpoint)?;
+    }
+    if let Some(interval) = peer.persistent_keepalive {
+        writeln!(f, "PersistentKeepalive={}", interval)?;
+    }
+    Ok(())
+}
+
+fn write_network(f: &mut String, name: &str, interface: &Interface) -> fmt::Result {
+    writeln!(f, "[Match]")?;
+    writeln!(f, "Name={}", name)?;
+    writeln!(f)?;
+    writeln!(f, "[Network]")?;
+    for address in &interface.address {
+        writeln!(f, "Address={}", address)?;
+    }
+    // wg-quick treats DNS entries which are not addresses as search domains
+    let (servers, domains): (Vec<&String>, Vec<&String>) = interface
+        .dns
+        .iter()
+        .partition(|entry| entry.parse::<IpAddr>().is_ok());
+    for server in servers {
+        writeln!(f, "DNS={}", server)?;
+    }
+    if !domains.is_empty() {
+        let domains: Vec<&str> = domains.iter().map(|domain| domain.as_str()).collect();
+        writeln!(f, "Domains={}", domains.join(" "))?;
+    }
+    Ok(())
+}
+
+impl TunnelConfig {
+    /// Export configuration as systemd-networkd `.netdev` and `.network` files for the
+    /// interface with the given name.
+    pub fn to_networkd(&self, name: &str) -> Result<NetworkdConfig, NetworkdError> {
+        if !valid_interface_name(name) {
+            return Err(NetworkdError::Name(name.to_string()));
+        }
+        if let Some(setting) = self.interface.wg_quick_setting() {
+            return Err(NetworkdError::Unsupported(setting));
+        }
+        let mut netdev = String::new();
+        write_netdev(&mut netdev, name, self).expect("writing to string cannot fail");
+        let mut network = String::new();
+        write_network(&mut network, name, &self.interface).expect("writing to string cannot fail");
+        Ok(NetworkdConfig { netdev, network })
+    }
+}
+
+#[cfg(test)]
+const TEST_CONFIG: &str = "
+[Interface]
+PrivateKey = yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+Address = 10.0.0.1/24, fd00::1/64
+ListenPort = 51820
+FwMark = 0x1234
+DNS = 10.0.0.53, example.internal
+MTU = 1420
+
+# server
+[Peer]
+PublicKey = xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+PresharedKey = /UwcSPg38hW/D9Y3tcS1FOV0K1wuURMbS0sesJEP5ak=
+AllowedIPs = 10.0.0.0/24, fd00::/64
+Endpoint = vpn.example.com:51820
+PersistentKeepalive = 25
+";
+
+#[test]
+fn test_networkd_export() {
+    let config = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    let exported = config.to_networkd("wg0").unwrap();
+    assert_eq!(
+        exported.netdev,
+        "\
+[NetDev]
+Name=wg0
+Kind=wireguard
+MTUBytes=1420
+
+[WireGuard]
+PrivateKey=yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+ListenPort=51820
+FirewallMark=4660
+RouteTable=main
+
+# server
+[WireGuardPeer]
+PublicKey=xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+PresharedKey=/UwcSPg38hW/D9Y3tcS1FOV0K1wuURMbS0sesJEP5ak=
+AllowedIPs=10.0.0.0/24
+AllowedIPs=fd00::/64
+Endpoint=vpn.example.com:51820
+PersistentKeepalive=25
+"
+    );
+    assert_eq!(
+        exported.network,
+        "\
+[Match]
+Name=wg0
+
+[Network]
+Address=10.0.0.1/24
+Address=fd00::1/64
+DNS=10.0.0.53
+Domains=example.internal
+"
+    );
+}
+
+#[test]
+fn test_networkd_export_invalid() {
+    let mut config = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    assert_eq!(
+        config.to_networkd("wireguard-tunnel0"),
+        Err(NetworkdError::Name("wireguard-tunnel0".into()))
+    );
+    assert_eq!(
+        config.to_networkd("../wg0"),
+        Err(NetworkdError::Name("../wg0".into()))
+    );
+    config.interface.table = Some("off".into());
+    assert!(!config
+        .to_networkd("wg0")
+        .unwrap()
+        .netdev
+        .contains("RouteTable"));
+    config
+        .interface
+        .post_up
+        .push("iptables -A FORWARD -i %i -j ACCEPT".into());
+    assert_eq!(
+        config.to_networkd("wg0"),
+        Err(NetworkdError::Unsupported("PostUp"))
+    );
+}
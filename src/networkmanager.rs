@@ -0,0 +1,267 @@
+//! Export of tunnel configurations to NetworkManager. A tunnel is described by a single
+//! keyfile, usually stored as `/etc/NetworkManager/system-connections/<name>.nmconnection`,
+//! which contains the interface settings in the `[wireguard]` section and one
+//! `[wireguard-peer.<pubkey>]` section per peer, named after its base64-encoded public key.
+//!
+//! The keyfile contains the private key of the interface, so it must only be readable by root,
+//! otherwise NetworkManager refuses to load it. No UUID is written, NetworkManager derives one
+//! from the file name.
+//!
+//! Like wg-quick, NetworkManager adds routes for the allowed IPs of peers unless `Table = off`
+//! is set. Only numeric routing tables are supported. Hook commands and `SaveConfig` have no
+//! equivalent in NetworkManager, so configurations using them cannot be exported.
+
+use crate::allowed_ips::Cidr;
+use crate::config::{valid_interface_name, Interface, Peer, TunnelConfig};
+use std::fmt::{self, Write};
+use std::net::IpAddr;
+use thiserror::Error;
+
+/// Possible errors that can be generated when exporting to NetworkManager.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NetworkManagerError {
+    /// Interface name is not valid
+    #[error("invalid interface name {0:?}")]
+    Name(String),
+    /// Configuration uses a setting which NetworkManager does not support
+    #[error("{0} is not supported by NetworkManager")]
+    Unsupported(&'static str),
+}
+
+/// Write list in the keyfile format, where every item is terminated by a semicolon.
+fn write_list<T: fmt::Display>(f: &mut String, key: &str, values: &[T]) -> fmt::Result {
+    if values.is_empty() {
+        return Ok(());
+    }
+    write!(f, "{}=", key)?;
+    for value in values {
+        write!(f, "{};", value)?;
+    }
+    writeln!(f)
+}
+
+fn write_wireguard(f: &mut String, interface: &Interface, table: Option<u32>) -> fmt::Result {
+    writeln!(f, "[wireguard]")?;
+    writeln!(f, "private-key={}", interface.private_key.to_base64())?;
+    if let Some(port) = interface.listen_port {
+        writeln!(f, "listen-port={}", port)?;
+    }
+    if let Some(fwmark) = interface.fwmark {
+        writeln!(f, "fwmark={}", fwmark)?;
+    }
+    if let Some(mtu) = interface.mtu {
+        writeln!(f, "mtu={}", mtu)?;
+    }
+    if table == Some(0) {
+        writeln!(f, "peer-routes=false")?;
+    }
+    Ok(())
+}
+
+fn write_peer(f: &mut String, peer: &Peer) -> fmt::Result {
+    if let Some(name) = &peer.name {
+        writeln!(f, "# {}", name.replace(['\r', '\n'], " "))?;
+    }
+    writeln!(f, "[wireguard-peer.{}]", peer.public_key.to_base64())?;
+    if let Some(endpoint) = &peer.endpoint {
+        writeln!(f, "endpoint={}", endpoint)?;
+    }
+    if let Some(preshared_key) = &peer.preshared_key {
+        writeln!(f, "preshared-key={}", preshared_key.to_base64())?;
+        writeln!(f, "preshared-key-flags=0")?;
+    }
+    if let Some(interval) = peer.persistent_keepalive {
+        writeln!(f, "persistent-keepalive={}", interval)?;
+    }
+    write_list(f, "allowed-ips", &peer.allowed_ips)
+}
+
+/// Write `[ipv4]` or `[ipv6]` section with the addresses and DNS servers of one family.
+fn write_ip(
+    f: &mut String,
+    section: &str,
+    addresses: &[&Cidr],
+    dns: &[IpAddr],
+    search: &[&String],
+    table: Option<u32>,
+) -> fmt::Result {
+    writeln!(f, "[{}]", section)?;
+    if addresses.is_empty() {
+        writeln!(f, "method=disabled")?;
+    } else {
+        writeln!(f, "method=manual")?;
+    }
+    for (index, address) in addresses.iter().enumerate() {
+        writeln!(f, "address{}={}", index + 1, address)?;
+    }
+    write_list(f, "dns", dns)?;
+    if !addresses.is_empty() {
+        write_list(f, "dns-search", search)?;
+    }
+    match table {
+        Some(0) | None => {}
+        Some(table) => writeln!(f, "route-table={}", table)?,
+    }
+    Ok(())
+}
+
+fn write_keyfile(
+    f: &mut String,
+    name: &str,
+    config: &TunnelConfig,
+    table: Option<u32>,
+) -> fmt::Result {
+    let interface = &config.interface;
+    writeln!(f, "[connection]")?;
+    writeln!(f, "id={}", name)?;
+    writeln!(f, "type=wireguard")?;
+    writeln!(f, "interface-name={}", name)?;
+    writeln!(f)?;
+    write_wireguard(f, interface, table)?;
+    for peer in &config.peers {
+        writeln!(f)?;
+        write_peer(f, peer)?;
+    }
+
+    // wg-quick treats DNS entries which are not addresses as search domains
+    let (servers, search): (Vec<IpAddr>, Vec<&String>) = interface.dns.iter().fold(
+        (Vec::new(), Vec::new()),
+        |(mut servers, mut search), entry| {
+            match entry.parse() {
+                Ok(addr) => servers.push(addr),
+                Err(_) => search.push(entry),
+            }
+            (servers, search)
+        },
+    );
+    for ipv4 in [true, false] {
+        let addresses: Vec<&Cidr> = interface
+            .address
+            .iter()
+            .filter(|cidr| cidr.is_ipv4() == ipv4)
+            .collect();
+        let servers: Vec<IpAddr> = servers
+            .iter()
+            .copied()
+            .filter(|addr| addr.is_ipv4() == ipv4)
+            .collect();
+        let section = if ipv4 { "ipv4" } else { "ipv6" };
+        writeln!(f)?;
+        write_ip(f, section, &addresses, &servers, &search, table)?;
+    }
+    Ok(())
+}
+
+impl TunnelConfig {
+    /// Export configuration as NetworkManager keyfile for a connection with the given name,
+    /// which is also used as the interface name.
+    pub fn to_networkmanager(&self, name: &str) -> Result<String, NetworkManagerError> {
+        if !valid_interface_name(name) {
+            return Err(NetworkManagerError::Name(name.to_string()));
+        }
+        if let Some(setting) = self.interface.wg_quick_setting() {
+            return Err(NetworkManagerError::Unsupported(setting));
+        }
+        // routing table, where 0 means that no routes are added
+        let table = match self.interface.table.as_deref() {
+            None => None,
+            Some(table) if table.eq_ignore_ascii_case("auto") => None,
+            Some(table) if table.eq_ignore_ascii_case("off") => Some(0),
+            Some(table) => match table.parse() {
+                Ok(0) | Err(_) => return Err(NetworkManagerError::Unsupported("Table")),
+                Ok(table) => Some(table),
+            },
+        };
+        let mut keyfile = String::new();
+        write_keyfile(&mut keyfile, name, self, table).expect("writing to string cannot fail");
+        Ok(keyfile)
+    }
+}
+
+#[cfg(test)]
+const TEST_CONFIG: &str = "
+[Interface]
+PrivateKey = yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+Address = 10.0.0.1/24, fd00::1/64
+ListenPort = 51820
+FwMark = 0x1234
+DNS = 10.0.0.53, fd00::53, example.internal
+MTU = 1420
+Table = 1000
+
+# server
+[Peer]
+PublicKey = xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+PresharedKey = /UwcSPg38hW/D9Y3tcS1FOV0K1wuURMbS0sesJEP5ak=
+AllowedIPs = 10.0.0.0/24, fd00::/64
+Endpoint = vpn.example.com:51820
+PersistentKeepalive = 25
+";
+
+#[test]
+fn test_networkmanager_export() {
+    let config = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    assert_eq!(
+        config.to_networkmanager("wg0").unwrap(),
+        "\
+[connection]
+id=wg0
+type=wireguard
+interface-name=wg0
+
+[wireguard]
+private-key=yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+listen-port=51820
+fwmark=4660
+mtu=1420
+
+# server
+[wireguard-peer.xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=]
+endpoint=vpn.example.com:51820
+preshared-key=/UwcSPg38hW/D9Y3tcS1FOV0K1wuURMbS0sesJEP5ak=
+preshared-key-flags=0
+persistent-keepalive=25
+allowed-ips=10.0.0.0/24;fd00::/64;
+
+[ipv4]
+method=manual
+address1=10.0.0.1/24
+dns=10.0.0.53;
+dns-search=example.internal;
+route-table=1000
+
+[ipv6]
+method=manual
+address1=fd00::1/64
+dns=fd00::53;
+dns-search=example.internal;
+route-table=1000
+"
+    );
+}
+
+#[test]
+fn test_networkmanager_export_invalid() {
+    let mut config = TunnelConfig::parse(TEST_CONFIG).unwrap();
+    assert_eq!(
+        config.to_networkmanager("wireguard-tunnel0"),
+        Err(NetworkManagerError::Name("wireguard-tunnel0".into()))
+    );
+    config.interface.table = Some("off".into());
+    config.interface.address.retain(Cidr::is_ipv4);
+    let keyfile = config.to_networkmanager("wg0").unwrap();
+    assert!(keyfile.contains("peer-routes=false\n"));
+    assert!(keyfile.contains("[ipv6]\nmethod=disabled\n"));
+    assert!(!keyfile.contains("route-table"));
+    config.interface.table = Some("vpn".into());
+    assert_eq!(
+        config.to_networkmanager("wg0"),
+        Err(NetworkManagerError::Unsupported("Table"))
+    );
+    config.interface.table = None;
+    config.interface.save_config = true;
+    assert_eq!(
+        config.to_networkmanager("wg0"),
+        Err(NetworkManagerError::Unsupported("SaveConfig"))
+    );
+}
@@ -0,0 +1,209 @@
+//! Rendering of keys and tunnel configurations as QR codes. Tunnel configurations are encoded
+//! as wg-quick configuration text, which is what the official WireGuard mobile apps expect
+//! when scanning a QR code. Public keys are encoded as base64.
+//!
+//! Codes can be rendered as SVG, or for terminals as Unicode blocks or ANSI colors. With the
+//! `qr-png` feature, they can also be rendered as PNG.
+
+use crate::config::TunnelConfig;
+use crate::Pubkey;
+use qrcode::render::{svg, unicode};
+use qrcode::{Color, QrCode};
+use thiserror::Error;
+
+/// Possible errors that can be generated when rendering QR codes.
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum QrError {
+    /// Data does not fit into the largest QR code
+    #[error("data too long for a QR code")]
+    TooLong,
+}
+
+/// Minimum size (in pixels) of rendered SVG images.
+const SVG_MIN_SIZE: u32 = 256;
+
+/// Minimum size (in pixels) of rendered PNG images.
+#[cfg(feature = "qr-png")]
+const PNG_MIN_SIZE: usize = 256;
+
+/// Width (in modules) of the light border around codes, which scanners need to find them.
+const QUIET_ZONE: usize = 4;
+
+/// Encode data as QR code. Data which is too long is the only error for byte strings.
+fn encode(data: String) -> Result<QrCode, QrError> {
+    QrCode::new(data).map_err(|_| QrError::TooLong)
+}
+
+fn render_svg(code: &QrCode) -> String {
+    code.render::<svg::Color>()
+        .min_dimensions(SVG_MIN_SIZE, SVG_MIN_SIZE)
+        .build()
+}
+
+/// Render using Unicode half blocks, two rows per line, suitable for printing to a terminal
+/// with a dark background.
+fn render_terminal(code: &QrCode) -> String {
+    code.render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build()
+}
+
+/// Rows of modules including the quiet zone, `true` for dark modules.
+fn modules(code: &QrCode) -> Vec<Vec<bool>> {
+    let width = code.width();
+    let colors = code.to_colors();
+    let mut rows = vec![vec![false; width + 2 * QUIET_ZONE]; width + 2 * QUIET_ZONE];
+    for (index, color) in colors.into_iter().enumerate() {
+        rows[index / width + QUIET_ZONE][index % width + QUIET_ZONE] = color == Color::Dark;
+    }
+    rows
+}
+
+/// Render using ANSI background colors, two spaces per module, for terminals whose font lacks
+/// the block characters.
+fn render_ansi(code: &QrCode) -> String {
+    let mut output = String::new();
+    for row in modules(code) {
+        for dark in row {
+            output.push_str(if dark { "\x1b[40m  " } else { "\x1b[47m  " });
+        }
+        output.push_str("\x1b[0m\n");
+    }
+    output
+}
+
+/// Render as grayscale PNG image.
+#[cfg(feature = "qr-png")]
+fn render_png(code: &QrCode) -> Vec<u8> {
+    let modules = modules(code);
+    let scale = PNG_MIN_SIZE.div_ceil(modules.len());
+    let size = modules.len() * scale;
+    let mut pixels = Vec::with_capacity(size * size);
+    for row in &modules {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&dark| std::iter::repeat_n(if dark { 0x00 } else { 0xff }, scale))
+            .collect();
+        for _ in 0..scale {
+            pixels.extend_from_slice(&line);
+        }
+    }
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .expect("encoding PNG into memory does not fail");
+    output
+}
+
+impl Pubkey {
+    /// Render public key as QR code in SVG format.
+    pub fn to_qr_svg(&self) -> String {
+        let code = QrCode::new(self.to_base64()).expect("public key fits in QR code");
+        render_svg(&code)
+    }
+
+    /// Render public key as QR code for printing to a terminal.
+    pub fn to_qr_terminal(&self) -> String {
+        let code = QrCode::new(self.to_base64()).expect("public key fits in QR code");
+        render_terminal(&code)
+    }
+
+    /// Render public key as QR code for printing to a terminal using ANSI colors.
+    pub fn to_qr_ansi(&self) -> String {
+        let code = QrCode::new(self.to_base64()).expect("public key fits in QR code");
+        render_ansi(&code)
+    }
+
+    /// Render public key as QR code in PNG format.
+    #[cfg(feature = "qr-png")]
+    pub fn to_qr_png(&self) -> Vec<u8> {
+        let code = QrCode::new(self.to_base64()).expect("public key fits in QR code");
+        render_png(&code)
+    }
+}
+
+impl TunnelConfig {
+    /// Render tunnel configuration as QR code in SVG format. Fails if the configuration is too
+    /// large to fit into a QR code.
+    pub fn to_qr_svg(&self) -> Result<String, QrError> {
+        let code = encode(self.to_string())?;
+        Ok(render_svg(&code))
+    }
+
+    /// Render tunnel configuration as QR code for printing to a terminal. Fails if the
+    /// configuration is too large to fit into a QR code.
+    pub fn to_qr_terminal(&self) -> Result<String, QrError> {
+        let code = encode(self.to_string())?;
+        Ok(render_terminal(&code))
+    }
+
+    /// Render tunnel configuration as QR code for printing to a terminal using ANSI colors.
+    /// Fails if the configuration is too large to fit into a QR code.
+    pub fn to_qr_ansi(&self) -> Result<String, QrError> {
+        let code = encode(self.to_string())?;
+        Ok(render_ansi(&code))
+    }
+
+    /// Render tunnel configuration as QR code in PNG format. Fails if the configuration is too
+    /// large to fit into a QR code.
+    #[cfg(feature = "qr-png")]
+    pub fn to_qr_png(&self) -> Result<Vec<u8>, QrError> {
+        let code = encode(self.to_string())?;
+        Ok(render_png(&code))
+    }
+}
+
+#[test]
+fn test_pubkey_qr() {
+    let pubkey = Pubkey::generate();
+    let svg = pubkey.to_qr_svg();
+    assert!(svg.contains("<svg"));
+    let terminal = pubkey.to_qr_terminal();
+    assert!(terminal.lines().count() > 10);
+    let ansi = pubkey.to_qr_ansi();
+    let code = QrCode::new(pubkey.to_base64()).unwrap();
+    assert_eq!(ansi.lines().count(), code.width() + 2 * QUIET_ZONE);
+    assert!(ansi.lines().all(|line| line.ends_with("\x1b[0m")));
+}
+
+#[cfg(feature = "qr-png")]
+#[test]
+fn test_pubkey_qr_png() {
+    let pubkey = Pubkey::generate();
+    let png = pubkey.to_qr_png();
+    let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.width, info.height);
+    assert!(info.width as usize >= PNG_MIN_SIZE);
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    // quiet zone is light, the finder pattern in the top left corner is dark
+    let scale = info.width as usize / modules(&QrCode::new(pubkey.to_base64()).unwrap()).len();
+    assert_eq!(pixels[0], 0xff);
+    assert_eq!(pixels[QUIET_ZONE * scale * (info.width as usize + 1)], 0x00);
+}
+
+#[test]
+fn test_config_qr() {
+    use crate::config::{Interface, Peer};
+    use crate::Privkey;
+    let mut config = TunnelConfig::new(Interface::new(Privkey::generate()));
+    let mut peer = Peer::new(Pubkey::generate());
+    peer.allowed_ips.push("0.0.0.0/0".parse().unwrap());
+    config.peers.push(peer);
+    assert!(config.to_qr_svg().unwrap().contains("<svg"));
+    assert!(config.to_qr_terminal().is_ok());
+    assert!(config.to_qr_ansi().is_ok());
+    #[cfg(feature = "qr-png")]
+    assert!(config.to_qr_png().unwrap().starts_with(b"\x89PNG"));
+    for _ in 0..100 {
+        config.peers.push(Peer::new(Pubkey::generate()));
+    }
+    assert_eq!(config.to_qr_svg(), Err(QrError::TooLong));
+}
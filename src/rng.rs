@@ -0,0 +1,106 @@
+//! Randomness used for generating keys. By default, every call reads from the kernel
+//! randomness generator.
+//!
+//! With the `fast-rng` feature, each thread instead keeps a ChaCha20 generator which is seeded
+//! from the kernel and reseeded after [RESEED_INTERVAL] bytes, which avoids a system call per
+//! generated key when generating many keys at once. The trade-offs are:
+//!
+//! - The generator state lives in process memory. Anyone able to read it can predict all keys
+//!   generated by that thread until the next reseed.
+//! - A forked child process would inherit the state and generate the same keys as its parent.
+//!   This is detected by comparing process IDs, which causes a reseed in the child.
+//! - The state is not cleared when the thread exits.
+//!
+//! On `wasm32-unknown-unknown`, the kernel randomness generator is `crypto.getRandomValues` of
+//! the JavaScript environment, and there are no processes which could fork.
+
+#[cfg(not(feature = "fast-rng"))]
+use rand_core::{OsRng, RngCore};
+
+/// Fill buffer with random bytes suitable for key material.
+#[cfg(not(feature = "fast-rng"))]
+pub(crate) fn fill_bytes(data: &mut [u8]) {
+    OsRng.fill_bytes(data);
+}
+
+/// Number of bytes generated before the thread generator is reseeded.
+#[cfg(feature = "fast-rng")]
+pub(crate) const RESEED_INTERVAL: usize = 1024 * 1024;
+
+#[cfg(feature = "fast-rng")]
+mod fast {
+    use super::RESEED_INTERVAL;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{OsRng, RngCore, SeedableRng};
+    use std::cell::RefCell;
+
+    /// Thread generator, along with the process it was seeded in.
+    pub(super) struct ThreadRng {
+        rng: ChaCha20Rng,
+        process: u32,
+        pub(super) generated: usize,
+    }
+
+    impl ThreadRng {
+        fn new() -> Self {
+            ThreadRng {
+                rng: ChaCha20Rng::from_rng(OsRng).expect("kernel randomness is available"),
+                process: process_id(),
+                generated: 0,
+            }
+        }
+
+        pub(super) fn fill_bytes(&mut self, data: &mut [u8]) {
+            if self.generated >= RESEED_INTERVAL || self.process != process_id() {
+                *self = ThreadRng::new();
+            }
+            self.rng.fill_bytes(data);
+            self.generated += data.len();
+        }
+    }
+
+    /// Identifier of the current process, for detecting forks.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn process_id() -> u32 {
+        std::process::id()
+    }
+
+    /// WebAssembly modules can not fork, and asking for the process ID panics.
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    fn process_id() -> u32 {
+        0
+    }
+
+    thread_local! {
+        pub(super) static THREAD_RNG: RefCell<ThreadRng> = RefCell::new(ThreadRng::new());
+    }
+}
+
+/// Fill buffer with random bytes suitable for key material.
+#[cfg(feature = "fast-rng")]
+pub(crate) fn fill_bytes(data: &mut [u8]) {
+    fast::THREAD_RNG.with(|rng| rng.borrow_mut().fill_bytes(data));
+}
+
+#[cfg(feature = "fast-rng")]
+#[test]
+fn test_thread_rng_reseed() {
+    let mut data = [0; 32];
+    fill_bytes(&mut data);
+    fast::THREAD_RNG.with(|rng| rng.borrow_mut().generated = RESEED_INTERVAL);
+    let mut next = [0; 32];
+    fill_bytes(&mut next);
+    assert_ne!(data, next);
+    fast::THREAD_RNG.with(|rng| assert_eq!(rng.borrow().generated, 32));
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+fn test_fill_bytes() {
+    let mut first = [0; 32];
+    let mut second = [0; 32];
+    fill_bytes(&mut first);
+    fill_bytes(&mut second);
+    assert_ne!(first, [0; 32]);
+    assert_ne!(first, second);
+}
@@ -0,0 +1,205 @@
+//! Primitives for coordinated key rotation. A [RotatingKey] tracks the current and the previous
+//! generation of a key along with the time they were generated. After a rotation, the previous
+//! key remains accepted for a configurable overlap window, which gives peers time to pick up
+//! the new key before the old one stops working.
+//!
+//! The state can be persisted using serde when the `serde` feature is enabled.
+
+use crate::{Privkey, Pubkey, Secret};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// Key types which can be rotated.
+pub trait Rotate: Copy + Eq {
+    /// Generate a fresh key.
+    fn generate() -> Self;
+}
+
+impl Rotate for Privkey {
+    fn generate() -> Self {
+        Privkey::generate()
+    }
+}
+
+impl Rotate for Secret {
+    fn generate() -> Self {
+        Secret::generate()
+    }
+}
+
+/// Single generation of a rotating key.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Generation<K> {
+    /// Key of this generation.
+    pub key: K,
+    /// Time at which this key was generated.
+    pub created: SystemTime,
+}
+
+/// Key which is periodically replaced, keeping the previous key valid for an overlap window.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RotatingKey<K> {
+    current: Generation<K>,
+    previous: Option<Generation<K>>,
+    overlap: Duration,
+}
+
+impl<K: Rotate> RotatingKey<K> {
+    /// Generate new rotating key with the given overlap window.
+    pub fn generate(overlap: Duration) -> Self {
+        Self::new(K::generate(), SystemTime::now(), overlap)
+    }
+
+    /// Rotate the key, returning the newly generated key.
+    pub fn rotate(&mut self) -> K {
+        self.rotate_to(K::generate(), SystemTime::now())
+    }
+}
+
+impl<K: Copy + Eq> RotatingKey<K> {
+    /// Create new rotating key from an existing key and the time it was generated.
+    pub fn new(key: K, created: SystemTime, overlap: Duration) -> Self {
+        RotatingKey {
+            current: Generation { key, created },
+            previous: None,
+            overlap,
+        }
+    }
+
+    /// Replace the current key with the given key, generated at `now`. The current key becomes
+    /// the previous key, and is accepted until the overlap window has passed.
+    pub fn rotate_to(&mut self, key: K, now: SystemTime) -> K {
+        self.previous = Some(self.current);
+        self.current = Generation { key, created: now };
+        key
+    }
+
+    /// Key which should be used for new sessions.
+    pub fn active(&self) -> K {
+        self.current.key
+    }
+
+    /// Current generation of the key.
+    pub fn current(&self) -> &Generation<K> {
+        &self.current
+    }
+
+    /// Previous generation of the key, regardless of whether it is still accepted.
+    pub fn previous(&self) -> Option<&Generation<K>> {
+        self.previous.as_ref()
+    }
+
+    /// Overlap window during which the previous key is still accepted.
+    pub fn overlap(&self) -> Duration {
+        self.overlap
+    }
+
+    /// Change the overlap window.
+    pub fn set_overlap(&mut self, overlap: Duration) {
+        self.overlap = overlap;
+    }
+
+    /// Time at which the overlap window ends and the previous key stops being accepted. This is
+    /// `None` if there is no previous key, or if the overlap window is too long for its end to
+    /// be represented, in which case it never ends.
+    pub fn overlap_end(&self) -> Option<SystemTime> {
+        self.previous
+            .and_then(|_| self.current.created.checked_add(self.overlap))
+    }
+
+    /// Determine if the previous key, if any, is still accepted at the given time.
+    fn overlaps(&self, now: SystemTime) -> bool {
+        match self.current.created.checked_add(self.overlap) {
+            Some(end) => now < end,
+            None => true,
+        }
+    }
+
+    /// Keys accepted at the given time: the current key and, within the overlap window, the
+    /// previous key.
+    pub fn accepted_at(&self, now: SystemTime) -> impl Iterator<Item = K> + '_ {
+        let previous = match self.overlaps(now) {
+            true => self.previous.map(|generation| generation.key),
+            false => None,
+        };
+        std::iter::once(self.current.key).chain(previous)
+    }
+
+    /// Forget the previous key if the overlap window has passed.
+    pub fn expire(&mut self, now: SystemTime) {
+        if !self.overlaps(now) {
+            self.previous = None;
+        }
+    }
+}
+
+impl RotatingKey<Privkey> {
+    /// Determine if the public key belongs to an accepted private key.
+    pub fn accepts(&self, pubkey: &Pubkey) -> bool {
+        self.accepts_at(pubkey, SystemTime::now())
+    }
+
+    /// Determine if the public key belongs to a private key accepted at the given time.
+    pub fn accepts_at(&self, pubkey: &Pubkey, now: SystemTime) -> bool {
+        self.accepted_at(now).any(|key| &key.pubkey() == pubkey)
+    }
+}
+
+impl RotatingKey<Secret> {
+    /// Determine if the preshared key is accepted.
+    pub fn accepts(&self, secret: &Secret) -> bool {
+        self.accepts_at(secret, SystemTime::now())
+    }
+
+    /// Determine if the preshared key is accepted at the given time.
+    pub fn accepts_at(&self, secret: &Secret, now: SystemTime) -> bool {
+        self.accepted_at(now).any(|key| &key == secret)
+    }
+}
+
+#[test]
+fn test_rotating_privkey() {
+    let start = SystemTime::UNIX_EPOCH;
+    let overlap = Duration::from_secs(60);
+    let first = Privkey::generate();
+    let mut key = RotatingKey::new(first, start, overlap);
+    assert_eq!(key.active(), first);
+    assert!(key.accepts_at(&first.pubkey(), start));
+    assert_eq!(key.overlap_end(), None);
+
+    let rotated = start + Duration::from_secs(3600);
+    let second = key.rotate_to(Privkey::generate(), rotated);
+    assert_eq!(key.active(), second);
+    assert_eq!(key.previous().unwrap().key, first);
+    assert!(key.accepts_at(&second.pubkey(), rotated));
+    assert!(key.accepts_at(&first.pubkey(), rotated + Duration::from_secs(59)));
+    assert!(!key.accepts_at(&first.pubkey(), rotated + overlap));
+
+    key.expire(rotated + Duration::from_secs(30));
+    assert!(key.previous().is_some());
+    key.expire(rotated + overlap);
+    assert!(key.previous().is_none());
+}
+
+#[test]
+fn test_rotating_secret() {
+    let mut key = RotatingKey::<Secret>::generate(Duration::from_secs(60));
+    let first = key.active();
+    let second = key.rotate();
+    assert_ne!(first, second);
+    assert!(key.accepts(&first));
+    assert!(key.accepts(&second));
+    assert!(!key.accepts(&Secret::generate()));
+    key.set_overlap(Duration::ZERO);
+    assert!(!key.accepts(&first));
+
+    // an overlap window which overflows never ends
+    key.set_overlap(Duration::MAX);
+    assert_eq!(key.overlap_end(), None);
+    assert!(key.accepts(&first));
+    key.expire(SystemTime::now());
+    assert!(key.previous().is_some());
+}
@@ -0,0 +1,276 @@
+//! Adapters for serializing individual fields of key types in another representation, for use
+//! with `#[serde(with = "...")]`. These are re-exported at the crate root.
+
+use crate::ParseError;
+use alloc::{string::String, vec::Vec};
+use core::fmt::Display;
+use core::ops::{Deref, DerefMut};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+/// Deserialize string and decode it into key.
+fn deserialize_decoded<'de, K, D, E, const N: usize>(
+    deserializer: D,
+    decode: impl FnOnce(&str) -> Result<Vec<u8>, E>,
+) -> Result<K, D::Error>
+where
+    K: Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+    D: Deserializer<'de>,
+    E: Display,
+{
+    use serde::de::Error;
+    let encoded = Zeroizing::new(String::deserialize(deserializer)?);
+    let data = Zeroizing::new(decode(encoded.trim()).map_err(D::Error::custom)?);
+    K::try_from(&data[..]).map_err(D::Error::custom)
+}
+
+/// Serialize keys as lowercase hex. Deserializing accepts either case and a `0x` prefix.
+#[cfg(feature = "hex")]
+pub mod as_hex {
+    use super::*;
+
+    /// Serialize key as lowercase hex.
+    pub fn serialize<K, S, const N: usize>(key: &K, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Deref<Target = [u8; N]>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&Zeroizing::new(hex::encode(key.as_slice())))
+    }
+
+    /// Deserialize key from hex.
+    pub fn deserialize<'de, K, D, const N: usize>(deserializer: D) -> Result<K, D::Error>
+    where
+        K: Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+        D: Deserializer<'de>,
+    {
+        deserialize_decoded(deserializer, |data| {
+            hex::decode(crate::strip_hex_prefix(data.as_bytes()))
+        })
+    }
+}
+
+/// Serialize keys as uppercase base32 with padding. Deserializing accepts either case, with or
+/// without padding.
+#[cfg(feature = "base32")]
+pub mod as_base32 {
+    use super::*;
+
+    const ALPHABET: base32::Alphabet = base32::Alphabet::RFC4648 { padding: true };
+
+    /// Serialize key as base32.
+    pub fn serialize<K, S, const N: usize>(key: &K, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Deref<Target = [u8; N]>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&Zeroizing::new(base32::encode(ALPHABET, key.as_slice())))
+    }
+
+    /// Deserialize key from base32.
+    pub fn deserialize<'de, K, D, const N: usize>(deserializer: D) -> Result<K, D::Error>
+    where
+        K: Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+        D: Deserializer<'de>,
+    {
+        deserialize_decoded(deserializer, |data| {
+            base32::decode(ALPHABET, data).ok_or(ParseError::Base32Error)
+        })
+    }
+}
+
+/// Serialize keys as base64 with the urlsafe alphabet and padding. Deserializing also accepts
+/// keys without padding.
+#[cfg(feature = "base64")]
+pub mod as_base64_urlsafe {
+    use super::*;
+
+    /// Serialize key as urlsafe base64.
+    pub fn serialize<K, S, const N: usize>(key: &K, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Deref<Target = [u8; N]>,
+        S: Serializer,
+    {
+        let encoded = Zeroizing::new(base64::encode_config(key.as_slice(), base64::URL_SAFE));
+        serializer.serialize_str(&encoded)
+    }
+
+    /// Deserialize key from urlsafe base64.
+    pub fn deserialize<'de, K, D, const N: usize>(deserializer: D) -> Result<K, D::Error>
+    where
+        K: Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+        D: Deserializer<'de>,
+    {
+        deserialize_decoded(deserializer, |data| {
+            base64::decode_config(data.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+        })
+    }
+}
+
+/// Serialize keys as raw bytes, in the same way as byte arrays, even in human readable formats.
+pub mod as_bytes {
+    use super::*;
+
+    /// Serialize key as bytes.
+    pub fn serialize<K, S, const N: usize>(key: &K, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Deref<Target = [u8; N]>,
+        S: Serializer,
+    {
+        crate::serialize_bytes(key, serializer)
+    }
+
+    /// Deserialize key from bytes.
+    pub fn deserialize<'de, K, D, const N: usize>(deserializer: D) -> Result<K, D::Error>
+    where
+        K: Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let data = Zeroizing::new(crate::deserialize_bytes::<D, N>(deserializer)?);
+        K::try_from(&data[..]).map_err(D::Error::custom)
+    }
+}
+
+/// Wrapper which serializes keys as raw bytes, in the same way as [as_bytes], even in formats
+/// which are human readable. This is useful for keys nested in generic containers, such as
+/// `Vec<Raw<Pubkey>>`, where `#[serde(with = "...")]` cannot be used.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Raw<K>(pub K);
+
+impl<K> Raw<K> {
+    /// Unwrap key.
+    pub fn into_inner(self) -> K {
+        self.0
+    }
+}
+
+impl<K> From<K> for Raw<K> {
+    fn from(key: K) -> Self {
+        Raw(key)
+    }
+}
+
+impl<K> Deref for Raw<K> {
+    type Target = K;
+    fn deref(&self) -> &K {
+        &self.0
+    }
+}
+
+impl<K> DerefMut for Raw<K> {
+    fn deref_mut(&mut self) -> &mut K {
+        &mut self.0
+    }
+}
+
+impl<K, const N: usize> Serialize for Raw<K>
+where
+    K: Deref<Target = [u8; N]>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        as_bytes::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, K, const N: usize> Deserialize<'de> for Raw<K>
+where
+    K: Deref<Target = [u8; N]> + for<'a> TryFrom<&'a [u8], Error = ParseError>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        as_bytes::deserialize(deserializer).map(Raw)
+    }
+}
+
+#[cfg(test)]
+use crate::{Privkey, Pubkey, Secret};
+
+#[cfg(test)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Fields {
+    #[cfg(feature = "hex")]
+    #[serde(with = "crate::as_hex")]
+    hex: Pubkey,
+    #[cfg(feature = "base32")]
+    #[serde(with = "crate::as_base32")]
+    base32: Secret,
+    #[cfg(feature = "base64")]
+    #[serde(with = "crate::as_base64_urlsafe")]
+    base64_urlsafe: Privkey,
+    #[serde(with = "crate::as_bytes")]
+    bytes: Pubkey,
+}
+
+#[test]
+fn test_serde_as() {
+    let fields = Fields {
+        #[cfg(feature = "hex")]
+        hex: Pubkey::new([0xab; 32]),
+        #[cfg(feature = "base32")]
+        base32: Secret::new([0; 32]),
+        #[cfg(feature = "base64")]
+        base64_urlsafe: Privkey::new([0xff; 32]),
+        bytes: Pubkey::new([1; 32]),
+    };
+    let json = serde_json::to_value(&fields).unwrap();
+    #[cfg(feature = "hex")]
+    assert_eq!(json["hex"], "ab".repeat(32));
+    #[cfg(feature = "base32")]
+    assert_eq!(json["base32"], format!("{}AAAA====", "A".repeat(48)));
+    #[cfg(feature = "base64")]
+    assert_eq!(json["base64_urlsafe"], format!("{}8=", "_".repeat(42)));
+    assert_eq!(json["bytes"], serde_json::Value::from(vec![1; 32]));
+    assert_eq!(serde_json::from_value::<Fields>(json).unwrap(), fields);
+}
+
+#[test]
+fn test_serde_as_lenient() {
+    #[cfg(feature = "hex")]
+    {
+        #[derive(Deserialize)]
+        struct Hex(#[serde(with = "crate::as_hex")] Pubkey);
+        let Hex(key) = serde_json::from_str(&format!("\"0x{}\"", "AB".repeat(32))).unwrap();
+        assert_eq!(key, Pubkey::new([0xab; 32]));
+        assert!(serde_json::from_str::<Hex>("\"abab\"").is_err());
+    }
+    #[cfg(feature = "base32")]
+    {
+        #[derive(Deserialize)]
+        struct Base32(#[serde(with = "crate::as_base32")] Secret);
+        let Base32(key) = serde_json::from_str(&format!("\"{}\"", "a".repeat(52))).unwrap();
+        assert_eq!(key, Secret::new([0; 32]));
+    }
+    #[cfg(feature = "base64")]
+    {
+        #[derive(Deserialize)]
+        struct Base64(#[serde(with = "crate::as_base64_urlsafe")] Privkey);
+        let Base64(key) = serde_json::from_str(&format!("\"{}8\"", "_".repeat(42))).unwrap();
+        assert_eq!(key, Privkey::new([0xff; 32]));
+    }
+}
+
+#[test]
+fn test_raw() {
+    use serde_test::{assert_tokens, Configure, Token};
+    let keys = vec![Raw(Pubkey::new([1; 32])), Raw(Pubkey::new([2; 32]))];
+    let json = serde_json::to_string(&keys).unwrap();
+    assert_eq!(
+        json,
+        format!("[[{}],[{}]]", ["1"; 32].join(","), ["2"; 32].join(","))
+    );
+    assert_eq!(
+        serde_json::from_str::<Vec<Raw<Pubkey>>>(&json).unwrap(),
+        keys
+    );
+    assert!(serde_json::from_str::<Raw<Pubkey>>("[1, 2]").is_err());
+
+    let raw = Raw::from(Secret::new([3; 32]));
+    let mut tokens = vec![Token::Tuple { len: 32 }];
+    tokens.extend([Token::U8(3); 32]);
+    tokens.push(Token::TupleEnd);
+    #[cfg(feature = "serde-bytes")]
+    let tokens = [Token::Bytes(&[3; 32])];
+    assert_tokens(&raw.readable(), &tokens);
+    assert_tokens(&raw.compact(), &tokens);
+    assert_eq!(raw.into_inner(), Secret::new([3; 32]));
+}
@@ -0,0 +1,261 @@
+//! Reading and writing keys stored in [SOPS](https://getsops.io) encrypted YAML or JSON
+//! documents, as commonly used in GitOps repositories to keep per-host keys encrypted at rest.
+//!
+//! Encryption and decryption are delegated to the `sops` binary, which picks up its keys (age,
+//! PGP or a cloud KMS) from its usual configuration. Environment variables such as
+//! `SOPS_AGE_KEY_FILE` can be passed using [Sops::env]. Writing requires sops 3.10 or later,
+//! which can read new values from standard input, so that keys never show up in process
+//! arguments.
+//!
+//! Keys are addressed by the path of mapping keys leading to them in the document:
+//!
+//! ```no_run
+//! # use wireguard_keys::{Privkey, sops::Sops};
+//! let sops = Sops::new().env("SOPS_AGE_KEY_FILE", "/etc/sops/age.txt");
+//! let privkey: Privkey = sops.read("hosts.yaml", &["hosts", "web1", "privatekey"]).unwrap();
+//! ```
+
+use crate::{CapabilityUnavailable, ParseError};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::str::FromStr;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Default name of the sops binary, which is looked up in `PATH`.
+pub const SOPS_BINARY: &str = "sops";
+
+/// Possible errors that can be generated when reading or writing keys using sops.
+#[derive(Error, Debug)]
+pub enum SopsError {
+    /// Error running sops
+    #[error("error running sops")]
+    Io(#[from] io::Error),
+    /// sops binary is not installed
+    #[error(transparent)]
+    Unavailable(CapabilityUnavailable),
+    /// sops exited with an error
+    #[error("sops failed with status {code:?}: {message}")]
+    Failed {
+        /// Exit code, if sops was not killed by a signal
+        code: Option<i32>,
+        /// Error message written by sops
+        message: String,
+    },
+    /// Path is empty or contains control characters
+    #[error("invalid path")]
+    Path,
+    /// Value is not valid UTF-8
+    #[error("value is not valid UTF-8")]
+    Utf8,
+    /// Value is not a valid key
+    #[error("error parsing key")]
+    Parse(#[from] ParseError),
+}
+
+/// sops binary was not found.
+const SOPS_UNAVAILABLE: CapabilityUnavailable = CapabilityUnavailable::new(
+    "sops",
+    "install sops or set the path to its binary using Sops::binary",
+);
+
+/// Handle for running the sops binary.
+#[derive(Clone, Debug)]
+pub struct Sops {
+    binary: PathBuf,
+    env: Vec<(OsString, OsString)>,
+}
+
+impl Default for Sops {
+    fn default() -> Self {
+        Sops {
+            binary: SOPS_BINARY.into(),
+            env: Vec::new(),
+        }
+    }
+}
+
+/// Write string as quoted JSON string, as used by sops for paths and values.
+fn write_quoted(f: &mut String, value: &str) -> Result<(), SopsError> {
+    if value.chars().any(char::is_control) {
+        return Err(SopsError::Path);
+    }
+    f.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            f.push('\\');
+        }
+        f.push(c);
+    }
+    f.push('"');
+    Ok(())
+}
+
+/// Convert path to the index syntax used by sops, such as `["hosts"]["web1"]`.
+fn index(path: &[&str]) -> Result<String, SopsError> {
+    if path.is_empty() {
+        return Err(SopsError::Path);
+    }
+    let mut index = String::new();
+    for component in path {
+        index.push('[');
+        write_quoted(&mut index, component)?;
+        index.push(']');
+    }
+    Ok(index)
+}
+
+impl Sops {
+    /// Create handle using the sops binary found in `PATH`.
+    pub fn new() -> Self {
+        Sops::default()
+    }
+
+    /// Set path of the sops binary.
+    pub fn binary(mut self, binary: impl Into<PathBuf>) -> Self {
+        self.binary = binary.into();
+        self
+    }
+
+    /// Set environment variable for sops, such as `SOPS_AGE_KEY_FILE`.
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.env
+            .push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.binary);
+        command.envs(self.env.iter().map(|(key, value)| (key, value)));
+        command
+    }
+
+    fn spawn_error(error: io::Error) -> SopsError {
+        if error.kind() == io::ErrorKind::NotFound {
+            SopsError::Unavailable(SOPS_UNAVAILABLE)
+        } else {
+            SopsError::Io(error)
+        }
+    }
+
+    fn check(output: &Output) -> Result<(), SopsError> {
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(SopsError::Failed {
+            code: output.status.code(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+
+    /// Decrypt document and read the key at the given path.
+    pub fn read<K>(&self, file: impl AsRef<Path>, path: &[&str]) -> Result<K, SopsError>
+    where
+        K: FromStr<Err = ParseError>,
+    {
+        let mut output = self
+            .command()
+            .arg("--decrypt")
+            .arg("--extract")
+            .arg(index(path)?)
+            .arg(file.as_ref())
+            .stdin(Stdio::null())
+            .output()
+            .map_err(Sops::spawn_error)?;
+        let stdout = Zeroizing::new(std::mem::take(&mut output.stdout));
+        Sops::check(&output)?;
+        let value = std::str::from_utf8(&stdout).map_err(|_| SopsError::Utf8)?;
+        Ok(value.trim().parse()?)
+    }
+
+    /// Set the key at the given path in the document, encrypting it. Mappings along the path
+    /// are created if they do not exist yet.
+    pub fn write<K: fmt::Display>(
+        &self,
+        file: impl AsRef<Path>,
+        path: &[&str],
+        key: &K,
+    ) -> Result<(), SopsError> {
+        let mut value = Zeroizing::new(String::new());
+        let encoded = Zeroizing::new(key.to_string());
+        write_quoted(&mut value, &encoded)?;
+        let mut child = self
+            .command()
+            .arg("set")
+            .arg("--value-stdin")
+            .arg(file.as_ref())
+            .arg(index(path)?)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Sops::spawn_error)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(value.as_bytes())?;
+        }
+        Sops::check(&child.wait_with_output()?)
+    }
+}
+
+#[test]
+fn test_sops_index() {
+    assert_eq!(
+        index(&["hosts", "web1", "privatekey"]).unwrap(),
+        r#"["hosts"]["web1"]["privatekey"]"#
+    );
+    assert_eq!(index(&[r#"a"b\"#]).unwrap(), r#"["a\"b\\"]"#);
+    assert!(matches!(index(&[]), Err(SopsError::Path)));
+    assert!(matches!(index(&["a\nb"]), Err(SopsError::Path)));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sops_read_write() {
+    use crate::Privkey;
+    use std::os::unix::fs::PermissionsExt;
+
+    // stand-in for sops which stores the index and value in plain text
+    const FAKE_SOPS: &str = r#"#!/bin/sh
+[ "$TEST_VARIABLE" = set ] || exit 3
+if [ "$1" = set ] && [ "$2" = --value-stdin ]; then
+    { printf '%s\n' "$4"; cat; } > "$3"
+elif [ "$1" = --decrypt ] && [ "$2" = --extract ]; then
+    { read -r index; read -r value; } < "$4"
+    [ "$index" = "$3" ] || { echo "component not found" >&2; exit 100; }
+    printf '%s\n' "$value" | tr -d '"'
+else
+    exit 2
+fi
+"#;
+
+    let directory =
+        std::env::temp_dir().join(format!("wireguard-keys-sops-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let binary = directory.join("sops");
+    std::fs::write(&binary, FAKE_SOPS).unwrap();
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let document = directory.join("hosts.yaml");
+    let sops = Sops::new().binary(&binary).env("TEST_VARIABLE", "set");
+
+    let privkey = Privkey::generate();
+    let path = ["hosts", "web1", "privatekey"];
+    sops.write(&document, &path, &privkey).unwrap();
+    assert_eq!(sops.read::<Privkey>(&document, &path).unwrap(), privkey);
+    match sops.read::<Privkey>(&document, &["hosts", "web2", "privatekey"]) {
+        Err(SopsError::Failed { code, message }) => {
+            assert_eq!(code, Some(100));
+            assert_eq!(message, "component not found");
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(matches!(
+        Sops::new()
+            .binary(directory.join("missing"))
+            .read::<Privkey>(&document, &path),
+        Err(SopsError::Unavailable(SOPS_UNAVAILABLE))
+    ));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,166 @@
+//! Generators for property tests of code handling keys, using
+//! [proptest](https://docs.rs/proptest) strategies or [quickcheck](https://docs.rs/quickcheck),
+//! for which the key types implement [Arbitrary].
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use wireguard_keys::{testing, Pubkey};
+//!
+//! proptest! {
+//!     fn roundtrip(pubkey in testing::pubkey()) {
+//!         prop_assert_eq!(pubkey.to_string().parse::<Pubkey>().unwrap(), pubkey);
+//!     }
+//!
+//!     fn rejected(encoded in testing::corrupted_base64()) {
+//!         prop_assert!(encoded.parse::<Pubkey>().is_err());
+//!     }
+//! }
+//! # roundtrip();
+//! # rejected();
+//! ```
+
+use crate::{Privkey, Pubkey, Secret};
+use proptest::prelude::*;
+use quickcheck::{Arbitrary, Gen};
+
+/// Public keys of low order on Curve25519, which result in an all-zero shared secret with any
+/// private key. Peers must not accept these.
+pub const LOW_ORDER_PUBKEYS: [[u8; 32]; 7] = [
+    // 0 (order 4)
+    [0; 32],
+    // 1 (order 1)
+    [
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ],
+    // order 8
+    [
+        0xe0, 0xeb, 0x7a, 0x7c, 0x3b, 0x41, 0xb8, 0xae, 0x16, 0x56, 0xe3, 0xfa, 0xf1, 0x9f, 0xc4,
+        0x6a, 0xda, 0x09, 0x8d, 0xeb, 0x9c, 0x32, 0xb1, 0xfd, 0x86, 0x62, 0x05, 0x16, 0x5f, 0x49,
+        0xb8, 0x00,
+    ],
+    // order 8
+    [
+        0x5f, 0x9c, 0x95, 0xbc, 0xa3, 0x50, 0x8c, 0x24, 0xb1, 0xd0, 0xb1, 0x55, 0x9c, 0x83, 0xef,
+        0x5b, 0x04, 0x44, 0x5c, 0xc4, 0x58, 0x1c, 0x8e, 0x86, 0xd8, 0x22, 0x4e, 0xdd, 0xd0, 0x9f,
+        0x11, 0x57,
+    ],
+    // p - 1 (order 2)
+    [
+        0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+    // p, which is 0
+    [
+        0xed, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+    // p + 1, which is 1
+    [
+        0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+];
+
+/// Characters which are not part of any encoding accepted by [Pubkey::parse].
+const INVALID_CHARS: &[char] = &['!', '#', '$', '%', '&', '*', '.', ';', '?', '@', '^', '~'];
+
+/// Strategy generating any public key.
+pub fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new)
+}
+
+/// Strategy generating any private key.
+pub fn privkey() -> impl Strategy<Value = Privkey> {
+    any::<[u8; 32]>().prop_map(Privkey::new)
+}
+
+/// Strategy generating any preshared key.
+pub fn secret() -> impl Strategy<Value = Secret> {
+    any::<[u8; 32]>().prop_map(Secret::new)
+}
+
+/// Strategy generating weak public keys, which have low order and must be rejected by peers.
+/// The unused top bit is set at random, as implementations ignore it.
+pub fn weak_pubkey() -> impl Strategy<Value = Pubkey> {
+    (prop::sample::select(&LOW_ORDER_PUBKEYS[..]), any::<bool>()).prop_map(|(mut data, high)| {
+        data[31] |= (high as u8) << 7;
+        Pubkey::new(data)
+    })
+}
+
+/// Strategy generating base64 strings which are a valid key with a single character replaced,
+/// removed or added. These are rejected by parsing in any encoding.
+pub fn corrupted_base64() -> impl Strategy<Value = String> {
+    let replaced = (pubkey(), 0..43usize, prop::sample::select(INVALID_CHARS)).prop_map(
+        |(key, index, invalid)| {
+            let mut encoded = key.to_base64();
+            encoded.replace_range(index..index + 1, invalid.encode_utf8(&mut [0; 4]));
+            encoded
+        },
+    );
+    let removed = (pubkey(), 0..43usize).prop_map(|(key, index)| {
+        let mut encoded = key.to_base64();
+        encoded.remove(index);
+        encoded
+    });
+    let padded = pubkey().prop_map(|key| key.to_base64() + "=");
+    prop_oneof![replaced, removed, padded]
+}
+
+macro_rules! impl_quickcheck {
+    ($type:ty) => {
+        impl Arbitrary for $type {
+            fn arbitrary(gen: &mut Gen) -> Self {
+                let mut data = [0; 32];
+                for byte in &mut data {
+                    *byte = u8::arbitrary(gen);
+                }
+                <$type>::new(data)
+            }
+        }
+    };
+}
+
+impl_quickcheck!(Pubkey);
+impl_quickcheck!(Privkey);
+impl_quickcheck!(Secret);
+
+#[test]
+fn test_weak_pubkey() {
+    use proptest::test_runner::TestRunner;
+    use x25519_dalek_fiat::{PublicKey, StaticSecret};
+    TestRunner::default()
+        .run(&(weak_pubkey(), privkey()), |(pubkey, privkey)| {
+            let secret = StaticSecret::from(*privkey);
+            let shared = secret.diffie_hellman(&PublicKey::from(*pubkey));
+            prop_assert_eq!(shared.to_bytes(), [0; 32]);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_corrupted_base64() {
+    use proptest::test_runner::TestRunner;
+    TestRunner::default()
+        .run(&corrupted_base64(), |encoded| {
+            prop_assert!(encoded.parse::<Pubkey>().is_err());
+            prop_assert!(Pubkey::parse_lenient(&encoded).is_err());
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_quickcheck() {
+    fn roundtrip(privkey: Privkey, secret: Secret) -> bool {
+        privkey.to_string().parse::<Privkey>().unwrap() == privkey
+            && secret.to_string().parse::<Secret>().unwrap() == secret
+    }
+    quickcheck::quickcheck(roundtrip as fn(Privkey, Secret) -> bool);
+}
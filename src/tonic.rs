@@ -0,0 +1,139 @@
+//! Helpers for gRPC services built with [tonic](https://docs.rs/tonic), which usually carry keys
+//! as raw bytes in fields such as `bytes public_key = 1;`.
+//!
+//! Fields are decoded with [decode_key], which reports invalid keys as `INVALID_ARGUMENT` with
+//! the name of the field:
+//!
+//! ```
+//! use tonic::{Code, Status};
+//! use wireguard_keys::{tonic::decode_key, Pubkey};
+//!
+//! fn peer_key(public_key: &[u8]) -> Result<Pubkey, Status> {
+//!     decode_key("public_key", public_key)
+//! }
+//!
+//! assert_eq!(peer_key(&[1; 32]).unwrap(), Pubkey::new([1; 32]));
+//! assert_eq!(peer_key(&[1; 31]).unwrap_err().code(), Code::InvalidArgument);
+//! ```
+//!
+//! Peers identified by a key in the request metadata can be authenticated with
+//! [pubkey_interceptor], which makes the key available to handlers through [request_pubkey].
+
+// tonic APIs return `Status` as error, which is large
+#![allow(clippy::result_large_err)]
+
+use crate::header::{parse_values, HeaderError};
+use crate::{ParseError, Pubkey};
+use tonic::{Request, Status};
+
+/// Metadata key read by [pubkey_interceptor].
+pub const PUBKEY_METADATA_KEY: &str = "x-wireguard-pubkey";
+
+impl From<ParseError> for Status {
+    fn from(error: ParseError) -> Self {
+        Status::invalid_argument(format!("invalid key: {error}"))
+    }
+}
+
+/// Decode key from the raw bytes of a message field.
+pub fn decode_key<K>(field: &str, data: &[u8]) -> Result<K, Status>
+where
+    K: for<'a> TryFrom<&'a [u8], Error = ParseError>,
+{
+    K::try_from(data)
+        .map_err(|error| Status::invalid_argument(format!("invalid key in {field}: {error}")))
+}
+
+/// Decode key from the raw bytes of a message field which may be unset. Proto3 does not
+/// distinguish unset from empty byte fields, so empty fields are returned as `None`.
+pub fn decode_optional_key<K>(field: &str, data: &[u8]) -> Result<Option<K>, Status>
+where
+    K: for<'a> TryFrom<&'a [u8], Error = ParseError>,
+{
+    if data.is_empty() {
+        return Ok(None);
+    }
+    decode_key(field, data).map(Some)
+}
+
+/// Interceptor which parses the public key in the [PUBKEY_METADATA_KEY] metadata of requests,
+/// using the same rules as the request guards in [crate::header]. Requests without the key are
+/// rejected as `UNAUTHENTICATED`, requests with an invalid key as `INVALID_ARGUMENT`.
+/// Services still have to check that the key is allowed.
+pub fn pubkey_interceptor(mut request: Request<()>) -> Result<Request<()>, Status> {
+    let values = request
+        .metadata()
+        .get_all(PUBKEY_METADATA_KEY)
+        .iter()
+        .map(|value| value.to_str())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| HeaderError::Encoding(PUBKEY_METADATA_KEY))
+        .and_then(|values| parse_values::<Pubkey, _>(PUBKEY_METADATA_KEY, values));
+    match values {
+        Ok(pubkey) => {
+            request.extensions_mut().insert(pubkey);
+            Ok(request)
+        }
+        Err(error @ HeaderError::Missing(_)) => Err(Status::unauthenticated(error.to_string())),
+        Err(error) => Err(Status::invalid_argument(error.to_string())),
+    }
+}
+
+/// Public key of a request which passed [pubkey_interceptor].
+pub fn request_pubkey<T>(request: &Request<T>) -> Option<Pubkey> {
+    request.extensions().get::<Pubkey>().copied()
+}
+
+#[test]
+fn test_tonic_decode() {
+    use crate::Secret;
+    use tonic::Code;
+
+    let pubkey: Pubkey = decode_key("public_key", &[1; 32]).unwrap();
+    assert_eq!(pubkey, Pubkey::new([1; 32]));
+    let status = decode_key::<Secret>("preshared_key", &[1; 31]).unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert!(status.message().contains("preshared_key"));
+    assert_eq!(
+        decode_optional_key::<Pubkey>("public_key", &[]).unwrap(),
+        None
+    );
+    assert_eq!(
+        decode_optional_key("public_key", &[1; 32]).unwrap(),
+        Some(pubkey)
+    );
+    assert_eq!(
+        Status::from(ParseError::Length).code(),
+        Code::InvalidArgument
+    );
+}
+
+#[cfg(any(
+    feature = "hex",
+    feature = "base64",
+    feature = "base32",
+    feature = "base58",
+    feature = "proquint"
+))]
+#[test]
+fn test_tonic_interceptor() {
+    use tonic::Code;
+
+    let pubkey = Pubkey::new([1; 32]);
+    let mut request = Request::new(());
+    request
+        .metadata_mut()
+        .insert(PUBKEY_METADATA_KEY, pubkey.to_string().parse().unwrap());
+    let request = pubkey_interceptor(request).unwrap();
+    assert_eq!(request_pubkey(&request), Some(pubkey));
+
+    let status = pubkey_interceptor(Request::new(())).unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    let mut request = Request::new(());
+    request
+        .metadata_mut()
+        .insert(PUBKEY_METADATA_KEY, "abc".parse().unwrap());
+    let status = pubkey_interceptor(request).unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(request_pubkey(&Request::new(())), None);
+}
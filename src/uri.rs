@@ -0,0 +1,366 @@
+//! Encoding of tunnel configurations as `wireguard://` URIs, so that they can be shared as
+//! links. A URI describes a tunnel with a single peer: the endpoint of the peer is the
+//! authority of the URI, and everything else is encoded as query parameters.
+//!
+//! ```text
+//! wireguard://vpn.example.com:51820?privatekey=...&publickey=...&allowedips=0.0.0.0%2F0
+//! ```
+//!
+//! | Parameter      | Field                                   |
+//! |----------------|-----------------------------------------|
+//! | `privatekey`   | [Interface::private_key]                |
+//! | `address`      | [Interface::address]                    |
+//! | `dns`          | [Interface::dns]                        |
+//! | `mtu`          | [Interface::mtu]                        |
+//! | `listenport`   | [Interface::listen_port]                |
+//! | `name`         | [Peer::name]                            |
+//! | `publickey`    | [Peer::public_key]                      |
+//! | `presharedkey` | [Peer::preshared_key]                   |
+//! | `allowedips`   | [Peer::allowed_ips]                     |
+//! | `keepalive`    | [Peer::persistent_keepalive]            |
+//!
+//! Other interface settings, in particular hook commands, are never encoded in URIs. Decoded
+//! values must not contain control characters, and values other than `name` no whitespace, so
+//! that a link can not smuggle additional lines into the configurations written from it. The
+//! interface is built using [Interface::builder], which validates it in the same way as
+//! programmatically created interfaces.
+//!
+//! Parsing is forgiving: the `wg://` scheme is accepted as well, parameter names are
+//! case-insensitive, unknown parameters are ignored, list parameters may be repeated, keys may
+//! use any supported encoding and a literal `+` is not treated as a space, so that base64 keys
+//! which were not percent-encoded still work. Output is canonical: parameters are always
+//! written in the order above and everything except unreserved characters and commas is
+//! percent-encoded.
+
+use crate::allowed_ips::AllowedIps;
+use crate::config::{BuildError, Interface, Peer, TunnelConfig};
+use crate::endpoint::EndpointError;
+use crate::{Privkey, Pubkey, Secret};
+use std::fmt::Write;
+use thiserror::Error;
+
+/// Possible errors that can be generated when parsing or emitting tunnel URIs.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum UriError {
+    /// URI does not use the `wireguard://` or `wg://` scheme
+    #[error("not a wireguard:// URI")]
+    Scheme,
+    /// URI contains an invalid percent-encoded sequence
+    #[error("invalid percent-encoding")]
+    Encoding,
+    /// Required parameter is missing
+    #[error("missing parameter {0}")]
+    Missing(&'static str),
+    /// Parameter has an invalid value
+    #[error("invalid value for parameter {0}")]
+    Value(String),
+    /// Authority of the URI is not a valid endpoint
+    #[error("invalid endpoint")]
+    Endpoint(#[from] EndpointError),
+    /// URIs can only encode tunnels with exactly one peer
+    #[error("tunnel has {0} peers, URIs need exactly one")]
+    PeerCount(usize),
+}
+
+/// Percent-encode a value, keeping unreserved characters and the given separators.
+fn encode(value: &str, separators: &[u8]) -> String {
+    let mut output = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric()
+            || matches!(byte, b'-' | b'.' | b'_' | b'~')
+            || separators.contains(&byte)
+        {
+            output.push(byte as char);
+        } else {
+            write!(output, "%{:02X}", byte).unwrap();
+        }
+    }
+    output
+}
+
+/// Decode a percent-encoded value.
+fn decode(value: &str) -> Result<String, UriError> {
+    let bytes = value.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3).ok_or(UriError::Encoding)?;
+            // from_str_radix would also accept a sign
+            if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+                return Err(UriError::Encoding);
+            }
+            output.push(u8::from_str_radix(hex, 16).map_err(|_| UriError::Encoding)?);
+            i += 3;
+        } else {
+            output.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(output).map_err(|_| UriError::Encoding)
+}
+
+/// Split comma-separated list parameter.
+fn split_list(value: &str) -> impl Iterator<Item = &str> + '_ {
+    value.split(',').filter(|item| !item.is_empty())
+}
+
+/// Map error building the interface to the parameter which caused it.
+fn build_error(error: BuildError) -> UriError {
+    match error {
+        BuildError::Missing(_) => UriError::Missing("privatekey"),
+        BuildError::Address { .. } => UriError::Value("address".into()),
+        BuildError::Dns(_) => UriError::Value("dns".into()),
+        BuildError::Mtu(_) => UriError::Value("mtu".into()),
+        BuildError::AllowedIp { .. }
+        | BuildError::Endpoint { .. }
+        | BuildError::Hook
+        | BuildError::Table(_) => {
+            unreachable!("not set from URI parameters")
+        }
+    }
+}
+
+impl TunnelConfig {
+    /// Parse tunnel configuration from a `wireguard://` or `wg://` URI.
+    pub fn from_uri(uri: &str) -> Result<Self, UriError> {
+        let uri = uri.trim();
+        let (scheme, rest) = uri.split_once("://").ok_or(UriError::Scheme)?;
+        if !scheme.eq_ignore_ascii_case("wireguard") && !scheme.eq_ignore_ascii_case("wg") {
+            return Err(UriError::Scheme);
+        }
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let authority = decode(authority.trim_end_matches('/'))?;
+
+        let mut public_key = None;
+        let mut interface = Interface::builder();
+        let mut peer = Peer::new(Pubkey::new([0; 32]));
+        if !authority.is_empty() {
+            peer.endpoint = Some(authority.parse()?);
+        }
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let name = decode(name)?.to_ascii_lowercase();
+            let value = decode(value)?;
+            let invalid = || UriError::Value(name.clone());
+            if value
+                .chars()
+                .any(|c| c.is_control() || (c.is_whitespace() && name != "name"))
+            {
+                return Err(invalid());
+            }
+            match name.as_str() {
+                "privatekey" => {
+                    interface =
+                        interface.private_key(Privkey::parse(&value).map_err(|_| invalid())?)
+                }
+                "address" => {
+                    let address: AllowedIps = value.parse().map_err(|_| invalid())?;
+                    for cidr in address.iter() {
+                        interface = interface.address(cidr);
+                    }
+                }
+                "dns" => {
+                    for entry in split_list(&value) {
+                        interface = interface.dns(entry);
+                    }
+                }
+                "mtu" => interface = interface.mtu(value.parse().map_err(|_| invalid())?),
+                "listenport" => {
+                    interface = interface.listen_port(value.parse().map_err(|_| invalid())?)
+                }
+                "name" => peer.name = Some(value.trim().to_string()),
+                "publickey" => public_key = Some(Pubkey::parse(&value).map_err(|_| invalid())?),
+                "presharedkey" => {
+                    peer.preshared_key = Some(Secret::parse(&value).map_err(|_| invalid())?)
+                }
+                "allowedips" => {
+                    let allowed_ips: AllowedIps = value.parse().map_err(|_| invalid())?;
+                    peer.allowed_ips.extend(allowed_ips)
+                }
+                "keepalive" => {
+                    let interval: u16 = value.parse().map_err(|_| invalid())?;
+                    peer.persistent_keepalive = Some(interval).filter(|interval| *interval != 0);
+                }
+                _ => {}
+            }
+        }
+        let interface = interface.build().map_err(build_error)?;
+        peer.public_key = public_key.ok_or(UriError::Missing("publickey"))?;
+        Ok(TunnelConfig {
+            interface,
+            peers: vec![peer],
+        })
+    }
+
+    /// Encode tunnel configuration as a canonical `wireguard://` URI.
+    pub fn to_uri(&self) -> Result<String, UriError> {
+        let peer = match self.peers.as_slice() {
+            [peer] => peer,
+            peers => return Err(UriError::PeerCount(peers.len())),
+        };
+        let interface = &self.interface;
+        let mut params = vec![("privatekey", interface.private_key.to_base64())];
+        if !interface.address.is_empty() {
+            let address: Vec<String> = interface.address.iter().map(ToString::to_string).collect();
+            params.push(("address", address.join(",")));
+        }
+        if !interface.dns.is_empty() {
+            params.push(("dns", interface.dns.join(",")));
+        }
+        if let Some(mtu) = interface.mtu {
+            params.push(("mtu", mtu.to_string()));
+        }
+        if let Some(port) = interface.listen_port {
+            params.push(("listenport", port.to_string()));
+        }
+        if let Some(name) = &peer.name {
+            params.push(("name", name.clone()));
+        }
+        params.push(("publickey", peer.public_key.to_base64()));
+        if let Some(preshared_key) = &peer.preshared_key {
+            params.push(("presharedkey", preshared_key.to_base64()));
+        }
+        if !peer.allowed_ips.is_empty() {
+            let allowed_ips: Vec<String> =
+                peer.allowed_ips.iter().map(ToString::to_string).collect();
+            params.push(("allowedips", allowed_ips.join(",")));
+        }
+        if let Some(interval) = peer.persistent_keepalive {
+            params.push(("keepalive", interval.to_string()));
+        }
+
+        let mut uri = String::from("wireguard://");
+        if let Some(endpoint) = &peer.endpoint {
+            uri.push_str(&encode(&endpoint.to_string(), b":[]"));
+        }
+        for (index, (name, value)) in params.iter().enumerate() {
+            uri.push(if index == 0 { '?' } else { '&' });
+            uri.push_str(name);
+            uri.push('=');
+            uri.push_str(&encode(value, b","));
+        }
+        Ok(uri)
+    }
+}
+
+#[cfg(test)]
+const TEST_URI: &str = "wireguard://vpn.example.com:51820\
+    ?privatekey=yAnz5TF%2BlXXJte14tji3zlMNq%2Bhd2rYUIgJBgB3fBmk%3D\
+    &address=10.0.0.2%2F32,fd00%3A%3A2%2F128\
+    &dns=10.0.0.1\
+    &name=home%20server\
+    &publickey=xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg%3D\
+    &allowedips=0.0.0.0%2F0,%3A%3A%2F0\
+    &keepalive=25";
+
+#[test]
+fn test_uri_roundtrip() {
+    let config = TunnelConfig::from_uri(TEST_URI).unwrap();
+    assert_eq!(
+        config.interface.address,
+        vec![
+            "10.0.0.2/32".parse().unwrap(),
+            "fd00::2/128".parse().unwrap()
+        ]
+    );
+    assert_eq!(config.interface.dns, vec!["10.0.0.1"]);
+    let peer = &config.peers[0];
+    assert_eq!(peer.name.as_deref(), Some("home server"));
+    assert_eq!(
+        peer.endpoint,
+        Some("vpn.example.com:51820".parse().unwrap())
+    );
+    assert_eq!(peer.allowed_ips.to_string(), "0.0.0.0/0, ::/0");
+    assert_eq!(peer.persistent_keepalive, Some(25));
+    assert_eq!(config.to_uri().unwrap(), TEST_URI);
+}
+
+#[test]
+fn test_uri_forgiving() {
+    let uri = "WG://[fd00::1]:51820/?PublicKey=xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=\
+        &PrivateKey=yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=&AllowedIPs=10.0.0.0/8\
+        &AllowedIPs=10.1.0.0/16&unknown=1";
+    let config = TunnelConfig::from_uri(uri).unwrap();
+    let canonical = TunnelConfig::from_uri(TEST_URI).unwrap();
+    assert_eq!(
+        config.interface.private_key,
+        canonical.interface.private_key
+    );
+    assert_eq!(config.peers[0].public_key, canonical.peers[0].public_key);
+    assert_eq!(
+        config.peers[0].endpoint,
+        Some("[fd00::1]:51820".parse().unwrap())
+    );
+    assert_eq!(
+        config.peers[0].allowed_ips.to_string(),
+        "10.0.0.0/8, 10.1.0.0/16"
+    );
+    assert!(config
+        .to_uri()
+        .unwrap()
+        .starts_with("wireguard://[fd00::1]:51820?privatekey="));
+}
+
+#[test]
+fn test_uri_invalid() {
+    assert_eq!(
+        TunnelConfig::from_uri("https://example.com"),
+        Err(UriError::Scheme)
+    );
+    assert_eq!(
+        TunnelConfig::from_uri("wg://host?publickey=abc"),
+        Err(UriError::Endpoint(EndpointError::MissingPort))
+    );
+    assert_eq!(
+        TunnelConfig::from_uri("wg://host:1?publickey=%zz"),
+        Err(UriError::Encoding)
+    );
+    assert_eq!(
+        TunnelConfig::from_uri(
+            "wg://host:1?publickey=xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg="
+        ),
+        Err(UriError::Missing("privatekey"))
+    );
+    assert_eq!(
+        TunnelConfig::from_uri("wg://host:1?publickey=abc"),
+        Err(UriError::Value("publickey".into()))
+    );
+    assert_eq!(
+        TunnelConfig::from_uri("wg://host:1?publickey=%+1"),
+        Err(UriError::Encoding)
+    );
+    let mut config = TunnelConfig::from_uri(TEST_URI).unwrap();
+    config.peers.clear();
+    assert_eq!(config.to_uri(), Err(UriError::PeerCount(0)));
+}
+
+#[test]
+fn test_uri_injection() {
+    // line breaks in values would be written as separate lines, such as hook commands
+    for param in [
+        "dns=1.1.1.1%0APostUp%20%3D%20cmd",
+        "dns=1.1.1.1%0D%0APostUp%20%3D%20cmd",
+        "name=home%0APostUp%20%3D%20cmd",
+        "mtu=1420%0A",
+        "dns=1.1.1.1%201.0.0.1",
+    ] {
+        let uri = TEST_URI.replace("keepalive=25", param);
+        let name = param.split('=').next().unwrap();
+        assert_eq!(
+            TunnelConfig::from_uri(&uri),
+            Err(UriError::Value(name.into())),
+            "{}",
+            param
+        );
+    }
+    let uri = TEST_URI.replace("home%20server", "%20home%20server%20");
+    let config = TunnelConfig::from_uri(&uri).unwrap();
+    assert_eq!(config.peers[0].name.as_deref(), Some("home server"));
+    let uri = TEST_URI.replace("dns=10.0.0.1", "mtu=100");
+    assert_eq!(
+        TunnelConfig::from_uri(&uri),
+        Err(UriError::Value("mtu".into()))
+    );
+}
@@ -0,0 +1,92 @@
+//! Wrapper for keeping track of the history of a key. Every [VersionedKey] carries a monotonic
+//! version number, the time it was created and an optional comment. Versioned keys are ordered
+//! by version first, so sorting a history puts the most recent key last.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Key with version, creation time and optional comment.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VersionedKey<K> {
+    /// Monotonic version of this key.
+    pub version: u64,
+    /// Time at which this version was created.
+    pub created: SystemTime,
+    /// Key of this version.
+    pub key: K,
+    /// Optional comment, such as the reason for a rotation.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub comment: Option<String>,
+}
+
+impl<K> VersionedKey<K> {
+    /// Create first version of a key, created now.
+    pub fn new(key: K) -> Self {
+        Self::with_version(key, 0, SystemTime::now())
+    }
+
+    /// Create key with explicit version and creation time.
+    pub fn with_version(key: K, version: u64, created: SystemTime) -> Self {
+        VersionedKey {
+            version,
+            created,
+            key,
+            comment: None,
+        }
+    }
+
+    /// Set the comment of this key.
+    pub fn with_comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Create the next version, replacing the key. Returns `None` if the version would
+    /// overflow.
+    pub fn next(&self, key: K) -> Option<Self> {
+        Some(Self::with_version(
+            key,
+            self.version.checked_add(1)?,
+            SystemTime::now(),
+        ))
+    }
+
+    /// Convert the key, keeping version, creation time and comment.
+    pub fn map<T, F: FnOnce(K) -> T>(self, f: F) -> VersionedKey<T> {
+        VersionedKey {
+            version: self.version,
+            created: self.created,
+            key: f(self.key),
+            comment: self.comment,
+        }
+    }
+}
+
+impl<K> std::ops::Deref for VersionedKey<K> {
+    type Target = K;
+    fn deref(&self) -> &Self::Target {
+        &self.key
+    }
+}
+
+#[test]
+fn test_versioned_key() {
+    use crate::{Privkey, Pubkey};
+    let first = VersionedKey::new(Privkey::generate()).with_comment("initial");
+    let second = first.next(Privkey::generate()).unwrap();
+    assert_eq!(first.version, 0);
+    assert_eq!(second.version, 1);
+    assert_eq!(first.comment.as_deref(), Some("initial"));
+    assert_eq!(second.comment, None);
+    assert!(first < second);
+    let pubkey: VersionedKey<Pubkey> = second.clone().map(|key| key.pubkey());
+    assert_eq!(pubkey.version, second.version);
+    assert_eq!(*pubkey, second.pubkey());
+    let last = VersionedKey::with_version(Privkey::generate(), u64::MAX, SystemTime::now());
+    assert!(last.next(Privkey::generate()).is_none());
+}
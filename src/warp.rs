@@ -0,0 +1,197 @@
+//! Filters for [warp](https://docs.rs/warp) which extract keys from path segments and headers.
+//! Invalid keys are rejected with [KeyRejection], which [recover] turns into a response with
+//! status 400 and a JSON body describing the problem:
+//!
+//! ```
+//! use warp::Filter;
+//! use wireguard_keys::warp::{pubkey_header, pubkey_param, recover};
+//!
+//! let peer = warp::path("peers")
+//!     .and(pubkey_param())
+//!     .and(pubkey_header("X-WireGuard-Pubkey"))
+//!     .map(|pubkey, client| format!("{pubkey} requested by {client}"))
+//!     .recover(recover);
+//! ```
+//!
+//! Path segments are percent-decoded before parsing, so keys can be written as standard base64
+//! as well as urlsafe base64.
+
+use crate::header::{parse_values, HeaderError};
+use crate::{ParseError, Pubkey};
+use std::str::FromStr;
+use thiserror::Error;
+use warp::http::StatusCode;
+use warp::reject::{Reject, Rejection};
+use warp::reply::{Reply, Response};
+use warp::Filter;
+
+/// Rejection of filters reading keys.
+#[derive(Error, Debug)]
+pub enum KeyRejection {
+    /// Key in header is missing or invalid
+    #[error(transparent)]
+    Header(#[from] HeaderError),
+    /// Path segment is not valid percent-encoded UTF-8
+    #[error("invalid path segment")]
+    Segment,
+    /// Key in path is invalid
+    #[error("invalid key in path: {0}")]
+    Path(#[source] ParseError),
+}
+
+impl KeyRejection {
+    /// Machine-readable error code, which is written as `error` in the response body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            KeyRejection::Header(error) => error.code(),
+            KeyRejection::Segment => "invalid_path",
+            KeyRejection::Path(_) => "invalid_key",
+        }
+    }
+
+    /// Response with status 400 and a JSON body with the `error` code and a human-readable
+    /// `message`.
+    pub fn to_response(&self) -> Response {
+        let body = serde_json::json!({
+            "error": self.code(),
+            "message": self.to_string(),
+        });
+        warp::reply::with_status(warp::reply::json(&body), StatusCode::BAD_REQUEST).into_response()
+    }
+}
+
+impl Reject for KeyRejection {}
+
+/// Filter extracting a key of type `K` from the next path segment. Segments which are not a
+/// valid key are rejected with [KeyRejection].
+pub fn key_param<K>() -> impl Filter<Extract = (K,), Error = Rejection> + Clone
+where
+    K: FromStr<Err = ParseError> + Send + 'static,
+{
+    warp::path::param::<String>().and_then(|mut segment: String| async move {
+        let result = match percent_decode(&segment) {
+            Some(mut decoded) => {
+                let result = decoded.parse().map_err(KeyRejection::Path);
+                zeroize::Zeroize::zeroize(&mut decoded);
+                result
+            }
+            None => Err(KeyRejection::Segment),
+        };
+        zeroize::Zeroize::zeroize(&mut segment);
+        result.map_err(warp::reject::custom)
+    })
+}
+
+/// Filter extracting a public key from the next path segment.
+pub fn pubkey_param() -> impl Filter<Extract = (Pubkey,), Error = Rejection> + Clone {
+    key_param()
+}
+
+/// Filter extracting a key of type `K` from the named header, which must be present exactly
+/// once. Missing or invalid headers are rejected with [KeyRejection].
+pub fn key_header<K>(name: &'static str) -> impl Filter<Extract = (K,), Error = Rejection> + Clone
+where
+    K: FromStr<Err = ParseError> + Send + 'static,
+{
+    warp::header::headers_cloned().and_then(move |headers: warp::http::HeaderMap| async move {
+        let values = headers
+            .get_all(name)
+            .iter()
+            .map(|value| value.to_str())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| HeaderError::Encoding(name));
+        values
+            .and_then(|values| parse_values(name, values))
+            .map_err(|error| warp::reject::custom(KeyRejection::Header(error)))
+    })
+}
+
+/// Filter extracting a public key from the named header.
+pub fn pubkey_header(
+    name: &'static str,
+) -> impl Filter<Extract = (Pubkey,), Error = Rejection> + Clone {
+    key_header(name)
+}
+
+/// Recover [KeyRejection] into a response using [KeyRejection::to_response]. Other
+/// rejections are passed on.
+pub async fn recover(rejection: Rejection) -> Result<Response, Rejection> {
+    match rejection.find::<KeyRejection>() {
+        Some(error) => Ok(error.to_response()),
+        None => Err(rejection),
+    }
+}
+
+/// Percent-decode path segment, which warp leaves encoded.
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut output = Vec::with_capacity(segment.len());
+    let mut bytes = segment.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            output.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            output.push(byte);
+        }
+    }
+    String::from_utf8(output).ok()
+}
+
+#[test]
+fn test_percent_decode() {
+    assert_eq!(percent_decode("ab%2Bc%2fd%3D").unwrap(), "ab+c/d=");
+    assert!(percent_decode("ab%2").is_none());
+    assert!(percent_decode("ab%zz").is_none());
+    assert!(percent_decode("%ff").is_none());
+}
+
+#[cfg(any(
+    feature = "hex",
+    feature = "base64",
+    feature = "base32",
+    feature = "base58",
+    feature = "proquint"
+))]
+#[test]
+fn test_warp_filters() {
+    let filter = warp::path("peers")
+        .and(pubkey_param())
+        .and(pubkey_header("X-WireGuard-Pubkey"))
+        .map(|pubkey: Pubkey, client: Pubkey| format!("{pubkey} {client}"))
+        .recover(recover);
+    let request = |path: &str, header: Option<&str>| {
+        let mut request = warp::test::request().path(path);
+        if let Some(header) = header {
+            request = request.header("x-wireguard-pubkey", header);
+        }
+        crate::block_on(request.reply(&filter))
+    };
+    // in base64, these contain `+` and `/`, which are not valid base58 and need
+    // percent-encoding in paths
+    let pubkey = Pubkey::new([0xfb; 32]);
+    let client = Pubkey::new([0xfc; 32]);
+    let path = format!(
+        "/peers/{}",
+        pubkey.to_string().replace('+', "%2B").replace('/', "%2F")
+    );
+    let response = request(&path, Some(&client.to_string()));
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body(), &format!("{pubkey} {client}"));
+
+    let response = request(&path, None);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "error": "missing_header",
+            "message": "missing header X-WireGuard-Pubkey",
+        })
+    );
+    let response = request("/peers/abc", Some(&client.to_string()));
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(body["error"], "invalid_key");
+    assert_eq!(request("/other", None).status(), StatusCode::NOT_FOUND);
+}
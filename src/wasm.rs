@@ -0,0 +1,141 @@
+//! JavaScript bindings built with [wasm-bindgen](https://docs.rs/wasm-bindgen), for generating
+//! keys and configurations in the browser with the same implementation as on servers. Keys are
+//! exposed as the classes `PrivateKey`, `PublicKey` and `PresharedKey`, and passed in and out
+//! as base64 strings:
+//!
+//! ```js
+//! import { PrivateKey, PublicKey } from "wireguard-keys";
+//!
+//! const privkey = PrivateKey.generate();
+//! const pubkey = privkey.publicKey().toBase64();
+//! const server = PublicKey.parse("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=");
+//! ```
+//!
+//! To build the module, compile the crate as dynamic library and generate the JavaScript glue
+//! code with the wasm-bindgen command line tool:
+//!
+//! ```sh
+//! cargo rustc --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/wireguard_keys.wasm
+//! ```
+//!
+//! Randomness comes from `crypto.getRandomValues` of the JavaScript environment. Private and
+//! preshared keys are zeroized when calling `free()` on their objects, but the garbage
+//! collector may keep copies of strings returned to JavaScript.
+
+use crate::{Privkey, Pubkey, Secret};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroize;
+
+/// Private key, exported as `PrivateKey`.
+#[wasm_bindgen(js_name = PrivateKey)]
+pub struct JsPrivkey(Privkey);
+
+/// Public key, exported as `PublicKey`.
+#[wasm_bindgen(js_name = PublicKey)]
+pub struct JsPubkey(Pubkey);
+
+/// Preshared key, exported as `PresharedKey`.
+#[wasm_bindgen(js_name = PresharedKey)]
+pub struct JsSecret(Secret);
+
+macro_rules! impl_wasm {
+    ($wrapper:ident, $type:ty, $class:ident) => {
+        #[wasm_bindgen(js_class = $class)]
+        impl $wrapper {
+            /// Decode key from base64.
+            #[wasm_bindgen(js_name = fromBase64)]
+            pub fn from_base64(data: &str) -> Result<$wrapper, JsError> {
+                Ok($wrapper(<$type>::from_base64(data)?))
+            }
+
+            /// Parse key in any enabled encoding, see [Pubkey::parse].
+            pub fn parse(data: &str) -> Result<$wrapper, JsError> {
+                Ok($wrapper(<$type>::parse(data)?))
+            }
+
+            /// Encode key as base64.
+            #[wasm_bindgen(js_name = toBase64)]
+            pub fn to_base64(&self) -> String {
+                self.0.to_base64()
+            }
+
+            /// Compare with another key.
+            pub fn equals(&self, other: &$wrapper) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl From<$type> for $wrapper {
+            fn from(key: $type) -> Self {
+                $wrapper(key)
+            }
+        }
+    };
+}
+
+impl_wasm!(JsPrivkey, Privkey, PrivateKey);
+impl_wasm!(JsPubkey, Pubkey, PublicKey);
+impl_wasm!(JsSecret, Secret, PresharedKey);
+
+#[wasm_bindgen(js_class = PrivateKey)]
+impl JsPrivkey {
+    /// Generate new private key.
+    pub fn generate() -> JsPrivkey {
+        JsPrivkey(Privkey::generate())
+    }
+
+    /// Derive the public key.
+    #[wasm_bindgen(js_name = publicKey)]
+    pub fn pubkey(&self) -> JsPubkey {
+        JsPubkey(self.0.pubkey())
+    }
+
+    /// Check if the public key belongs to this private key.
+    pub fn matches(&self, pubkey: &JsPubkey) -> bool {
+        self.0.matches(&pubkey.0)
+    }
+}
+
+#[wasm_bindgen(js_class = PresharedKey)]
+impl JsSecret {
+    /// Generate new preshared key.
+    pub fn generate() -> JsSecret {
+        JsSecret(Secret::generate())
+    }
+}
+
+impl Drop for JsPrivkey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for JsSecret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+fn test_wasm_keys() {
+    let privkey = JsPrivkey::generate();
+    let pubkey = privkey.pubkey();
+    assert_eq!(pubkey.0, privkey.0.pubkey());
+    assert!(privkey.matches(&pubkey));
+    assert!(!JsPrivkey::generate().matches(&pubkey));
+
+    let parsed = JsPubkey::from_base64(&pubkey.to_base64()).unwrap();
+    assert!(parsed.equals(&pubkey));
+    let parsed = JsPrivkey::parse(&privkey.to_base64()).unwrap();
+    assert!(parsed.equals(&privkey));
+    assert!(parsed.pubkey().equals(&pubkey));
+
+    let secret = JsSecret::generate();
+    assert_eq!(
+        JsSecret::from_base64(&secret.to_base64()).unwrap().0,
+        secret.0
+    );
+    assert!(!secret.equals(&JsSecret::from(Secret::new([0; 32]))));
+}